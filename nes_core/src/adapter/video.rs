@@ -1,3 +1,10 @@
+use crate::entity::frame_buffer::{FrameBuffer, PixelFormat};
+
 pub trait VideoAdapter {
-    fn draw_frame(&mut self, pixels: [[u8; 3]; 256 * 240]);
+    /// Format the PPU renders in, asked once when the console is created.
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::Rgb24
+    }
+
+    fn draw_frame(&mut self, frame: &FrameBuffer);
 }
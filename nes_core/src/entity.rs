@@ -1,7 +1,17 @@
 pub mod apu;
 pub mod cartridge;
+pub mod cdl;
+pub mod cheat;
 pub mod cpu;
+pub mod debug_view;
+pub mod frame_buffer;
 pub mod joypad;
+pub mod movie;
 pub mod nes_file;
 pub mod nes_rgb;
+pub mod overscan;
+pub mod palette;
 pub mod ppu;
+pub mod region;
+pub mod sampler;
+pub mod symbol;
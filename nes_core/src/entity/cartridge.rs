@@ -1,6 +1,9 @@
+use crate::util::crc32::Crc32;
+
 use super::{
     nes_file::{NesFileHeader, INES_MAGIC_NUMBER},
     ppu::VerticalMirroring,
+    region::Region,
 };
 
 pub struct Cartridge {
@@ -15,6 +18,7 @@ pub struct Cartridge {
     pub prg_ram: Vec<u8>,
     pub prg_map: [u32; 4],
     pub chr_map: [u32; 8],
+    pub region: Region,
 }
 
 impl Cartridge {
@@ -28,22 +32,45 @@ impl Cartridge {
         }
     }
 
+    /// Translates a CPU address ($8000~$FFFF) into an offset of `prg_rom`.
     #[cfg_attr(not(debug_assertions), inline(always))]
-    pub fn read_prg(&self, addr: u16) -> u8 {
-        if addr < 0x8000 {
-            return 0;
+    pub fn prg_addr(&self, addr: u16) -> usize {
+        self.prg_map[(addr as usize - 0x8000) / 0x2000] as usize + (addr as usize - 0x8000) % 0x2000
+    }
+
+    /// Translates a PPU address ($0000~$1FFF) into an offset of `chr_rom`.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn chr_addr(&self, addr: u16) -> usize {
+        self.chr_map[addr as usize / 0x400] as usize + (addr as usize) % 0x400
+    }
+
+    /// Translates a CPU address ($6000~$7FFF) into an offset of `prg_ram`, which is
+    /// mirrored when smaller than 8KiB. `None` when the cartridge has no PRG-RAM.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn prg_ram_addr(&self, addr: u16) -> Option<usize> {
+        match addr {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                Some((addr as usize - 0x6000) % self.prg_ram.len())
+            }
+            _ => None,
         }
-        self.prg_rom[self.prg_map[(addr as usize - 0x8000) / 0x2000] as usize
-            + (addr as usize - 0x8000) % 0x2000]
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn read_prg(&self, addr: u16) -> Option<u8> {
+        self.peek_prg(addr)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn read_chr(&self, addr: u16) -> u8 {
-        self.chr_rom[self.chr_map[addr as usize / 0x400] as usize + (addr as usize) % 0x400]
+        self.peek_chr(addr)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
-    pub fn write_prg(&self, _: u16, value: u8) -> u8 {
+    pub fn write_prg(&mut self, addr: u16, value: u8) -> u8 {
+        if let Some(i) = self.prg_ram_addr(addr) {
+            self.prg_ram[i] = value;
+        }
         value
     }
 
@@ -52,6 +79,44 @@ impl Cartridge {
         value
     }
 
+    /// Returns what a read of `addr` would return, without mapper side effects.
+    /// `None` when nothing on the cartridge answers, leaving the CPU bus open.
+    pub fn peek_prg(&self, addr: u16) -> Option<u8> {
+        if addr < 0x8000 {
+            return self.prg_ram_addr(addr).map(|i| self.prg_ram[i]);
+        }
+        Some(self.prg_rom[self.prg_addr(addr)])
+    }
+
+    /// Returns what a read of `addr` would return, without mapper side effects.
+    pub fn peek_chr(&self, addr: u16) -> u8 {
+        self.chr_rom[self.chr_addr(addr)]
+    }
+
+    /// Overwrites PRG-RAM, or patches PRG-ROM at the bank currently mapped to `addr`.
+    pub fn poke_prg(&mut self, addr: u16, value: u8) {
+        if addr < 0x8000 {
+            self.write_prg(addr, value);
+            return;
+        }
+        let i = self.prg_addr(addr);
+        self.prg_rom[i] = value;
+    }
+
+    /// Patches CHR at the bank currently mapped to `addr`.
+    pub fn poke_chr(&mut self, addr: u16, value: u8) {
+        let i = self.chr_addr(addr);
+        self.chr_rom[i] = value;
+    }
+
+    /// CRC-32 of PRG and CHR ROM without the header, the key game databases use.
+    pub fn crc32(&self) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(&self.prg_rom);
+        crc.update(&self.chr_rom);
+        crc.finish()
+    }
+
     pub fn init_prg_map(&mut self) {
         for i in 0..(self.prg_page_kbyte_units as usize / 8) {
             self.prg_map[i] = (0x2000 * i as u32) % self.prg_size;
@@ -0,0 +1,116 @@
+/// PRG flags, bit-compatible with FCEUX `.cdl` files.
+pub const PRG_CODE: u8 = 0x01;
+pub const PRG_DATA: u8 = 0x02;
+/// Bits 2-3: CPU window ($8000/$A000/$C000/$E000) the byte was last accessed through.
+pub const PRG_WINDOW: u8 = 0x0C;
+pub const PRG_INDIRECT_CODE: u8 = 0x10;
+pub const PRG_INDIRECT_DATA: u8 = 0x20;
+pub const PRG_PCM: u8 = 0x40;
+
+/// CHR flags.
+pub const CHR_RENDERED: u8 = 0x01;
+pub const CHR_READ: u8 = 0x02;
+
+/// Code/Data Logger: one flag byte per PRG-ROM and CHR-ROM byte.
+#[derive(Debug, Default)]
+pub struct CodeDataLog {
+    pub enabled: bool,
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+    /// Address of the opcode being executed; reads from it up to PC are code.
+    pub code_start: u16,
+    /// The data read of the current instruction goes through a pointer.
+    pub indirect_data: bool,
+    /// The current instruction was reached by `JMP ($nnnn)`.
+    pub indirect_code: bool,
+    /// Set by `JMP ($nnnn)` for the next `begin_instruction`.
+    pub indirect_jump: bool,
+}
+
+impl CodeDataLog {
+    pub fn new(prg_size: usize, chr_size: usize) -> Self {
+        Self {
+            prg: vec![0; prg_size],
+            chr: vec![0; chr_size],
+            ..Self::default()
+        }
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn begin_instruction(&mut self, pc: u16) {
+        self.code_start = pc;
+        self.indirect_data = false;
+        self.indirect_code = std::mem::take(&mut self.indirect_jump);
+    }
+
+    /// Marks the next instruction as the target of an indirect jump.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn indirect_jump(&mut self) {
+        self.indirect_jump = true;
+    }
+
+    /// Records a CPU read of PRG-ROM. `cpu_addr` picks the window bits,
+    /// `offset` is the byte in `prg_rom` the mapper translated it to.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn log_prg(&mut self, cpu_addr: u16, offset: usize, pc: u16) {
+        let flags = if (self.code_start..pc).contains(&cpu_addr) {
+            PRG_CODE
+                | if self.indirect_code {
+                    PRG_INDIRECT_CODE
+                } else {
+                    0
+                }
+        } else {
+            PRG_DATA
+                | if self.indirect_data {
+                    PRG_INDIRECT_DATA
+                } else {
+                    0
+                }
+        };
+        self.mark_prg(cpu_addr, offset, flags);
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn mark_prg(&mut self, cpu_addr: u16, offset: usize, flags: u8) {
+        if let Some(byte) = self.prg.get_mut(offset) {
+            let window = ((cpu_addr >> 13) & 0x03) as u8;
+            *byte = (*byte & !PRG_WINDOW) | flags | (window << 2);
+        }
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn mark_chr(&mut self, offset: usize, flags: u8) {
+        if let Some(byte) = self.chr.get_mut(offset) {
+            *byte |= flags;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.prg.fill(0);
+        self.chr.fill(0);
+    }
+
+    /// Serializes to the FCEUX layout: the PRG log followed by the CHR log.
+    pub fn save(&self) -> Vec<u8> {
+        let mut file = self.prg.clone();
+        file.extend(&self.chr);
+        file
+    }
+
+    /// Restores a log written by `save` (or FCEUX) for the same ROM.
+    pub fn load(&mut self, file: &[u8]) -> Result<(), String> {
+        if file.len() != self.prg.len() + self.chr.len() {
+            return Err(format!(
+                "cdl size {} does not match PRG {} + CHR {}",
+                file.len(),
+                self.prg.len(),
+                self.chr.len()
+            ));
+        }
+        let (prg, chr) = file.split_at(self.prg.len());
+        self.prg.copy_from_slice(prg);
+        self.chr.copy_from_slice(chr);
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+/// Game Genie alphabet; the index of a letter is its 4bit value.
+const GAME_GENIE_LETTERS: &[u8; 16] = b"APZLGITYEOXUKSVN";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheatKind {
+    /// Replaces a PRG-ROM read ($8000~$FFFF), only when the ROM holds `compare` if given.
+    GameGenie {
+        addr: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+    /// Pro Action Replay: freezes a RAM byte to `value`.
+    RamFreeze { addr: u16, value: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheat {
+    /// Normalized code as entered (upper case, no separators).
+    pub code: String,
+    pub kind: CheatKind,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    /// Parses a Game Genie code (`SXIOPO`, `YEUZUGAA`) or a PAR code (`AAAAVV` in hex, `0077:FF`).
+    /// Six characters that are all hex digits are read as a PAR code.
+    pub fn parse(code: &str) -> Result<Self, String> {
+        let code: String = code
+            .chars()
+            .filter(|c| !matches!(c, '-' | ':' | ' '))
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let kind = match code.len() {
+            6 if code.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                let addr = u16::from_str_radix(&code[0..4], 16).unwrap();
+                let value = u8::from_str_radix(&code[4..6], 16).unwrap();
+                CheatKind::RamFreeze { addr, value }
+            }
+            6 | 8 => decode_game_genie(&code)?,
+            _ => return Err(format!("{}: invalid cheat code length", code)),
+        };
+        Ok(Self {
+            code,
+            kind,
+            enabled: true,
+        })
+    }
+
+    /// Returns what a read of `addr` yields with this cheat, `value` being the real memory.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn apply(&self, addr: u16, value: u8) -> u8 {
+        if !self.enabled {
+            return value;
+        }
+        match self.kind {
+            CheatKind::GameGenie {
+                addr: cheat_addr,
+                value: cheat_value,
+                compare,
+            } if cheat_addr == addr && compare.is_none_or(|c| c == value) => cheat_value,
+            CheatKind::RamFreeze {
+                addr: cheat_addr,
+                value: cheat_value,
+            } if ram_mirror(cheat_addr) == ram_mirror(addr) => cheat_value,
+            _ => value,
+        }
+    }
+}
+
+fn ram_mirror(addr: u16) -> u16 {
+    match addr {
+        0x0000..=0x1FFF => addr % 0x800,
+        _ => addr,
+    }
+}
+
+fn decode_game_genie(code: &str) -> Result<CheatKind, String> {
+    let n = code
+        .bytes()
+        .map(|b| {
+            GAME_GENIE_LETTERS
+                .iter()
+                .position(|l| *l == b)
+                .map(|i| i as u16)
+                .ok_or(format!(
+                    "{}: invalid Game Genie letter '{}'",
+                    code, b as char
+                ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let addr = 0x8000
+        | ((n[3] & 7) << 12)
+        | ((n[5] & 7) << 8)
+        | ((n[4] & 8) << 8)
+        | ((n[2] & 7) << 4)
+        | ((n[1] & 8) << 4)
+        | (n[4] & 7)
+        | (n[3] & 8);
+    let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7);
+    Ok(match n.len() {
+        8 => CheatKind::GameGenie {
+            addr,
+            value: (value | (n[7] & 8)) as u8,
+            compare: Some((((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8)) as u8),
+        },
+        _ => CheatKind::GameGenie {
+            addr,
+            value: (value | (n[5] & 8)) as u8,
+            compare: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _game_genie_6() {
+        assert_eq!(
+            CheatKind::GameGenie {
+                addr: 0x91D9,
+                value: 0xAD,
+                compare: None
+            },
+            Cheat::parse("SXIOPO").unwrap().kind
+        );
+    }
+
+    #[test]
+    fn _game_genie_8() {
+        let cheat = Cheat::parse("yeuz-ugaa").unwrap();
+        assert_eq!("YEUZUGAA", cheat.code);
+        assert_eq!(
+            CheatKind::GameGenie {
+                addr: 0xACB3,
+                value: 0x07,
+                compare: Some(0x00)
+            },
+            cheat.kind
+        );
+        assert_eq!(0x07, cheat.apply(0xACB3, 0x00));
+        assert_eq!(0x01, cheat.apply(0xACB3, 0x01));
+    }
+
+    #[test]
+    fn _ram_freeze() {
+        let mut cheat = Cheat::parse("0075:09").unwrap();
+        assert_eq!(
+            CheatKind::RamFreeze {
+                addr: 0x0075,
+                value: 0x09
+            },
+            cheat.kind
+        );
+        assert_eq!(0x09, cheat.apply(0x0875, 0x03));
+        cheat.enabled = false;
+        assert_eq!(0x03, cheat.apply(0x0075, 0x03));
+    }
+
+    #[test]
+    fn _invalid() {
+        assert!(Cheat::parse("SXIOPQ").is_err());
+        assert!(Cheat::parse("SXIO").is_err());
+    }
+}
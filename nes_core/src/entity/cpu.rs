@@ -1,7 +1,7 @@
 use crate::util::bit::{AsU8, PartialBit};
 
 #[allow(non_snake_case)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Register {
     /// Accumulator
     pub A: u8,
@@ -19,7 +19,7 @@ pub struct Register {
 
 /// 6502 cpu status register
 #[allow(non_snake_case)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StatusRegister {
     /// negative flag (1 when result is negative)
     pub N: bool,
@@ -84,11 +84,23 @@ impl StatusRegister {
 pub type WRam = [u8; 0x800];
 
 #[allow(non_snake_case)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Control {
     pub RST: bool,
+    /// NMI line, asserted by the PPU; the CPU reacts to its rising edge.
     pub NMI: bool,
+    /// IRQ line, level triggered: asserted while any of `irq_sources` is.
     pub IRQ: bool,
+    /// `IrqSource` bits of the devices pulling the IRQ line low.
+    pub irq_sources: u8,
+}
+
+/// Devices sharing the IRQ line, as bits of `Control::irq_sources`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqSource {
+    FrameCounter = 0x01,
+    Dmc = 0x02,
+    Mapper = 0x04,
 }
 
 #[allow(clippy::upper_case_acronyms)]
@@ -0,0 +1,62 @@
+/// RGB24 picture drawn by the PPU viewers, row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugView {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl DebugView {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height * 3],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * self.width + x) * 3;
+        [self.data[i], self.data[i + 1], self.data[i + 2]]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
+        let i = (y * self.width + x) * 3;
+        self.data[i..i + 3].copy_from_slice(&rgb);
+    }
+
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, rgb: [u8; 3]) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.set(x, y, rgb);
+            }
+        }
+    }
+
+    /// Border of a `width`x`height` rectangle at (`x`, `y`), wrapping around the edges.
+    pub fn outline(&mut self, x: usize, y: usize, width: usize, height: usize, rgb: [u8; 3]) {
+        for dx in 0..width {
+            self.set((x + dx) % self.width, y % self.height, rgb);
+            self.set((x + dx) % self.width, (y + height - 1) % self.height, rgb);
+        }
+        for dy in 0..height {
+            self.set(x % self.width, (y + dy) % self.height, rgb);
+            self.set((x + width - 1) % self.width, (y + dy) % self.height, rgb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _outline_wraps() {
+        let mut view = DebugView::new(4, 4);
+        view.outline(3, 3, 2, 2, [1, 2, 3]);
+        for (x, y) in [(3, 3), (0, 3), (3, 0), (0, 0)] {
+            assert_eq!([1, 2, 3], view.get(x, y));
+        }
+        assert_eq!([0, 0, 0], view.get(1, 1));
+    }
+}
@@ -0,0 +1,125 @@
+use super::palette::Palette;
+
+/// Layout the PPU writes pixels in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// R, G, B
+    #[default]
+    Rgb24,
+    /// R, G, B, 0xFF
+    Rgba32,
+    /// B, G, R, 0xFF
+    Bgra32,
+    /// 9bit palette index (`emphasis << 6 | colour`) as a little endian u16.
+    Indexed,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb24 => 3,
+            PixelFormat::Rgba32 | PixelFormat::Bgra32 => 4,
+            PixelFormat::Indexed => 2,
+        }
+    }
+}
+
+/// 256x240 picture, row by row.
+#[derive(Debug, Clone)]
+pub struct FrameBuffer {
+    format: PixelFormat,
+    data: Vec<u8>,
+    /// Palette index of every pixel, whatever the format, for filters that rebuild the video signal.
+    indices: Vec<u16>,
+    /// Colour subcarrier phase of the first pixel in 1/12 cycles; each line starts 4/12 later.
+    pub phase: u8,
+}
+
+impl FrameBuffer {
+    pub const WIDTH: usize = 256;
+    pub const HEIGHT: usize = 240;
+
+    pub fn new(format: PixelFormat) -> Self {
+        Self {
+            format,
+            data: vec![0; Self::WIDTH * Self::HEIGHT * format.bytes_per_pixel()],
+            indices: vec![0; Self::WIDTH * Self::HEIGHT],
+            phase: 0,
+        }
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Raw pixels, `WIDTH * format().bytes_per_pixel()` bytes per row.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Palette index (`emphasis << 6 | colour`) of each pixel.
+    pub fn indices(&self) -> &[u16] {
+        &self.indices
+    }
+
+    /// Subcarrier phase at the first pixel of line `y`, in 1/12 cycles.
+    pub fn line_phase(&self, y: usize) -> u8 {
+        // 341 dots of 8/12 cycle each
+        ((self.phase as usize + y * 341 * 8) % 12) as u8
+    }
+
+    /// Stores pixel `i` (`y * WIDTH + x`) as palette entry `index`.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn set(&mut self, i: usize, index: u16, palette: &Palette) {
+        self.indices[i] = index;
+        let [r, g, b] = palette.rgb[index as usize];
+        match self.format {
+            PixelFormat::Rgb24 => self.data[i * 3..i * 3 + 3].copy_from_slice(&[r, g, b]),
+            PixelFormat::Rgba32 => self.data[i * 4..i * 4 + 4].copy_from_slice(&[r, g, b, 0xFF]),
+            PixelFormat::Bgra32 => self.data[i * 4..i * 4 + 4].copy_from_slice(&[b, g, r, 0xFF]),
+            PixelFormat::Indexed => {
+                self.data[i * 2..i * 2 + 2].copy_from_slice(&index.to_le_bytes())
+            }
+        }
+    }
+
+    /// Colour at (`x`, `y`) whatever the format; `palette` resolves indexed pixels.
+    pub fn rgb(&self, x: usize, y: usize, palette: &Palette) -> [u8; 3] {
+        let i = y * Self::WIDTH + x;
+        let d = &self.data;
+        match self.format {
+            PixelFormat::Rgb24 => [d[i * 3], d[i * 3 + 1], d[i * 3 + 2]],
+            PixelFormat::Rgba32 => [d[i * 4], d[i * 4 + 1], d[i * 4 + 2]],
+            PixelFormat::Bgra32 => [d[i * 4 + 2], d[i * 4 + 1], d[i * 4]],
+            PixelFormat::Indexed => {
+                palette.rgb[u16::from_le_bytes([d[i * 2], d[i * 2 + 1]]) as usize & 0x1FF]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _set_in_every_format() {
+        let palette = Palette::default();
+        let index = 1 << 6 | 0x16;
+        let [r, g, b] = palette.rgb[index];
+        for (format, bytes) in [
+            (PixelFormat::Rgb24, vec![r, g, b]),
+            (PixelFormat::Rgba32, vec![r, g, b, 0xFF]),
+            (PixelFormat::Bgra32, vec![b, g, r, 0xFF]),
+            (PixelFormat::Indexed, vec![0x56, 0x00]),
+        ] {
+            let mut frame = FrameBuffer::new(format);
+            frame.set(257, index as u16, &palette);
+            let n = format.bytes_per_pixel();
+            assert_eq!(256 * 240 * n, frame.data().len());
+            assert_eq!(&bytes[..], &frame.data()[257 * n..258 * n]);
+            assert_eq!([r, g, b], frame.rgb(1, 1, &palette));
+            assert_eq!(index as u16, frame.indices()[257]);
+        }
+    }
+}
@@ -25,4 +25,18 @@ impl JoyPadBtnState {
             | (self.B.as_u8() << 1)
             | self.A.as_u8()
     }
+
+    /// The reverse of `get_u8`.
+    pub fn from_u8(buttons: u8) -> Self {
+        Self {
+            A: buttons & 0x01 != 0,
+            B: buttons & 0x02 != 0,
+            SELECT: buttons & 0x04 != 0,
+            START: buttons & 0x08 != 0,
+            UP: buttons & 0x10 != 0,
+            DOWN: buttons & 0x20 != 0,
+            LEFT: buttons & 0x40 != 0,
+            RIGHT: buttons & 0x80 != 0,
+        }
+    }
 }
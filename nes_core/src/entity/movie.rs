@@ -0,0 +1,133 @@
+use std::str::FromStr;
+
+/// Input of one frame of a movie.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MovieFrame {
+    /// FM2 command bits: 1 soft reset, 2 power cycle. The rest (FDS, VS coins) is ignored.
+    pub commands: u8,
+    /// Buttons of both controllers in the bit order of `JoyPadBtnState::get_u8`.
+    pub ports: [u8; 2],
+}
+
+impl MovieFrame {
+    pub fn soft_reset(&self) -> bool {
+        self.commands & 1 != 0
+    }
+
+    pub fn power_cycle(&self) -> bool {
+        self.commands & 2 != 0
+    }
+}
+
+/// Controller input recorded frame by frame, read from an FCEUX `.fm2` file. The movie must
+/// start at power on; movies from a savestate, in binary form or for the Four Score or
+/// the Zapper are rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Movie {
+    /// The movie was made on a PAL console.
+    pub pal: bool,
+    pub frames: Vec<MovieFrame>,
+}
+
+impl FromStr for Movie {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut movie = Self::default();
+        for (n, line) in s.lines().enumerate() {
+            let error = |e: String| format!("line {}: {}", n + 1, e);
+            if line.starts_with('|') {
+                movie.frames.push(parse_frame(line).map_err(error)?);
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let unsupported = match (key, value.trim()) {
+                ("palFlag", value) => {
+                    movie.pal = value == "1";
+                    None
+                }
+                ("binary", "1") => Some("binary movies"),
+                ("fourscore", "1") => Some("Four Score movies"),
+                ("port0" | "port1", value) if value != "0" && value != "1" => {
+                    Some("devices other than the standard controller")
+                }
+                ("port2", value) if value != "0" => Some("expansion port devices"),
+                ("savestate", _) => Some("movies starting from a savestate"),
+                _ => None,
+            };
+            if let Some(what) = unsupported {
+                return Err(error(format!("{} are not supported", what)));
+            }
+        }
+        Ok(movie)
+    }
+}
+
+/// `|commands|RLDUTSBA|RLDUTSBA|port2|`, where any character other than ' ' and '.' is a
+/// pressed button. An unplugged controller has an empty field.
+fn parse_frame(line: &str) -> Result<MovieFrame, String> {
+    let mut fields = line.split('|').skip(1);
+    let commands = fields.next().unwrap_or_default().trim();
+    let commands = commands
+        .parse()
+        .map_err(|_| format!("invalid commands {}", commands))?;
+    let mut ports = [0; 2];
+    for port in &mut ports {
+        let buttons = fields.next().unwrap_or_default();
+        match buttons.len() {
+            0 => {}
+            8 => {
+                for (i, c) in buttons.chars().enumerate() {
+                    if c != ' ' && c != '.' {
+                        *port |= 0x80 >> i;
+                    }
+                }
+            }
+            _ => return Err(format!("invalid controller input {}", buttons)),
+        }
+    }
+    Ok(MovieFrame { commands, ports })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse() {
+        let movie: Movie = "version 3\n\
+                            romFilename helloworld\n\
+                            palFlag 0\n\
+                            port0 1\n\
+                            port1 1\n\
+                            port2 0\n\
+                            |1|........|........||\n\
+                            |0|R......A|.L..T...||\n\
+                            |0|...U.SB.|........||\n"
+            .parse()
+            .unwrap();
+        assert!(!movie.pal);
+        assert_eq!(3, movie.frames.len());
+        assert!(movie.frames[0].soft_reset());
+        assert!(!movie.frames[0].power_cycle());
+        assert_eq!([0x81, 0x48], movie.frames[1].ports);
+        assert_eq!([0x16, 0], movie.frames[2].ports);
+    }
+
+    #[test]
+    fn _unplugged_controller() {
+        let movie: Movie = "port1 0\n|2|..D.....|||\n".parse().unwrap();
+        assert!(movie.frames[0].power_cycle());
+        assert_eq!([0x20, 0], movie.frames[0].ports);
+    }
+
+    #[test]
+    fn _unsupported() {
+        assert!("binary 1".parse::<Movie>().is_err());
+        assert!("fourscore 1".parse::<Movie>().is_err());
+        assert!("port0 2".parse::<Movie>().is_err());
+        assert!("savestate base64:AAAA".parse::<Movie>().is_err());
+        assert!("|x|........|........||".parse::<Movie>().is_err());
+        assert!("|0|...|........||".parse::<Movie>().is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::util::{bit::PartialBit, vec::Slice};
 
-use super::{cartridge::Cartridge, ppu::VerticalMirroring};
+use super::{cartridge::Cartridge, ppu::VerticalMirroring, region::Region};
 
 /// magic number os .nes file
 /// "NES<EOF>"
@@ -75,6 +75,7 @@ impl NesFileHeader {
             prg_ram: vec![0; header.prm_ram_size_in_8kbyte_units as usize * 0x2000],
             prg_map: [0; 4],
             chr_map: [0; 8],
+            region: Region::from_header(&head),
         };
 
         cartridge.init_prg_map();
@@ -1,3 +1,4 @@
+/// 2C02 palette without emphasis.
 pub const NES_RGB: [[u8; 3]; 64] = [
     [0x7C, 0x7C, 0x7C],
     [0x00, 0x00, 0xFC],
@@ -64,3 +65,53 @@ pub const NES_RGB: [[u8; 3]; 64] = [
     [0x00, 0x00, 0x00],
     [0x00, 0x00, 0x00],
 ];
+
+/// Level each emphasis bit leaves the other two channels at, in 1/1000.
+const EMPHASIS_ATTENUATION: u32 = 746;
+
+/// `NES_RGB` for all 8 emphasis combinations, indexed by `emphasis << 6 | colour`.
+pub const NES_RGB_EMPHASIS: [[u8; 3]; 512] = with_emphasis(&NES_RGB);
+
+/// Expands a 64 colour palette to 512 entries. Each emphasis bit (bit0 = red, bit1 = green,
+/// bit2 = blue) darkens the channels it does not emphasize.
+pub const fn with_emphasis(base: &[[u8; 3]; 64]) -> [[u8; 3]; 512] {
+    let mut palette = [[0; 3]; 512];
+    let mut i = 0;
+    while i < 512 {
+        let emphasis = i >> 6;
+        let colour = base[i & 0x3F];
+        let mut c = 0;
+        while c < 3 {
+            let mut level = colour[c] as u32;
+            let mut bit = 0;
+            while bit < 3 {
+                if emphasis & (1 << bit) != 0 && bit != c {
+                    level = level * EMPHASIS_ATTENUATION / 1000;
+                }
+                bit += 1;
+            }
+            palette[i][c] = level as u8;
+            c += 1;
+        }
+        i += 1;
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _no_emphasis_is_base_palette() {
+        assert_eq!(NES_RGB[..], NES_RGB_EMPHASIS[..64]);
+    }
+
+    #[test]
+    fn _emphasis_darkens_other_channels() {
+        // $30 white with red emphasis
+        assert_eq!([0xFC, 0xBB, 0xBB], NES_RGB_EMPHASIS[1 << 6 | 0x30]);
+        // with every bit set each channel is darkened twice
+        assert_eq!([0x8B, 0x8B, 0x8B], NES_RGB_EMPHASIS[7 << 6 | 0x30]);
+    }
+}
@@ -0,0 +1,159 @@
+use std::str::FromStr;
+
+use super::frame_buffer::FrameBuffer;
+
+/// Rows and columns hidden at each edge of the 256x240 picture, in NES pixels.
+/// TVs never showed the whole picture, and some games leave garbage near the edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overscan {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl FromStr for Overscan {
+    type Err = String;
+
+    /// "none", "ntsc" (8 lines at the top and bottom), or "top,bottom,left,right".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => return Ok(Self::NONE),
+            "ntsc" => return Ok(Self::NTSC),
+            _ => {}
+        }
+        let sides = s
+            .split(',')
+            .map(|n| n.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid overscan {}", s))?;
+        let [top, bottom, left, right] = sides[..] else {
+            return Err(format!("overscan needs top,bottom,left,right: {}", s));
+        };
+        let overscan = Self {
+            top,
+            bottom,
+            left,
+            right,
+        };
+        if top + bottom >= FrameBuffer::HEIGHT || left + right >= FrameBuffer::WIDTH {
+            return Err(format!("overscan {} hides the whole picture", s));
+        }
+        Ok(overscan)
+    }
+}
+
+impl Overscan {
+    pub const NONE: Self = Self {
+        top: 0,
+        bottom: 0,
+        left: 0,
+        right: 0,
+    };
+    pub const NTSC: Self = Self {
+        top: 8,
+        bottom: 8,
+        left: 0,
+        right: 0,
+    };
+
+    /// Size of the visible area in NES pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (
+            FrameBuffer::WIDTH - self.left - self.right,
+            FrameBuffer::HEIGHT - self.top - self.bottom,
+        )
+    }
+
+    /// Visible area `(x, y, width, height)` of a `width`x`height` picture made from a frame,
+    /// e.g. after a filter scaled it.
+    pub fn rect(&self, width: usize, height: usize) -> (usize, usize, usize, usize) {
+        let x = |n: usize| (n * width + FrameBuffer::WIDTH / 2) / FrameBuffer::WIDTH;
+        let y = |n: usize| (n * height + FrameBuffer::HEIGHT / 2) / FrameBuffer::HEIGHT;
+        let (left, top) = (x(self.left), y(self.top));
+        (
+            left,
+            top,
+            width - x(self.right) - left,
+            height - y(self.bottom) - top,
+        )
+    }
+}
+
+/// Per-game overscan, read from lines of `CRC32 top,bottom,left,right` where the CRC
+/// is the one of `Cartridge::crc32`. `#` starts a comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverscanOverrides {
+    games: Vec<(u32, Overscan)>,
+}
+
+impl FromStr for OverscanOverrides {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut games = Vec::new();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |e: String| format!("line {}: {}", n + 1, e);
+            let (crc, overscan) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| error(format!("expected CRC32 and overscan: {}", line)))?;
+            let crc = u32::from_str_radix(crc, 16)
+                .map_err(|_| error(format!("invalid CRC32 {}", crc)))?;
+            games.push((crc, overscan.trim().parse().map_err(error)?));
+        }
+        Ok(Self { games })
+    }
+}
+
+impl OverscanOverrides {
+    pub fn get(&self, crc32: u32) -> Option<Overscan> {
+        self.games
+            .iter()
+            .find(|&&(crc, _)| crc == crc32)
+            .map(|&(_, overscan)| overscan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse() {
+        assert_eq!(Ok(Overscan::NTSC), "NTSC".parse());
+        assert_eq!(Ok(Overscan::NONE), "none".parse());
+        let overscan: Overscan = "8, 8, 8, 0".parse().unwrap();
+        assert_eq!((248, 224), overscan.size());
+        assert!("8,8".parse::<Overscan>().is_err());
+        assert!("120,120,0,0".parse::<Overscan>().is_err());
+    }
+
+    #[test]
+    fn _rect_scales_with_the_picture() {
+        let overscan: Overscan = "8,8,8,0".parse().unwrap();
+        assert_eq!((8, 8, 248, 224), overscan.rect(256, 240));
+        assert_eq!((24, 24, 744, 672), overscan.rect(768, 720));
+        // NTSC filter output, 602 wide
+        assert_eq!((19, 8, 583, 224), overscan.rect(602, 240));
+        assert_eq!((0, 0, 256, 240), Overscan::NONE.rect(256, 240));
+    }
+
+    #[test]
+    fn _overrides() {
+        let overrides: OverscanOverrides = "# game  top,bottom,left,right\n\
+                                            1A2B3C4D 8,8,8,0\n\
+                                            \n\
+                                            deadbeef 0,16,0,0 # status bar garbage\n"
+            .parse()
+            .unwrap();
+        assert_eq!(Some("8,8,8,0".parse().unwrap()), overrides.get(0x1A2B3C4D));
+        assert_eq!(Some("0,16,0,0".parse().unwrap()), overrides.get(0xDEADBEEF));
+        assert_eq!(None, overrides.get(0));
+        assert!("1A2B3C4D".parse::<OverscanOverrides>().is_err());
+        assert!("xyz 8,8,0,0".parse::<OverscanOverrides>().is_err());
+    }
+}
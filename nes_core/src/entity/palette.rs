@@ -0,0 +1,277 @@
+use std::{f64::consts::PI, str::FromStr};
+
+use super::nes_rgb::{with_emphasis, NES_RGB_EMPHASIS};
+
+/// RGB colour for each of the 64 PPU colours in all 8 emphasis combinations,
+/// indexed by `emphasis << 6 | colour`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub rgb: [[u8; 3]; 512],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            rgb: NES_RGB_EMPHASIS,
+        }
+    }
+}
+
+impl Palette {
+    /// Reads a `.pal` file: 64 colours (192 bytes), emphasis is then derived from them,
+    /// or 512 colours (1536 bytes) with emphasis included.
+    pub fn from_pal(bytes: &[u8]) -> Result<Self, String> {
+        let mut colours = bytes.chunks_exact(3).map(|c| [c[0], c[1], c[2]]);
+        match bytes.len() {
+            192 => {
+                let mut base = [[0; 3]; 64];
+                base.fill_with(|| colours.next().unwrap());
+                Ok(Self {
+                    rgb: with_emphasis(&base),
+                })
+            }
+            1536 => {
+                let mut rgb = [[0; 3]; 512];
+                rgb.fill_with(|| colours.next().unwrap());
+                Ok(Self { rgb })
+            }
+            len => Err(format!(
+                "palette must be 192 or 1536 bytes, got {} bytes",
+                len
+            )),
+        }
+    }
+
+    /// Writes the palette as a 1536 byte `.pal` file.
+    pub fn to_pal(&self) -> Vec<u8> {
+        self.rgb.iter().flatten().copied().collect()
+    }
+
+    /// Decodes every colour from the composite signal the 2C02 generates.
+    pub fn from_ntsc(params: &NtscParams) -> Self {
+        let mut rgb = [[0; 3]; 512];
+        for (pixel, rgb) in rgb.iter_mut().enumerate() {
+            *rgb = params.decode(pixel);
+        }
+        Self { rgb }
+    }
+}
+
+/// Signal levels of the 2C02, relative to sync.
+const BLACK: f64 = 0.518;
+const WHITE: f64 = 1.962;
+const ATTENUATION: f64 = 0.746;
+/// Square wave low levels for luma 0..3, followed by the high levels.
+const LEVELS: [f64; 8] = [0.350, 0.518, 0.962, 1.550, 1.094, 1.506, 1.962, 1.962];
+
+/// TV adjustments applied when decoding the composite signal (Bisqwit's generator).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtscParams {
+    /// Hue rotation in degrees.
+    pub hue: f64,
+    pub saturation: f64,
+    pub contrast: f64,
+    pub brightness: f64,
+    /// Gamma the palette is made for; 2.2 leaves the signal linear.
+    pub gamma: f64,
+}
+
+impl Default for NtscParams {
+    fn default() -> Self {
+        Self {
+            hue: 0.0,
+            saturation: 1.0,
+            contrast: 1.0,
+            brightness: 1.0,
+            gamma: 1.8,
+        }
+    }
+}
+
+impl FromStr for NtscParams {
+    type Err = String;
+
+    /// "hue=15,saturation=1.2": any of hue, saturation, contrast, brightness and gamma,
+    /// the others keep their defaults.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = Self::default();
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("invalid NTSC setting {}", setting))?;
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid NTSC setting {}", setting))?;
+            let field = match name.trim().to_ascii_lowercase().as_str() {
+                "hue" => &mut params.hue,
+                "saturation" => &mut params.saturation,
+                "contrast" => &mut params.contrast,
+                "brightness" => &mut params.brightness,
+                "gamma" => &mut params.gamma,
+                _ => return Err(format!("unknown NTSC setting {}", name)),
+            };
+            *field = value;
+        }
+        Ok(params)
+    }
+}
+
+impl NtscParams {
+    /// Composite level of `pixel` (emphasis << 6 | colour) at one of the 12 subcarrier phases,
+    /// scaled so that black is 0 and white is 1.
+    pub fn signal(pixel: usize, phase: usize) -> f64 {
+        let in_colour_phase = |colour: usize| (colour + phase + 8) % 12 < 6;
+        let colour = pixel & 0x0F;
+        let emphasis = pixel >> 6;
+        // $xE/$xF output the $1D level
+        let level = if colour > 13 { 1 } else { (pixel >> 4) & 3 };
+        let (low, high) = match colour {
+            0 => (LEVELS[4 + level], LEVELS[4 + level]),
+            13.. => (LEVELS[level], LEVELS[level]),
+            _ => (LEVELS[level], LEVELS[4 + level]),
+        };
+        let signal = if in_colour_phase(colour) { high } else { low };
+        let signal = if (emphasis & 1 != 0 && in_colour_phase(0))
+            || (emphasis & 2 != 0 && in_colour_phase(4))
+            || (emphasis & 4 != 0 && in_colour_phase(8))
+        {
+            signal * ATTENUATION
+        } else {
+            signal
+        };
+        (signal - BLACK) / (WHITE - BLACK)
+    }
+
+    /// Angle of the subcarrier at `phase` relative to the colour burst, with the hue adjustment.
+    pub fn angle(&self, phase: usize) -> f64 {
+        PI * (phase as f64 + self.hue / 30.0) / 6.0
+    }
+
+    /// Applies the TV adjustments to a demodulated YIQ colour.
+    pub fn to_rgb(&self, y: f64, i: f64, q: f64) -> [u8; 3] {
+        let y = y * self.contrast + self.brightness - 1.0;
+        let i = i * self.saturation * self.contrast;
+        let q = q * self.saturation * self.contrast;
+        let gamma = |f: f64| {
+            let f = if f <= 0.0 {
+                0.0
+            } else {
+                f.powf(2.2 / self.gamma)
+            };
+            (f * 255.0).round().clamp(0.0, 255.0) as u8
+        };
+        [
+            gamma(y + 0.946882 * i + 0.623557 * q),
+            gamma(y - 0.274788 * i - 0.635691 * q),
+            gamma(y - 1.108545 * i + 1.709007 * q),
+        ]
+    }
+
+    fn decode(&self, pixel: usize) -> [u8; 3] {
+        let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+        for phase in 0..12 {
+            let v = Self::signal(pixel, phase);
+            let angle = self.angle(phase);
+            y += v;
+            i += v * angle.cos();
+            q += v * angle.sin();
+        }
+        self.to_rgb(y / 12.0, i / 12.0, q / 12.0)
+    }
+}
+
+/// Built-in palettes, selectable by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PalettePreset {
+    /// The `NES_RGB` table.
+    #[default]
+    Aries,
+    /// Decoded from the composite signal with the `NtscParams` defaults.
+    Ntsc,
+}
+
+impl FromStr for PalettePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aries" => Ok(PalettePreset::Aries),
+            "ntsc" => Ok(PalettePreset::Ntsc),
+            _ => Err(format!("unknown palette {}", s)),
+        }
+    }
+}
+
+impl PalettePreset {
+    pub fn palette(self) -> Palette {
+        match self {
+            PalettePreset::Aries => Palette::default(),
+            PalettePreset::Ntsc => Palette::from_ntsc(&NtscParams::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _from_pal() {
+        let mut bytes = vec![0; 192];
+        bytes[0x30 * 3..0x30 * 3 + 3].copy_from_slice(&[0xFF, 0xFF, 0xFF]);
+        let palette = Palette::from_pal(&bytes).unwrap();
+        assert_eq!([0xFF, 0xFF, 0xFF], palette.rgb[0x30]);
+        assert_eq!([0xFF, 0xBE, 0xBE], palette.rgb[1 << 6 | 0x30]);
+
+        let mut bytes = vec![0; 1536];
+        bytes[1535] = 0x12;
+        let palette = Palette::from_pal(&bytes).unwrap();
+        assert_eq!([0, 0, 0x12], palette.rgb[511]);
+        assert_eq!(bytes, palette.to_pal());
+
+        assert!(Palette::from_pal(&[0; 191]).is_err());
+    }
+
+    #[test]
+    fn _ntsc_hues() {
+        let rgb = PalettePreset::Ntsc.palette().rgb;
+        let dominant = |c: [u8; 3]| (0..3).max_by_key(|&i| c[i]).unwrap();
+        // $x6 red, $xA green, $x2 blue
+        assert_eq!(0, dominant(rgb[0x16]));
+        assert_eq!(1, dominant(rgb[0x1A]));
+        assert_eq!(2, dominant(rgb[0x12]));
+        assert_eq!([0xFF, 0xFF, 0xFF], rgb[0x20]);
+        assert_eq!([0, 0, 0], rgb[0x0F]);
+        // gray columns carry no chroma
+        let [r, g, b] = rgb[0x00];
+        assert!(r == g && g == b);
+    }
+
+    #[test]
+    fn _ntsc_emphasis_darkens() {
+        let rgb = PalettePreset::Ntsc.palette().rgb;
+        let [r, g, b] = rgb[1 << 6 | 0x30];
+        assert!(r > g && r > b);
+        let sum = |c: [u8; 3]| c.iter().map(|&v| v as u32).sum::<u32>();
+        assert!(sum(rgb[7 << 6 | 0x30]) < sum(rgb[0x30]));
+    }
+
+    #[test]
+    fn _params_from_str() {
+        let params: NtscParams = "hue=-15, Saturation=1.2".parse().unwrap();
+        assert_eq!(-15.0, params.hue);
+        assert_eq!(1.2, params.saturation);
+        assert_eq!(NtscParams::default().gamma, params.gamma);
+        assert_eq!(Ok(NtscParams::default()), "".parse());
+        assert!("tint=1".parse::<NtscParams>().is_err());
+        assert!("hue".parse::<NtscParams>().is_err());
+    }
+
+    #[test]
+    fn _preset_from_str() {
+        assert_eq!(Ok(PalettePreset::Ntsc), "NTSC".parse());
+        assert!("sepia".parse::<PalettePreset>().is_err());
+        assert_eq!(Palette::default(), PalettePreset::Aries.palette());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::util::bit::{AsU8, PartialBit};
+use crate::util::bit::{AsU16, AsU8, PartialBit};
 
 pub type VerticalMirroring = bool;
 
@@ -14,7 +14,7 @@ pub type Oam = [u8; 0x100];
 // type SecondaryOam = [Sprite; 8];
 
 /// sprite
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Sprite {
     pub x: u8,
     pub attr: u8,
@@ -22,6 +22,31 @@ pub struct Sprite {
     pub y: u8,
 }
 
+impl Sprite {
+    /// Sprite `n` (0~63) of OAM.
+    pub fn from_oam(oam: &Oam, n: usize) -> Self {
+        let [y, tile, attr, x] = [0, 1, 2, 3].map(|m| oam[n * 4 + m]);
+        Self { x, attr, tile, y }
+    }
+
+    /// Sprite palette (0~3).
+    pub fn palette(&self) -> u8 {
+        self.attr & 3
+    }
+
+    pub fn behind_background(&self) -> bool {
+        self.attr.bit_flag(5)
+    }
+
+    pub fn flip_h(&self) -> bool {
+        self.attr.bit_flag(6)
+    }
+
+    pub fn flip_v(&self) -> bool {
+        self.attr.bit_flag(7)
+    }
+}
+
 #[allow(non_snake_case)]
 #[derive(Debug, Default)]
 pub struct Register {
@@ -38,9 +63,12 @@ pub struct Register {
 
 #[derive(Debug, Default)]
 pub struct BusLatch {
+    /// I/O latch, returned for the bits a register read does not drive.
     pub result: u8,
     pub buffer: u8,
     pub strobe: bool,
+    /// Frame each bit of `result` was last driven; stale bits decay to 0.
+    pub refreshed: [u64; 8],
 }
 
 #[derive(Debug, Default)]
@@ -95,14 +123,29 @@ pub struct PpuMask {
 impl PpuMask {
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn set_u8(&mut self, value: u8) {
-        self.gray = value.bit_flag(7);
-        self.bg_left = value.bit_flag(6);
-        self.spr_left = value.bit_flag(5);
-        self.bg = value.bit_flag(4);
-        self.spr = value.bit_flag(3);
-        self.red = value.bit_flag(2);
-        self.green = value.bit_flag(1);
-        self.blue = value.bit_flag(0);
+        self.blue = value.bit_flag(7);
+        self.green = value.bit_flag(6);
+        self.red = value.bit_flag(5);
+        self.spr = value.bit_flag(4);
+        self.bg = value.bit_flag(3);
+        self.spr_left = value.bit_flag(2);
+        self.bg_left = value.bit_flag(1);
+        self.gray = value.bit_flag(0);
+    }
+
+    /// Emphasis bits as an index into the 512 entry palette (bit0 = red, bit1 = green, bit2 = blue).
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn emphasis(&self) -> u16 {
+        self.red.as_u16() | (self.green.as_u16() << 1) | (self.blue.as_u16() << 2)
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn gray_mask(&self) -> u8 {
+        if self.gray {
+            0x30
+        } else {
+            0x3F
+        }
     }
 }
 
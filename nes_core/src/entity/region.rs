@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+/// TV system the console was built for. It sets the clock ratio and frame layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    /// Famiclone timing: PAL frame layout with NTSC clock ratio and APU tables.
+    Dendy,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ntsc" => Ok(Region::Ntsc),
+            "pal" => Ok(Region::Pal),
+            "dendy" => Ok(Region::Dendy),
+            _ => Err(format!("unknown region {}", s)),
+        }
+    }
+}
+
+const NOISE_PERIODS_NTSC: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const NOISE_PERIODS_PAL: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+const DMC_RATES_NTSC: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+const DMC_RATES_PAL: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+const FRAME_COUNTER_STEPS_NTSC: [u16; 5] = [7457, 14913, 22371, 29829, 37281];
+const FRAME_COUNTER_STEPS_PAL: [u16; 5] = [8313, 16627, 24939, 33253, 41565];
+
+impl Region {
+    /// Reads the TV system from an iNES header: NES 2.0 byte 12, or iNES byte 9 bit 0.
+    pub fn from_header(header: &[u8]) -> Self {
+        let is_nes2 = header[7] & 0x0C == 0x08;
+        if is_nes2 {
+            match header[12] & 0x03 {
+                1 => Region::Pal,
+                3 => Region::Dendy,
+                // 2 is multi-region, which runs fine as NTSC
+                _ => Region::Ntsc,
+            }
+        } else if header[9] & 0x01 == 0x01 {
+            Region::Pal
+        } else {
+            Region::Ntsc
+        }
+    }
+
+    /// Guesses the TV system from GoodNES/No-Intro tags in a file name,
+    /// e.g. `Game (E).nes` or `Game (Europe).nes`.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let name = file_name.to_ascii_lowercase();
+        if name.contains("(dendy)") {
+            Some(Region::Dendy)
+        } else if ["(e)", "(europe)", "(pal)", "(a)", "(australia)"]
+            .iter()
+            .any(|tag| name.contains(tag))
+        {
+            Some(Region::Pal)
+        } else if ["(u)", "(usa)", "(j)", "(japan)", "(ntsc)"]
+            .iter()
+            .any(|tag| name.contains(tag))
+        {
+            Some(Region::Ntsc)
+        } else {
+            None
+        }
+    }
+
+    /// PPU dots per 5 CPU cycles (3 or 3.2 dots per cycle).
+    pub fn ppu_dots_per_5_cycles(self) -> u8 {
+        match self {
+            Region::Ntsc | Region::Dendy => 15,
+            Region::Pal => 16,
+        }
+    }
+
+    pub fn scanlines(self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// Scanline where vblank starts and NMI fires.
+    pub fn vblank_scanline(self) -> u16 {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            // Dendy idles for 51 lines after the picture before vblank
+            Region::Dendy => 291,
+        }
+    }
+
+    pub fn pre_render_scanline(self) -> u16 {
+        self.scanlines() - 1
+    }
+
+    /// Only NTSC skips a dot on odd frames while rendering.
+    pub fn skips_odd_frame_dot(self) -> bool {
+        self == Region::Ntsc
+    }
+
+    /// The 2C07 and its clones wire the red and green emphasis bits of PPUMASK the other way round.
+    pub fn swaps_red_green_emphasis(self) -> bool {
+        self != Region::Ntsc
+    }
+
+    pub fn cpu_clock_hz(self) -> u32 {
+        match self {
+            Region::Ntsc => 1_789_773,
+            Region::Pal => 1_662_607,
+            Region::Dendy => 1_773_448,
+        }
+    }
+
+    pub fn frame_rate(self) -> f64 {
+        match self {
+            Region::Ntsc => 60.0988,
+            Region::Pal | Region::Dendy => 50.0070,
+        }
+    }
+
+    /// Exact `frame_rate` as numerator and denominator, from the master clock and the
+    /// PPU dots of a frame (89341.5 for NTSC, which skips a dot every other frame).
+    pub fn frame_rate_ratio(self) -> (u32, u32) {
+        match self {
+            Region::Ntsc => (39_375_000, 655_171),
+            Region::Pal | Region::Dendy => (322_445, 6_448),
+        }
+    }
+
+    /// Noise channel timer periods in CPU cycles.
+    pub fn noise_periods(self) -> &'static [u16; 16] {
+        match self {
+            Region::Pal => &NOISE_PERIODS_PAL,
+            Region::Ntsc | Region::Dendy => &NOISE_PERIODS_NTSC,
+        }
+    }
+
+    /// DMC rates in CPU cycles per output bit.
+    pub fn dmc_rates(self) -> &'static [u16; 16] {
+        match self {
+            Region::Pal => &DMC_RATES_PAL,
+            Region::Ntsc | Region::Dendy => &DMC_RATES_NTSC,
+        }
+    }
+
+    /// CPU cycles of the frame counter steps: the first four end the 4-step sequence,
+    /// and the 5-step sequence replaces the fourth with the fifth.
+    pub fn frame_counter_steps(self) -> &'static [u16; 5] {
+        match self {
+            Region::Pal => &FRAME_COUNTER_STEPS_PAL,
+            Region::Ntsc | Region::Dendy => &FRAME_COUNTER_STEPS_NTSC,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _from_header() {
+        let mut header = [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(Region::Ntsc, Region::from_header(&header));
+        header[9] = 1;
+        assert_eq!(Region::Pal, Region::from_header(&header));
+        header[7] = 0x08;
+        header[12] = 3;
+        assert_eq!(Region::Dendy, Region::from_header(&header));
+        header[12] = 2;
+        assert_eq!(Region::Ntsc, Region::from_header(&header));
+    }
+
+    #[test]
+    fn _from_file_name() {
+        assert_eq!(
+            Some(Region::Pal),
+            Region::from_file_name("Elite (E) [!].nes")
+        );
+        assert_eq!(Some(Region::Ntsc), Region::from_file_name("Game (USA).nes"));
+        assert_eq!(None, Region::from_file_name("helloworld.nes"));
+    }
+
+    #[test]
+    fn _frame_rate_ratio() {
+        for region in [Region::Ntsc, Region::Pal, Region::Dendy] {
+            let (num, den) = region.frame_rate_ratio();
+            assert!((num as f64 / den as f64 - region.frame_rate()).abs() < 0.0001);
+        }
+    }
+}
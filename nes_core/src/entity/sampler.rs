@@ -0,0 +1,93 @@
+use std::f32::consts::PI;
+
+/// Cutoff of the high-pass filter after the mixer, which removes the DC offset like the
+/// capacitors of the console do.
+const HIGH_PASS_HZ: f32 = 90.0;
+
+/// Turns the mixer output, which changes every CPU cycle, into 16bit PCM at `rate` Hz.
+/// A sample is the average of the cycles it covers, so the number of samples follows the
+/// emulated clock: a frame of N cycles always gives N * rate / clock_hz samples.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    pub rate: u32,
+    clock_hz: u32,
+    /// Progress towards the next sample, in units of 1 / (rate * clock_hz) s.
+    phase: u32,
+    sum: f32,
+    count: u32,
+    /// Previous input and output of the high-pass filter.
+    prev_in: f32,
+    prev_out: f32,
+    alpha: f32,
+    samples: Vec<i16>,
+}
+
+impl Sampler {
+    /// `rate` is clamped to `clock_hz`, at most one sample per cycle.
+    pub fn new(rate: u32, clock_hz: u32) -> Self {
+        let rate = rate.clamp(1, clock_hz);
+        let rc = 1.0 / (2.0 * PI * HIGH_PASS_HZ);
+        Self {
+            rate,
+            clock_hz,
+            phase: 0,
+            sum: 0.0,
+            count: 0,
+            prev_in: 0.0,
+            prev_out: 0.0,
+            alpha: rc / (rc + 1.0 / rate as f32),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Adds the mixer output (0.0~1.0) of one CPU cycle.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn push(&mut self, level: f32) {
+        self.sum += level;
+        self.count += 1;
+        self.phase += self.rate;
+        if self.phase < self.clock_hz {
+            return;
+        }
+        self.phase -= self.clock_hz;
+        let level = self.sum / self.count as f32;
+        (self.sum, self.count) = (0.0, 0);
+        let out = self.alpha * (self.prev_out + level - self.prev_in);
+        (self.prev_in, self.prev_out) = (level, out);
+        self.samples
+            .push((out * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+    }
+
+    /// Samples made since the last call.
+    pub fn take(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _samples_follow_the_clock() {
+        let mut sampler = Sampler::new(44100, 1_789_773);
+        for _ in 0..1_789_773 {
+            sampler.push(0.0);
+        }
+        let samples = sampler.take();
+        assert_eq!(44100, samples.len());
+        assert!(samples.iter().all(|&s| s == 0));
+        assert!(sampler.take().is_empty());
+    }
+
+    #[test]
+    fn _dc_offset_fades() {
+        let mut sampler = Sampler::new(1000, 100_000);
+        for _ in 0..100_000 {
+            sampler.push(0.5);
+        }
+        let samples = sampler.take();
+        assert!(samples[0] > 10000, "{}", samples[0]);
+        assert!(samples[999].abs() < 10, "{}", samples[999]);
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Size of a PRG bank as used to key ROM labels.
+pub const PRG_BANK_SIZE: usize = 0x2000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub comment: Option<String>,
+    /// Number of bytes the label spans (arrays, words).
+    pub size: u16,
+}
+
+/// Where a label lives. ROM labels follow the bank they were assembled into,
+/// so the same CPU address can carry a different name in each bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SymbolLocation {
+    /// CPU address outside of PRG-ROM (RAM, PRG-RAM, I/O registers).
+    Cpu(u16),
+    /// PRG-ROM, as an 8KB bank number and the offset inside that bank.
+    Prg { bank: usize, offset: u16 },
+}
+
+impl SymbolLocation {
+    pub fn prg(prg_offset: usize) -> Self {
+        Self::Prg {
+            bank: prg_offset / PRG_BANK_SIZE,
+            offset: (prg_offset % PRG_BANK_SIZE) as u16,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: BTreeMap<SymbolLocation, Symbol>,
+    names: HashMap<String, SymbolLocation>,
+}
+
+impl SymbolTable {
+    /// Adds a label, replacing the one at `location` if any. A name stands for one place,
+    /// so a name already given to another location is rejected and the table is unchanged.
+    pub fn insert(&mut self, location: SymbolLocation, symbol: Symbol) -> Result<(), String> {
+        if let Some(&other) = self.names.get(&symbol.name) {
+            if other != location {
+                return Err(format!("duplicate label {}", symbol.name));
+            }
+        }
+        if let Some(old) = self.symbols.insert(location, symbol.clone()) {
+            self.names.remove(&old.name);
+        }
+        self.names.insert(symbol.name, location);
+        Ok(())
+    }
+
+    pub fn get(&self, location: SymbolLocation) -> Option<&Symbol> {
+        self.symbols.get(&location)
+    }
+
+    /// Returns the label covering `location` and the distance from its start,
+    /// so `table+2` can be shown for the middle of an array.
+    pub fn find(&self, location: SymbolLocation) -> Option<(&Symbol, u16)> {
+        let (start, symbol) = self.symbols.range(..=location).next_back()?;
+        let distance = match (start, location) {
+            (SymbolLocation::Cpu(start), SymbolLocation::Cpu(addr)) => addr - start,
+            (
+                SymbolLocation::Prg { bank, offset },
+                SymbolLocation::Prg {
+                    bank: addr_bank,
+                    offset: addr,
+                },
+            ) if *bank == addr_bank => addr - offset,
+            _ => return None,
+        };
+        (distance < symbol.size.max(1)).then_some((symbol, distance))
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<SymbolLocation> {
+        self.names.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.symbols.clear();
+        self.names.clear();
+    }
+
+    /// Loads a symbol file, choosing the format from the file name:
+    /// `*.dbg` (ca65), `*.mlb` (Mesen), `*.<bank>.nl` / `*.ram.nl` (FCEUX).
+    pub fn load(&mut self, file_name: &str, text: &str) -> Result<(), String> {
+        let lower = file_name.to_ascii_lowercase();
+        if lower.ends_with(".dbg") {
+            self.load_ca65_dbg(text)
+        } else if lower.ends_with(".mlb") {
+            self.load_mesen_mlb(text)
+        } else if let Some(stem) = lower.strip_suffix(".nl") {
+            let bank = match stem.rsplit('.').next() {
+                Some("ram") => None,
+                Some(bank) => Some(
+                    usize::from_str_radix(bank, 16)
+                        .map_err(|_| format!("{}: no bank number in file name", file_name))?,
+                ),
+                None => None,
+            };
+            self.load_fceux_nl(text, bank)
+        } else {
+            Err(format!("{}: unknown symbol file format", file_name))
+        }
+    }
+
+    /// Loads a ca65/ld65 debug file (`--dbgfile`).
+    /// Labels in segments written to the ROM image are keyed by their PRG offset,
+    /// everything else by CPU address.
+    pub fn load_ca65_dbg(&mut self, text: &str) -> Result<(), String> {
+        struct Segment {
+            start: u32,
+            rom_offset: Option<usize>,
+        }
+        let mut segments = HashMap::new();
+        let mut syms = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let (kind, attrs) = match line.split_once(char::is_whitespace) {
+                Some((kind, attrs)) => (kind, parse_dbg_attributes(attrs)),
+                None => continue,
+            };
+            let number = |key: &str| -> Result<Option<u32>, String> {
+                attrs
+                    .get(key)
+                    .map(|v| parse_number(v).ok_or(format!("line {}: bad {} '{}'", i + 1, key, v)))
+                    .transpose()
+            };
+            match kind {
+                "seg" => {
+                    let id = number("id")?.ok_or(format!("line {}: seg without id", i + 1))?;
+                    // ooffs counts from the start of the output file, header included
+                    let rom_offset = match (attrs.get("oname"), number("ooffs")?) {
+                        (Some(_), Some(ooffs)) => (ooffs as usize).checked_sub(16),
+                        _ => None,
+                    };
+                    segments.insert(
+                        id,
+                        Segment {
+                            start: number("start")?.unwrap_or(0),
+                            rom_offset,
+                        },
+                    );
+                }
+                "sym" => {
+                    let (name, value) = match (attrs.get("name"), number("val")?) {
+                        (Some(name), Some(value)) => (name.trim_matches('"'), value),
+                        _ => continue,
+                    };
+                    // skip cheap locals (@loop) and constants
+                    if name.starts_with('@') || attrs.get("type").copied() != Some("lab") {
+                        continue;
+                    }
+                    let size = number("size")?.unwrap_or(1) as u16;
+                    syms.push((name.to_string(), value, size, number("seg")?));
+                }
+                _ => {}
+            }
+        }
+
+        for (name, value, size, seg) in syms {
+            let location = match seg.and_then(|id| segments.get(&id)) {
+                Some(Segment {
+                    start,
+                    rom_offset: Some(rom_offset),
+                }) if value >= *start => SymbolLocation::prg(rom_offset + (value - start) as usize),
+                _ => SymbolLocation::Cpu(value as u16),
+            };
+            self.insert(
+                location,
+                Symbol {
+                    name,
+                    comment: None,
+                    size,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Loads an FCEUX name list. `bank` is the 16KB bank number taken from the
+    /// file name (`game.nes.0.nl`), or `None` for `game.nes.ram.nl`.
+    pub fn load_fceux_nl(&mut self, text: &str, bank: Option<usize>) -> Result<(), String> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if !line.starts_with('$') {
+                continue;
+            }
+            let mut fields = line.splitn(3, '#');
+            let addr = fields.next().unwrap_or_default();
+            let name = fields.next().unwrap_or_default();
+            let comment = fields.next().filter(|c| !c.is_empty());
+            if name.is_empty() {
+                continue;
+            }
+            let (addr, size) = match addr[1..].split_once('/') {
+                Some((addr, size)) => (addr, u16::from_str_radix(size, 16).ok()),
+                None => (&addr[1..], None),
+            };
+            let addr = u16::from_str_radix(addr, 16)
+                .map_err(|_| format!("line {}: bad address '{}'", i + 1, addr))?;
+            let location = match bank {
+                Some(bank) if addr >= 0x8000 => {
+                    SymbolLocation::prg(bank * 0x4000 + (addr as usize & 0x3FFF))
+                }
+                _ => SymbolLocation::Cpu(addr),
+            };
+            self.insert(
+                location,
+                Symbol {
+                    name: name.to_string(),
+                    // FCEUX stores multi-line comments with a '\' separator
+                    comment: comment.map(|c| c.replace('\\', "\n")),
+                    size: size.unwrap_or(1),
+                },
+            )
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        Ok(())
+    }
+
+    /// Loads a Mesen label file (`P:0123:Name:Comment`).
+    pub fn load_mesen_mlb(&mut self, text: &str) -> Result<(), String> {
+        for (i, line) in text.lines().enumerate() {
+            let mut fields = line.trim_end().splitn(4, ':');
+            let (kind, addr, name) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(kind), Some(addr), Some(name)) if !name.is_empty() => (kind, addr, name),
+                _ => continue,
+            };
+            let comment = fields.next().filter(|c| !c.is_empty());
+            let (start, end) = match addr.split_once('-') {
+                Some((start, end)) => (start, end),
+                None => (addr, addr),
+            };
+            let parse = |v: &str| {
+                usize::from_str_radix(v, 16)
+                    .map_err(|_| format!("line {}: bad address '{}'", i + 1, v))
+            };
+            let (start, end) = (parse(start)?, parse(end)?);
+            let location = match kind {
+                "P" => SymbolLocation::prg(start),
+                "R" => SymbolLocation::Cpu(start as u16),
+                "S" | "W" => SymbolLocation::Cpu(0x6000 + start as u16),
+                "G" => SymbolLocation::Cpu(start as u16),
+                _ => continue,
+            };
+            self.insert(
+                location,
+                Symbol {
+                    name: name.to_string(),
+                    comment: comment.map(|c| c.replace("\\n", "\n")),
+                    size: (end.saturating_sub(start) + 1) as u16,
+                },
+            )
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_number(v: &str) -> Option<u32> {
+    match v.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => v.parse().ok(),
+    }
+}
+
+/// Splits `id=0,name="a,b",val=0x10` into key/value pairs, honouring quotes.
+fn parse_dbg_attributes(attrs: &str) -> HashMap<&str, &str> {
+    let mut map = HashMap::new();
+    let mut rest = attrs.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let end = if let Some(quoted) = value.strip_prefix('"') {
+            quoted.find('"').map_or(value.len(), |i| i + 2)
+        } else {
+            value.find(',').unwrap_or(value.len())
+        };
+        map.insert(key.trim(), &value[..end]);
+        rest = value[end..].trim_start_matches(',');
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _ca65_dbg() {
+        let mut table = SymbolTable::default();
+        table
+            .load_ca65_dbg(concat!(
+                "version\tmajor=2,minor=0\n",
+                "seg\tid=0,name=\"ZEROPAGE\",start=0x000000,size=0x0010,addrsize=zeropage,type=rw\n",
+                "seg\tid=1,name=\"CODE\",start=0x00C000,size=0x0100,addrsize=absolute,type=ro,oname=\"game.nes\",ooffs=16400\n",
+                "sym\tid=0,name=\"NMI_Handler\",addrsize=absolute,scope=0,def=1,val=0xC0A3,seg=1,type=lab\n",
+                "sym\tid=1,name=\"frame\",addrsize=zeropage,size=2,scope=0,def=2,val=0x4,seg=0,type=lab\n",
+                "sym\tid=2,name=\"@loop\",addrsize=absolute,scope=0,def=3,val=0xC0A5,seg=1,type=lab\n",
+                "sym\tid=3,name=\"PPUCTRL\",addrsize=absolute,scope=0,def=4,val=0x2000,type=equ\n",
+            ))
+            .unwrap();
+        assert_eq!(2, table.len());
+        assert_eq!(
+            Some(SymbolLocation::Prg {
+                bank: 2,
+                offset: 0x00A3
+            }),
+            table.lookup("NMI_Handler")
+        );
+        let (frame, distance) = table.find(SymbolLocation::Cpu(0x0005)).unwrap();
+        assert_eq!(("frame", 1), (frame.name.as_str(), distance));
+        assert!(table.find(SymbolLocation::Cpu(0x0006)).is_none());
+    }
+
+    #[test]
+    fn _fceux_nl() {
+        let mut table = SymbolTable::default();
+        table
+            .load(
+                "game.nes.1.nl",
+                "$C0A3#NMI_Handler#vblank\\entry\n$FFFA/6#Vectors#\n",
+            )
+            .unwrap();
+        table.load("game.nes.ram.nl", "$0300#buffer#\n").unwrap();
+        let nmi = table.get(SymbolLocation::prg(0x40A3)).unwrap();
+        assert_eq!("NMI_Handler", nmi.name);
+        assert_eq!(Some("vblank\nentry"), nmi.comment.as_deref());
+        assert_eq!(6, table.get(SymbolLocation::prg(0x7FFA)).unwrap().size);
+        assert_eq!(Some(SymbolLocation::Cpu(0x0300)), table.lookup("buffer"));
+    }
+
+    #[test]
+    fn _mesen_mlb() {
+        let mut table = SymbolTable::default();
+        table
+            .load_mesen_mlb(
+                "P:40A3:NMI_Handler:entry\nR:0010-0011:ptr:\nS:0000:save\nG:2000:PPUCTRL\n",
+            )
+            .unwrap();
+        assert_eq!(
+            Some(SymbolLocation::prg(0x40A3)),
+            table.lookup("NMI_Handler")
+        );
+        assert_eq!(2, table.get(SymbolLocation::Cpu(0x0010)).unwrap().size);
+        assert_eq!(Some(SymbolLocation::Cpu(0x6000)), table.lookup("save"));
+        assert_eq!(Some(SymbolLocation::Cpu(0x2000)), table.lookup("PPUCTRL"));
+    }
+
+    #[test]
+    fn _insert_replaces_and_rejects_duplicates() {
+        let mut table = SymbolTable::default();
+        let symbol = |name: &str| Symbol {
+            name: name.to_string(),
+            comment: None,
+            size: 1,
+        };
+        table
+            .insert(SymbolLocation::Cpu(0x10), symbol("old"))
+            .unwrap();
+        table
+            .insert(SymbolLocation::Cpu(0x10), symbol("new"))
+            .unwrap();
+        assert_eq!(None, table.lookup("old"));
+        assert_eq!(Some(SymbolLocation::Cpu(0x10)), table.lookup("new"));
+        // the same label again is fine, the same name elsewhere is not
+        table
+            .insert(SymbolLocation::Cpu(0x10), symbol("new"))
+            .unwrap();
+        assert!(table
+            .insert(SymbolLocation::Cpu(0x20), symbol("new"))
+            .is_err());
+        assert_eq!(None, table.get(SymbolLocation::Cpu(0x20)));
+        assert_eq!(1, table.len());
+        assert!(table
+            .load_mesen_mlb("R:0010:ptr:\nR:0020:ptr:\n")
+            .unwrap_err()
+            .starts_with("line 2"));
+    }
+}
@@ -1,6 +1,15 @@
 pub mod apu;
+pub mod apu_channel;
+pub mod cdl;
+pub mod cheat;
 pub mod cpu;
+pub mod cpu_step;
+pub mod dma;
 pub mod joypad;
 pub mod nes;
 pub mod ppu;
 pub mod ppu_state;
+pub mod ppu_viewer;
+pub mod ram_search;
+pub mod screenshot;
+pub mod symbol;
@@ -1,25 +1,92 @@
-use crate::entity::apu::Register;
+use crate::entity::{apu::Register, cpu::IrqSource, sampler::Sampler};
 
-use super::nes::NesState;
+use super::{
+    apu_channel::{NoiseState, PulseState, TriangleState},
+    nes::NesState,
+};
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ApuState {
     pub register: Register,
+    pub frame_counter: FrameCounter,
+    pub pulse1: PulseState,
+    pub pulse2: PulseState,
+    pub triangle: TriangleState,
+    pub noise: NoiseState,
+    pub dmc: DmcState,
+    /// Collects the audio output once `start_audio` is called.
+    pub sampler: Option<Sampler>,
+}
+
+impl Default for ApuState {
+    fn default() -> Self {
+        Self {
+            register: Register::default(),
+            frame_counter: FrameCounter::default(),
+            pulse1: PulseState {
+                ones_complement: true,
+                ..Default::default()
+            },
+            pulse2: PulseState::default(),
+            triangle: TriangleState::default(),
+            noise: NoiseState::default(),
+            dmc: DmcState::default(),
+            sampler: None,
+        }
+    }
+}
+
+/// Clocks the envelopes and length counters, and raises the frame IRQ in 4-step mode.
+#[derive(Debug, Default)]
+pub struct FrameCounter {
+    pub five_step: bool,
+    pub irq_inhibit: bool,
+    pub irq: bool,
+    /// CPU cycles since the start of the sequence.
+    pub cycle: u16,
+    /// CPU cycles until a $4017 write restarts the sequence; 0 when none is pending.
+    pub reset_delay: u8,
+}
+
+/// Delta modulation channel: the memory reader and output unit.
+#[derive(Debug, Default)]
+pub struct DmcState {
+    pub irq_enabled: bool,
+    pub loop_flag: bool,
+    pub rate_index: u8,
+    pub output_level: u8,
+    pub sample_addr: u16,
+    pub sample_len: u16,
+    pub current_addr: u16,
+    pub bytes_remaining: u16,
+    pub sample_buffer: Option<u8>,
+    pub timer: u16,
+    pub shift_register: u8,
+    pub bits_remaining: u8,
+    pub silence: bool,
+    pub irq: bool,
 }
 
 impl NesState {
     #[cfg_attr(not(debug_assertions), inline(always))]
-    pub fn read_apu(&self, addr: u16) -> u8 {
-        match addr {
-            0x4000..=0x4003 => self.apu.register.pulse1[(addr - 0x4000) as usize],
-            0x4004..=0x4007 => self.apu.register.pulse2[(addr - 0x4004) as usize],
-            0x4008..=0x400B => self.apu.register.triangle[(addr - 0x4008) as usize],
-            0x400C..=0x400F => self.apu.register.noise[(addr - 0x400C) as usize],
-            0x4010..=0x4013 => self.apu.register.dmc[(addr - 0x4010) as usize],
-            0x4015 => self.apu.register.status,
-            0x4017 => self.apu.register.frame_counter,
-            _ => 0,
-        }
+    /// $4015 is the only readable APU register; bit 5 is left to the open bus.
+    pub fn peek_apu_status(&self) -> u8 {
+        (self.apu.pulse1.length.active() as u8)
+            | (self.apu.pulse2.length.active() as u8) << 1
+            | (self.apu.triangle.length.active() as u8) << 2
+            | (self.apu.noise.length.active() as u8) << 3
+            | ((self.apu.dmc.bytes_remaining > 0) as u8) << 4
+            | (self.apu.frame_counter.irq as u8) << 6
+            | (self.apu.dmc.irq as u8) << 7
+    }
+
+    /// `peek_apu_status`, and the read acknowledges the frame IRQ.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn read_apu_status(&mut self) -> u8 {
+        let status = self.peek_apu_status();
+        self.apu.frame_counter.irq = false;
+        self.update_apu_irq();
+        status
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -27,27 +94,259 @@ impl NesState {
         match addr {
             0x4000..=0x4003 => {
                 self.apu.register.pulse1[(addr - 0x4000) as usize] = val;
+                self.apu.pulse1.write(addr - 0x4000, val);
             }
             0x4004..=0x4007 => {
                 self.apu.register.pulse2[(addr - 0x4004) as usize] = val;
+                self.apu.pulse2.write(addr - 0x4004, val);
             }
             0x4008..=0x400B => {
                 self.apu.register.triangle[(addr - 0x4008) as usize] = val;
+                self.apu.triangle.write(addr - 0x4008, val);
             }
             0x400C..=0x400F => {
                 self.apu.register.noise[(addr - 0x400C) as usize] = val;
+                self.apu.noise.write(addr - 0x400C, val);
             }
             0x4010..=0x4013 => {
                 self.apu.register.dmc[(addr - 0x4010) as usize] = val;
+                self.write_dmc(addr, val);
             }
             0x4015 => {
                 self.apu.register.status = val;
+                self.apu.pulse1.length.set_enabled(val & 0x01 != 0);
+                self.apu.pulse2.length.set_enabled(val & 0x02 != 0);
+                self.apu.triangle.length.set_enabled(val & 0x04 != 0);
+                self.apu.noise.length.set_enabled(val & 0x08 != 0);
+                self.apu.dmc.irq = false;
+                if val & 0x10 == 0 {
+                    self.apu.dmc.bytes_remaining = 0;
+                } else if self.apu.dmc.bytes_remaining == 0 {
+                    self.restart_dmc();
+                }
+                self.update_apu_irq();
             }
             0x4017 => {
                 self.apu.register.frame_counter = val;
+                let frame_counter = &mut self.apu.frame_counter;
+                frame_counter.five_step = val & 0x80 != 0;
+                frame_counter.irq_inhibit = val & 0x40 != 0;
+                if frame_counter.irq_inhibit {
+                    frame_counter.irq = false;
+                    self.update_apu_irq();
+                }
+                // the sequence restarts on the 2nd APU cycle after the write
+                self.apu.frame_counter.reset_delay = if self.is_get_cycle() { 3 } else { 4 };
             }
             _ => {}
         }
         val
     }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn write_dmc(&mut self, addr: u16, val: u8) {
+        let dmc = &mut self.apu.dmc;
+        match addr {
+            0x4010 => {
+                dmc.irq_enabled = val & 0x80 != 0;
+                dmc.loop_flag = val & 0x40 != 0;
+                dmc.rate_index = val & 0x0F;
+                if !dmc.irq_enabled {
+                    dmc.irq = false;
+                    self.update_apu_irq();
+                }
+            }
+            0x4011 => dmc.output_level = val & 0x7F,
+            0x4012 => dmc.sample_addr = 0xC000 + val as u16 * 64,
+            0x4013 => dmc.sample_len = val as u16 * 16 + 1,
+            _ => {}
+        }
+    }
+
+    fn restart_dmc(&mut self) {
+        self.apu.dmc.current_addr = self.apu.dmc.sample_addr;
+        self.apu.dmc.bytes_remaining = self.apu.dmc.sample_len;
+        if self.apu.dmc.sample_buffer.is_none() {
+            self.start_dmc_dma();
+        }
+    }
+
+    fn update_apu_irq(&mut self) {
+        self.cpu.set_irq(IrqSource::Dmc, self.apu.dmc.irq);
+        self.cpu
+            .set_irq(IrqSource::FrameCounter, self.apu.frame_counter.irq);
+    }
+
+    /// Called by DMA with the fetched sample byte.
+    pub fn set_dmc_sample_buffer(&mut self, value: u8) {
+        let dmc = &mut self.apu.dmc;
+        if dmc.bytes_remaining == 0 {
+            return;
+        }
+        dmc.sample_buffer = Some(value);
+        dmc.current_addr = dmc.current_addr.checked_add(1).unwrap_or(0x8000);
+        dmc.bytes_remaining -= 1;
+        if dmc.bytes_remaining == 0 {
+            if dmc.loop_flag {
+                self.restart_dmc();
+            } else if dmc.irq_enabled {
+                dmc.irq = true;
+                self.update_apu_irq();
+            }
+        }
+    }
+
+    /// Starts collecting the audio output as `sample_rate` Hz PCM, fetched with `take_samples`.
+    pub fn start_audio(&mut self, sample_rate: u32) {
+        self.apu.sampler = Some(Sampler::new(sample_rate, self.region.cpu_clock_hz()));
+    }
+
+    /// Samples made since the last call; empty unless `start_audio` was called.
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        self.apu
+            .sampler
+            .as_mut()
+            .map(Sampler::take)
+            .unwrap_or_default()
+    }
+
+    /// Advances the APU by one CPU cycle.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn apu_step(&mut self) {
+        self.frame_counter_step();
+        if self.is_get_cycle() {
+            self.apu.pulse1.step();
+            self.apu.pulse2.step();
+        }
+        self.apu.triangle.step();
+        self.apu.noise.step(self.region.noise_periods());
+        self.dmc_step();
+        if self.apu.sampler.is_some() {
+            let apu = &self.apu;
+            let level = mix(
+                apu.pulse1.output() + apu.pulse2.output(),
+                apu.triangle.output(),
+                apu.noise.output(),
+                apu.dmc.output_level,
+            );
+            if let Some(sampler) = &mut self.apu.sampler {
+                sampler.push(level);
+            }
+        }
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn frame_counter_step(&mut self) {
+        let steps = self.region.frame_counter_steps();
+        let frame_counter = &mut self.apu.frame_counter;
+        if frame_counter.reset_delay > 0 {
+            frame_counter.reset_delay -= 1;
+            if frame_counter.reset_delay == 0 {
+                frame_counter.cycle = 0;
+                // 5-step mode clocks everything at once when it starts
+                if frame_counter.five_step {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+                return;
+            }
+        }
+
+        let frame_counter = &mut self.apu.frame_counter;
+        frame_counter.cycle += 1;
+        let cycle = frame_counter.cycle;
+        let last = if frame_counter.five_step {
+            steps[4]
+        } else {
+            steps[3]
+        };
+        if !frame_counter.five_step
+            && !frame_counter.irq_inhibit
+            && (last - 1..=last + 1).contains(&cycle)
+        {
+            frame_counter.irq = true;
+            self.update_apu_irq();
+        }
+        if cycle > last {
+            self.apu.frame_counter.cycle = 0;
+        }
+
+        if cycle == steps[0] || cycle == steps[2] {
+            self.clock_quarter_frame();
+        } else if cycle == steps[1] || cycle == last {
+            self.clock_quarter_frame();
+            self.clock_half_frame();
+        }
+    }
+
+    /// Envelopes and the triangle's linear counter.
+    fn clock_quarter_frame(&mut self) {
+        self.apu.pulse1.envelope.clock();
+        self.apu.pulse2.envelope.clock();
+        self.apu.triangle.clock_linear_counter();
+        self.apu.noise.envelope.clock();
+    }
+
+    /// Length counters and sweep units.
+    fn clock_half_frame(&mut self) {
+        self.apu.pulse1.length.clock();
+        self.apu.pulse1.clock_sweep();
+        self.apu.pulse2.length.clock();
+        self.apu.pulse2.clock_sweep();
+        self.apu.triangle.length.clock();
+        self.apu.noise.length.clock();
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn dmc_step(&mut self) {
+        if self.apu.dmc.timer > 0 {
+            self.apu.dmc.timer -= 1;
+            return;
+        }
+        self.apu.dmc.timer = self.region.dmc_rates()[self.apu.dmc.rate_index as usize] - 1;
+
+        let dmc = &mut self.apu.dmc;
+        if !dmc.silence {
+            if dmc.shift_register & 1 == 1 {
+                if dmc.output_level <= 125 {
+                    dmc.output_level += 2;
+                }
+            } else if dmc.output_level >= 2 {
+                dmc.output_level -= 2;
+            }
+        }
+        dmc.shift_register >>= 1;
+        dmc.bits_remaining = dmc.bits_remaining.saturating_sub(1);
+        if dmc.bits_remaining == 0 {
+            dmc.bits_remaining = 8;
+            match dmc.sample_buffer.take() {
+                Some(sample) => {
+                    dmc.silence = false;
+                    dmc.shift_register = sample;
+                }
+                None => dmc.silence = true,
+            }
+            if dmc.bytes_remaining > 0 {
+                self.start_dmc_dma();
+            }
+        }
+    }
+}
+
+/// Mixer output (0.0~1.0) from the nonlinear formulas of the pulse group and of the
+/// triangle, noise and DMC group. `pulse` is the sum of both pulse channels.
+#[cfg_attr(not(debug_assertions), inline(always))]
+fn mix(pulse: u8, triangle: u8, noise: u8, dmc: u8) -> f32 {
+    let pulse_out = if pulse == 0 {
+        0.0
+    } else {
+        95.88 / (8128.0 / pulse as f32 + 100.0)
+    };
+    let tnd = triangle as f32 / 8227.0 + noise as f32 / 12241.0 + dmc as f32 / 22638.0;
+    let tnd_out = if tnd == 0.0 {
+        0.0
+    } else {
+        159.79 / (1.0 / tnd + 100.0)
+    };
+    pulse_out + tnd_out
 }
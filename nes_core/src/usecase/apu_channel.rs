@@ -0,0 +1,448 @@
+/// Length counter loads, indexed by bits 3~7 of the channel's 4th register.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Silences a channel after a number of half frames.
+#[derive(Debug, Default)]
+pub struct LengthCounter {
+    pub enabled: bool,
+    pub halt: bool,
+    pub counter: u8,
+}
+
+impl LengthCounter {
+    /// Write to the channel's 4th register.
+    pub fn load(&mut self, val: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(val >> 3) as usize];
+        }
+    }
+
+    /// $4015 write; a disabled channel is silenced at once.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+/// Volume of the pulse and noise channels: constant, or a saw decaying every quarter frame.
+#[derive(Debug, Default)]
+pub struct Envelope {
+    pub start: bool,
+    pub looping: bool,
+    pub constant: bool,
+    /// Constant volume, or the decay period.
+    pub volume: u8,
+    pub divider: u8,
+    pub decay: u8,
+}
+
+impl Envelope {
+    /// Write to the channel's 1st register (`--LC VVVV`).
+    pub fn write(&mut self, val: u8) {
+        self.looping = val & 0x20 != 0;
+        self.constant = val & 0x10 != 0;
+        self.volume = val & 0x0F;
+    }
+
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider > 0 {
+            self.divider -= 1;
+        } else {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// Pulse channel: a square wave of 4 duty cycles, with a sweep unit bending its period.
+#[derive(Debug, Default)]
+pub struct PulseState {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    pub duty: u8,
+    pub sequence_step: u8,
+    /// 11bit timer period in APU cycles.
+    pub period: u16,
+    pub timer: u16,
+    pub sweep_enabled: bool,
+    pub sweep_period: u8,
+    pub sweep_negate: bool,
+    pub sweep_shift: u8,
+    pub sweep_divider: u8,
+    pub sweep_reload: bool,
+    /// Pulse 1 negates the sweep change in ones' complement, so it sweeps down one further.
+    pub ones_complement: bool,
+}
+
+impl PulseState {
+    /// Write to $4000~$4003 or $4004~$4007.
+    pub fn write(&mut self, reg: u16, val: u8) {
+        match reg {
+            0 => {
+                self.duty = val >> 6;
+                self.length.halt = val & 0x20 != 0;
+                self.envelope.write(val);
+            }
+            1 => {
+                self.sweep_enabled = val & 0x80 != 0;
+                self.sweep_period = (val >> 4) & 0x07;
+                self.sweep_negate = val & 0x08 != 0;
+                self.sweep_shift = val & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | val as u16,
+            3 => {
+                self.period = (self.period & 0x00FF) | ((val & 0x07) as u16) << 8;
+                self.length.load(val);
+                self.sequence_step = 0;
+                self.envelope.start = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances the timer by one APU cycle (2 CPU cycles).
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn step(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+        } else {
+            self.timer = self.period;
+            self.sequence_step = self.sequence_step.wrapping_sub(1) & 0x07;
+        }
+    }
+
+    fn target_period(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        if self.sweep_negate {
+            self.period
+                .saturating_sub(change + self.ones_complement as u16)
+        } else {
+            self.period + change
+        }
+    }
+
+    /// Periods under 8 and sweeps past $7FF silence the channel, even with the sweep disabled.
+    fn muted(&self) -> bool {
+        self.period < 8 || self.target_period() > 0x07FF
+    }
+
+    /// Half frame clock of the sweep unit.
+    pub fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.period = self.target_period();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// 0~15.
+    pub fn output(&self) -> u8 {
+        if self.muted()
+            || !self.length.active()
+            || DUTY_TABLE[self.duty as usize][self.sequence_step as usize] == 0
+        {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+/// Triangle channel: a fixed 32-step ramp, gated by the length and linear counters.
+#[derive(Debug, Default)]
+pub struct TriangleState {
+    pub length: LengthCounter,
+    /// Also halts the length counter.
+    pub linear_control: bool,
+    pub linear_reload_value: u8,
+    pub linear_counter: u8,
+    pub linear_reload: bool,
+    /// 11bit timer period in CPU cycles.
+    pub period: u16,
+    pub timer: u16,
+    pub sequence_step: u8,
+}
+
+impl TriangleState {
+    /// Write to $4008~$400B.
+    pub fn write(&mut self, reg: u16, val: u8) {
+        match reg {
+            0 => {
+                self.linear_control = val & 0x80 != 0;
+                self.length.halt = self.linear_control;
+                self.linear_reload_value = val & 0x7F;
+            }
+            2 => self.period = (self.period & 0x0700) | val as u16,
+            3 => {
+                self.period = (self.period & 0x00FF) | ((val & 0x07) as u16) << 8;
+                self.length.load(val);
+                self.linear_reload = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances the timer by one CPU cycle. The sequence holds its level while either
+    /// counter is 0, and at ultrasonic periods, which would only make a pop.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn step(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period;
+        if self.linear_counter > 0 && self.length.active() && self.period >= 2 {
+            self.sequence_step = (self.sequence_step + 1) & 0x1F;
+        }
+    }
+
+    /// Quarter frame clock of the linear counter.
+    pub fn clock_linear_counter(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.linear_control {
+            self.linear_reload = false;
+        }
+    }
+
+    /// 15~0, then 0~15.
+    pub fn output(&self) -> u8 {
+        if self.sequence_step < 16 {
+            15 - self.sequence_step
+        } else {
+            self.sequence_step - 16
+        }
+    }
+}
+
+/// Noise channel: a 15bit LFSR whose low bit gates the envelope.
+#[derive(Debug)]
+pub struct NoiseState {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    /// Short mode taps bit 6 instead of bit 1, for a metallic 93-step loop.
+    pub short_mode: bool,
+    pub period_index: u8,
+    pub timer: u16,
+    pub shift_register: u16,
+}
+
+impl Default for NoiseState {
+    fn default() -> Self {
+        Self {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            short_mode: false,
+            period_index: 0,
+            timer: 0,
+            // the register is 1 at power on
+            shift_register: 1,
+        }
+    }
+}
+
+impl NoiseState {
+    /// Write to $400C~$400F.
+    pub fn write(&mut self, reg: u16, val: u8) {
+        match reg {
+            0 => {
+                self.length.halt = val & 0x20 != 0;
+                self.envelope.write(val);
+            }
+            2 => {
+                self.short_mode = val & 0x80 != 0;
+                self.period_index = val & 0x0F;
+            }
+            3 => {
+                self.length.load(val);
+                self.envelope.start = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances the timer by one CPU cycle; `periods` are in CPU cycles.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn step(&mut self, periods: &[u16; 16]) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = periods[self.period_index as usize] - 1;
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 1;
+        self.shift_register = self.shift_register >> 1 | feedback << 14;
+    }
+
+    /// 0~15.
+    pub fn output(&self) -> u8 {
+        if self.shift_register & 1 == 1 || !self.length.active() {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _envelope_decays_and_loops() {
+        let mut envelope = Envelope::default();
+        envelope.write(0x20);
+        envelope.start = true;
+        envelope.clock();
+        assert_eq!(15, envelope.output());
+        for _ in 0..15 {
+            envelope.clock();
+        }
+        assert_eq!(0, envelope.output());
+        envelope.clock();
+        assert_eq!(15, envelope.output());
+        envelope.write(0x17);
+        assert_eq!(7, envelope.output());
+    }
+
+    #[test]
+    fn _length_counter() {
+        let mut length = LengthCounter::default();
+        length.load(0x08);
+        assert!(!length.active(), "a disabled channel does not load");
+        length.set_enabled(true);
+        length.load(0x18);
+        assert_eq!(2, length.counter);
+        length.clock();
+        length.clock();
+        assert!(!length.active());
+        length.load(0x08);
+        length.set_enabled(false);
+        assert!(!length.active());
+    }
+
+    #[test]
+    fn _pulse_sweep() {
+        for (ones_complement, target) in [(true, 0x0FF), (false, 0x100)] {
+            let mut pulse = PulseState {
+                ones_complement,
+                ..Default::default()
+            };
+            pulse.length.set_enabled(true);
+            pulse.write(0, 0xBF);
+            pulse.write(2, 0x00);
+            pulse.write(3, 0x02);
+            // enabled, period 0, negate, shift 1
+            pulse.write(1, 0x89);
+            pulse.clock_sweep();
+            assert_eq!(target, pulse.period);
+        }
+
+        let mut pulse = PulseState::default();
+        pulse.write(2, 0x00);
+        pulse.write(3, 0x03);
+        assert!(!pulse.muted());
+        // the target of a disabled sweep with shift 0 is twice the period
+        pulse.write(3, 0x04);
+        assert!(pulse.muted());
+        pulse.write(2, 0x07);
+        pulse.write(3, 0x00);
+        assert!(pulse.muted());
+    }
+
+    #[test]
+    fn _triangle_linear_counter() {
+        let mut triangle = TriangleState::default();
+        triangle.length.set_enabled(true);
+        triangle.write(0, 0x02);
+        triangle.write(2, 0x02);
+        triangle.write(3, 0x08);
+        triangle.clock_linear_counter();
+        assert_eq!(2, triangle.linear_counter);
+        assert!(
+            !triangle.linear_reload,
+            "cleared unless the control flag is set"
+        );
+
+        for _ in 0..6 {
+            triangle.step();
+        }
+        assert_eq!(2, triangle.sequence_step);
+        assert_eq!(13, triangle.output());
+        triangle.clock_linear_counter();
+        triangle.clock_linear_counter();
+        for _ in 0..6 {
+            triangle.step();
+        }
+        assert_eq!(
+            2, triangle.sequence_step,
+            "a linear counter of 0 holds the level"
+        );
+    }
+
+    #[test]
+    fn _noise_sequences() {
+        let periods = [1; 16];
+        for (short_mode, len) in [(false, 32767), (true, 93)] {
+            let mut noise = NoiseState {
+                short_mode,
+                ..Default::default()
+            };
+            noise.step(&periods);
+            let start = noise.shift_register;
+            let steps = (1..=32767)
+                .find(|_| {
+                    noise.step(&periods);
+                    noise.shift_register == start
+                })
+                .unwrap();
+            assert_eq!(len, steps);
+        }
+    }
+}
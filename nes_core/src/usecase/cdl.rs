@@ -0,0 +1,17 @@
+use super::nes::NesState;
+
+impl NesState {
+    /// Logs a CPU read of PRG-ROM through the current bank mapping.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn log_prg(&mut self, addr: u16) {
+        let offset = self.cartridge.prg_addr(addr);
+        self.cdl.log_prg(addr, offset, self.cpu.register.PC);
+    }
+
+    /// Logs a PPU read of CHR-ROM through the current bank mapping.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn log_chr(&mut self, addr: u16, flags: u8) {
+        let offset = self.cartridge.chr_addr(addr);
+        self.cdl.mark_chr(offset, flags);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::entity::cheat::Cheat;
+
+use super::nes::NesState;
+
+impl NesState {
+    /// Parses and enables a Game Genie or Pro Action Replay code.
+    pub fn add_cheat(&mut self, code: &str) -> Result<(), String> {
+        let cheat = Cheat::parse(code)?;
+        if !self.cheats.iter().any(|c| c.code == cheat.code) {
+            self.cheats.push(cheat);
+        }
+        Ok(())
+    }
+
+    /// Returns false when `code` was not registered.
+    pub fn remove_cheat(&mut self, code: &str) -> bool {
+        let code = Cheat::parse(code).map_or_else(|_| code.to_string(), |c| c.code);
+        let len = self.cheats.len();
+        self.cheats.retain(|c| c.code != code);
+        self.cheats.len() != len
+    }
+
+    /// Flips a registered cheat on or off and returns the new state.
+    pub fn toggle_cheat(&mut self, code: &str) -> Option<bool> {
+        let code = Cheat::parse(code).map_or_else(|_| code.to_string(), |c| c.code);
+        let cheat = self.cheats.iter_mut().find(|c| c.code == code)?;
+        cheat.enabled = !cheat.enabled;
+        Some(cheat.enabled)
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn apply_cheats(&self, addr: u16, value: u8) -> u8 {
+        self.cheats
+            .iter()
+            .fold(value, |value, cheat| cheat.apply(addr, value))
+    }
+}
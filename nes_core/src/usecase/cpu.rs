@@ -1,18 +1,36 @@
 use crate::{
-    entity::cpu::{Control, InterruptionType, Register, WRam},
+    entity::cpu::{Control, InterruptionType, IrqSource, Register, WRam},
     util::bit::{get_little_endian, AsU8, Zero},
 };
 
-use super::nes::NesState;
-
-const TOTAL_CYCLES: u16 = 29781;
+use super::{
+    cpu_step::{StepMode, StepState},
+    dma::DmaState,
+    nes::NesState,
+};
 
 #[derive(Debug)]
 pub struct CpuState {
     pub register: Register,
     pub wram: WRam,
     pub control: Control,
-    pub remaining_cycles: i32,
+    /// CPU cycles elapsed since power on.
+    pub cycles: u64,
+    /// Fraction of a PPU dot carried over between CPU cycles, in fifths of a dot.
+    pub ppu_clock_phase: u8,
+    pub dma: DmaState,
+    /// Last value on the data bus; unmapped reads return it.
+    pub open_bus: u8,
+    /// A KIL/JAM opcode locked up the CPU; only a reset gets it going again.
+    pub jam: bool,
+    /// NMI line level at the last poll; NMI triggers on its rising edge.
+    pub prev_nmi: bool,
+    /// An NMI edge was seen and the NMI sequence has not run yet.
+    pub need_nmi: bool,
+    /// The last poll saw IRQ asserted with the I flag clear.
+    pub run_irq: bool,
+    pub prev_run_irq: bool,
+    pub step: StepState,
 }
 
 impl Default for CpuState {
@@ -21,15 +39,31 @@ impl Default for CpuState {
             register: Register::default(),
             wram: [0; 0x800],
             control: Control::default(),
-            remaining_cycles: 0,
+            cycles: 0,
+            ppu_clock_phase: 0,
+            dma: DmaState::default(),
+            open_bus: 0,
+            jam: false,
+            prev_nmi: false,
+            need_nmi: false,
+            run_irq: false,
+            prev_run_irq: false,
+            step: StepState::default(),
         }
     }
 }
 
 impl CpuState {
+    /// Asserts or releases the IRQ line for `source`; the line stays asserted while
+    /// another source holds it.
     #[cfg_attr(not(debug_assertions), inline(always))]
-    pub fn set_irq(&mut self) {
-        self.control.IRQ = true;
+    pub fn set_irq(&mut self, source: IrqSource, active: bool) {
+        if active {
+            self.control.irq_sources |= source as u8;
+        } else {
+            self.control.irq_sources &= !(source as u8);
+        }
+        self.control.IRQ = self.control.irq_sources != 0;
     }
 
     #[allow(non_snake_case)]
@@ -59,20 +93,35 @@ impl CpuState {
 
 impl NesState {
     #[cfg_attr(not(debug_assertions), inline(always))]
-    fn read_cpu_bus(&mut self, addr: u16) -> u8 {
-        match addr {
-            0x0000..=0x1FFF => self.cpu.wram[(addr % 0x800) as usize],
+    pub fn read_cpu_bus(&mut self, addr: u16) -> u8 {
+        let value = match addr {
+            0x0000..=0x1FFF => {
+                let value = self.cpu.wram[(addr % 0x800) as usize];
+                if self.cheats.is_empty() {
+                    value
+                } else {
+                    self.apply_cheats(addr, value)
+                }
+            }
             0x2000..=0x3FFF => self.read_ppu(addr),
-            0x4000..=0x4013 | 0x4015 => self.read_apu(addr),
-            0x4017 => self.read_joypad_state(true),
-            0x4014 => 0,
-            0x4016 => self.read_joypad_state(false),
-            0x4018..=0xFFFF => self.cartridge.read_prg(addr),
-        }
+            // the APU answers $4015 inside the chip, so the data bus keeps its value
+            0x4015 => return self.read_apu_status() | (self.cpu.open_bus & 0x20),
+            0x4000..=0x4014 => self.cpu.open_bus,
+            0x4016 => self.read_joypad_state(false) | (self.cpu.open_bus & 0xE0),
+            0x4017 => self.read_joypad_state(true) | (self.cpu.open_bus & 0xE0),
+            0x4018..=0xFFFF => match self.cartridge.read_prg(addr) {
+                Some(value) if !self.cheats.is_empty() => self.apply_cheats(addr, value),
+                Some(value) => value,
+                None => self.cpu.open_bus,
+            },
+        };
+        self.cpu.open_bus = value;
+        value
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn write_cpu_bus(&mut self, addr: u16, value: u8) -> u8 {
+        self.cpu.open_bus = value;
         match addr {
             0x0000..=0x1FFF => {
                 self.cpu.wram[(addr % 0x800) as usize] = value;
@@ -81,7 +130,7 @@ impl NesState {
             0x2000..=0x3FFF => self.write_ppu(addr, value),
             0x4000..=0x4013 | 0x4015 | 0x4017 => self.write_apu(addr, value),
             0x4014 => {
-                self.dma_oam(value);
+                self.start_oam_dma(value);
                 0
             }
             0x4016 => {
@@ -92,23 +141,81 @@ impl NesState {
         }
     }
 
-    fn tick(&mut self) {
-        self.ppu_step();
-        self.ppu_step();
-        self.ppu_step();
-        self.cpu.remaining_cycles -= 1;
+    /// Returns what a CPU read of `addr` would return, without side effects or ticks.
+    /// Cheats are not applied, so memory shows its real contents.
+    pub fn peek_cpu(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.cpu.wram[(addr % 0x800) as usize],
+            0x2000..=0x3FFF => self.peek_ppu_register(addr),
+            0x4015 => self.peek_apu_status() | (self.cpu.open_bus & 0x20),
+            0x4000..=0x4014 => self.cpu.open_bus,
+            0x4016 => self.peek_joypad_state(false) | (self.cpu.open_bus & 0xE0),
+            0x4017 => self.peek_joypad_state(true) | (self.cpu.open_bus & 0xE0),
+            0x4018..=0xFFFF => self.cartridge.peek_prg(addr).unwrap_or(self.cpu.open_bus),
+        }
+    }
+
+    /// Overwrites RAM (WRAM and PRG-RAM) or patches PRG-ROM at `addr`.
+    /// I/O registers are left untouched.
+    pub fn poke_cpu(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.cpu.wram[(addr % 0x800) as usize] = value,
+            0x2000..=0x4017 => {}
+            0x4018..=0xFFFF => self.cartridge.poke_prg(addr, value),
+        }
+    }
+
+    /// Samples the interrupt lines. Runs at the start of every cycle, so it sees the
+    /// state left by the previous cycle, as the 6502 does at the end of each cycle.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn poll_interrupts(&mut self) {
+        if self.cpu.control.NMI && !self.cpu.prev_nmi {
+            self.cpu.need_nmi = true;
+        }
+        self.cpu.prev_nmi = self.cpu.control.NMI;
+        self.cpu.prev_run_irq = self.cpu.run_irq;
+        self.cpu.run_irq = self.cpu.control.IRQ && !self.cpu.register.P.I;
+    }
+
+    pub fn tick(&mut self) {
+        if self.cpu.step.mode != StepMode::Run && !self.step_tick() {
+            return;
+        }
+        self.poll_interrupts();
+        self.cpu.ppu_clock_phase += self.region.ppu_dots_per_5_cycles();
+        while self.cpu.ppu_clock_phase >= 5 {
+            self.ppu_step();
+            self.cpu.ppu_clock_phase -= 5;
+        }
+        self.apu_step();
+        self.cpu.cycles += 1;
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn read_cpu(&mut self, addr: u16) -> u8 {
+        self.process_pending_dma(addr);
         self.tick();
-        self.read_cpu_bus(addr)
+        self.step_access(|nes| {
+            if nes.cdl.enabled && addr >= 0x8000 {
+                nes.log_prg(addr);
+            }
+            nes.read_cpu_bus(addr)
+        })
+    }
+
+    /// A bus read whose value the CPU throws away. It has the side effects of a
+    /// real read and lets DMA halt the CPU, but is not logged by the CDL.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn dummy_read(&mut self, addr: u16) {
+        self.process_pending_dma(addr);
+        self.tick();
+        self.step_access(|nes| nes.read_cpu_bus(addr));
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn write_cpu(&mut self, addr: u16, value: u8) -> u8 {
         self.tick();
-        self.write_cpu_bus(addr, value)
+        self.step_access(|nes| nes.write_cpu_bus(addr, value))
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -124,24 +231,16 @@ impl NesState {
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn push(&mut self, val: u8) -> u8 {
         let value = self.write_cpu(0x100 + self.cpu.register.S as u16, val);
-        self.cpu.register.S -= 1;
+        self.cpu.register.S = self.cpu.register.S.wrapping_sub(1);
         value
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn pop(&mut self) -> u8 {
-        self.cpu.register.S += 1;
+        self.cpu.register.S = self.cpu.register.S.wrapping_add(1);
         self.read_cpu(0x100 + self.cpu.register.S as u16)
     }
 
-    #[cfg_attr(not(debug_assertions), inline(always))]
-    fn dma_oam(&mut self, bank: u8) {
-        for i in 0..256 {
-            let value = self.read_cpu((bank as u16) * 0x100 + (i as u16));
-            self.write_cpu(0x2014, value);
-        }
-    }
-
     /// Addressing Modes
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn imm(&mut self) -> u16 {
@@ -163,28 +262,40 @@ impl NesState {
         self.read16(addr)
     }
 
+    /// Adds `index` to `base`. The CPU first reads from the address with the carry
+    /// not yet applied to the high byte, which writes and RMW instructions
+    /// always do and reads only do when crossing a page.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn indexed(&mut self, base: u16, index: u8, always_dummy_read: bool) -> u16 {
+        let addr = base.wrapping_add(index as u16);
+        if always_dummy_read || self.cpu.cross_page(base, index) {
+            self.dummy_read((base & 0xFF00) | (addr & 0x00FF));
+        }
+        addr
+    }
+
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn _abx(&mut self) -> u16 {
-        self.tick();
-        self.abs() + self.cpu.register.X as u16
+        let base = self.abs();
+        self.indexed(base, self.cpu.register.X, true)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn abx(&mut self) -> u16 {
-        let addr = self.abs();
-        if self.cpu.cross_page(addr, self.cpu.register.X) {
-            self.tick();
-        }
-        addr.wrapping_add(self.cpu.register.X as u16)
+        let base = self.abs();
+        self.indexed(base, self.cpu.register.X, false)
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn _aby(&mut self) -> u16 {
+        let base = self.abs();
+        self.indexed(base, self.cpu.register.Y, true)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn aby(&mut self) -> u16 {
-        let addr = self.abs();
-        if self.cpu.cross_page(addr, self.cpu.register.Y) {
-            self.tick();
-        }
-        addr.wrapping_add(self.cpu.register.Y as u16)
+        let base = self.abs();
+        self.indexed(base, self.cpu.register.Y, false)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -195,39 +306,45 @@ impl NesState {
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn zpx(&mut self) -> u16 {
-        self.tick();
-        (self.zp() + self.cpu.register.X as u16) & 0xFF
+        let base = self.zp();
+        self.dummy_read(base);
+        (base + self.cpu.register.X as u16) & 0xFF
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn zpy(&mut self) -> u16 {
-        self.tick();
-        (self.zp() + self.cpu.register.Y as u16) & 0xFF
+        let base = self.zp();
+        self.dummy_read(base);
+        (base + self.cpu.register.Y as u16) & 0xFF
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn izx(&mut self) -> u16 {
         let addr = self.zpx();
-        self.read16_little_endian(addr, (addr + 1) & 0xFF)
+        let addr = self.read16_little_endian(addr, (addr + 1) & 0xFF);
+        self.cdl.indirect_data = true;
+        addr
     }
 
+    /// Reads the (zp) pointer of `(zp),Y`.
     #[cfg_attr(not(debug_assertions), inline(always))]
-    fn _izy(&mut self) -> u16 {
+    fn iz(&mut self) -> u16 {
         let addr = self.zp();
-        self.read16_little_endian(addr, (addr + 1) & 0xFF)
-            .wrapping_add(self.cpu.register.Y as u16)
+        let addr = self.read16_little_endian(addr, (addr + 1) & 0xFF);
+        self.cdl.indirect_data = true;
+        addr
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn _izy(&mut self) -> u16 {
+        let base = self.iz();
+        self.indexed(base, self.cpu.register.Y, true)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn izy(&mut self) -> u16 {
-        let addr = self._izy();
-        if self.cpu.cross_page(
-            addr.wrapping_sub(self.cpu.register.Y as u16),
-            self.cpu.register.Y,
-        ) {
-            self.tick();
-        }
-        addr
+        let base = self.iz();
+        self.indexed(base, self.cpu.register.Y, false)
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -237,30 +354,6 @@ impl NesState {
         self.write_cpu(addr, val);
     }
 
-    #[allow(non_snake_case)]
-    #[cfg_attr(not(debug_assertions), inline(always))]
-    fn st_A_izy(&mut self) {
-        self.tick();
-        let addr = self._izy();
-        self.write_cpu(addr, self.cpu.register.A);
-    }
-
-    #[allow(non_snake_case)]
-    #[cfg_attr(not(debug_assertions), inline(always))]
-    fn st_A_abx(&mut self) {
-        self.tick();
-        let addr = self.abs() + self.cpu.register.X as u16;
-        self.write_cpu(addr, self.cpu.register.A);
-    }
-
-    #[allow(non_snake_case)]
-    #[cfg_attr(not(debug_assertions), inline(always))]
-    fn st_A_aby(&mut self) {
-        self.tick();
-        let addr = self.abs() + self.cpu.register.Y as u16;
-        self.write_cpu(addr, self.cpu.register.A);
-    }
-
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn G(&mut self, addr_fn: fn(&mut Self) -> u16) -> (u16, u8) {
@@ -489,7 +582,7 @@ impl NesState {
     fn ASL(&mut self, addr_fn: fn(&mut Self) -> u16) {
         let (addr, val) = self.G(addr_fn);
         self.cpu.register.P.C = (val & 0x80).as_bool();
-        self.tick();
+        self.write_cpu(addr, val);
         let res = self.write_cpu(addr, val << 1);
         self.cpu.update_NZ(res);
     }
@@ -499,7 +592,7 @@ impl NesState {
     fn LSR(&mut self, addr_fn: fn(&mut Self) -> u16) {
         let (addr, val) = self.G(addr_fn);
         self.cpu.register.P.C = (val & 0x01).as_bool();
-        self.tick();
+        self.write_cpu(addr, val);
         let res = self.write_cpu(addr, val >> 1);
         self.cpu.update_NZ(res);
     }
@@ -510,7 +603,7 @@ impl NesState {
         let (addr, val) = self.G(addr_fn);
         let c = self.cpu.register.P.C.as_u8();
         self.cpu.register.P.C = (val & 0x80).as_bool();
-        self.tick();
+        self.write_cpu(addr, val);
         let res = self.write_cpu(addr, (val << 1) | c);
         self.cpu.update_NZ(res);
     }
@@ -521,7 +614,7 @@ impl NesState {
         let (addr, val) = self.G(addr_fn);
         let c = self.cpu.register.P.C.as_u8() << 7;
         self.cpu.register.P.C = (val & 0x01).as_bool();
-        self.tick();
+        self.write_cpu(addr, val);
         let res = self.write_cpu(addr, (val >> 1) | c);
         self.cpu.update_NZ(res);
     }
@@ -530,7 +623,7 @@ impl NesState {
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn DEC(&mut self, addr_fn: fn(&mut Self) -> u16) {
         let (addr, val) = self.G(addr_fn);
-        self.tick();
+        self.write_cpu(addr, val);
         let res = self.write_cpu(addr, val.wrapping_sub(1));
         self.cpu.update_NZ(res);
     }
@@ -539,7 +632,7 @@ impl NesState {
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn INC(&mut self, addr_fn: fn(&mut Self) -> u16) {
         let (addr, val) = self.G(addr_fn);
-        self.tick();
+        self.write_cpu(addr, val);
         let res = self.write_cpu(addr, val.wrapping_add(1));
         self.cpu.update_NZ(res);
     }
@@ -549,7 +642,7 @@ impl NesState {
         let res = register_getter(self).wrapping_sub(1);
         register_setter(self, res);
         self.cpu.update_NZ(res);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -557,7 +650,7 @@ impl NesState {
         let res = register_getter(self).wrapping_add(1);
         register_setter(self, res);
         self.cpu.update_NZ(res);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[allow(non_snake_case)]
@@ -566,7 +659,7 @@ impl NesState {
         self.cpu.register.P.C = (self.cpu.register.A & 0x80).as_bool();
         self.cpu.register.A <<= 1;
         self.cpu.update_NZ(self.cpu.register.A);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[allow(non_snake_case)]
@@ -575,7 +668,7 @@ impl NesState {
         self.cpu.register.P.C = (self.cpu.register.A & 0x01).as_bool();
         self.cpu.register.A >>= 1;
         self.cpu.update_NZ(self.cpu.register.A);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[allow(non_snake_case)]
@@ -585,7 +678,7 @@ impl NesState {
         self.cpu.register.P.C = (self.cpu.register.A & 0x80).as_bool();
         self.cpu.register.A = (self.cpu.register.A << 1) | c;
         self.cpu.update_NZ(self.cpu.register.A);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[allow(non_snake_case)]
@@ -595,7 +688,7 @@ impl NesState {
         self.cpu.register.P.C = (self.cpu.register.A & 0x01).as_bool();
         self.cpu.register.A = (self.cpu.register.A >> 1) | c;
         self.cpu.update_NZ(self.cpu.register.A);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -603,21 +696,21 @@ impl NesState {
         let res = register_getter(self);
         register_setter(self, res);
         self.cpu.update_NZ(res);
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn tr_X_S(&mut self) {
         self.cpu.register.S = self.cpu.register.X;
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn PLP(&mut self) {
-        self.tick();
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
+        self.dummy_read(0x100 + self.cpu.register.S as u16);
         let val = self.pop();
         self.cpu.register.P.set_u8(val & 0b1110_1111);
     }
@@ -625,7 +718,7 @@ impl NesState {
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn PHP(&mut self) {
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
         let val = self.cpu.register.P.get_u8() | 0b0011_0000;
         self.push(val);
     }
@@ -633,8 +726,8 @@ impl NesState {
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn PLA(&mut self) {
-        self.tick();
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
+        self.dummy_read(0x100 + self.cpu.register.S as u16);
         let val = self.pop();
         self.cpu.register.A = val;
         self.cpu.update_NZ(self.cpu.register.A);
@@ -643,7 +736,7 @@ impl NesState {
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn PHA(&mut self) {
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
         self.push(self.cpu.register.A);
     }
 
@@ -652,15 +745,18 @@ impl NesState {
         let addr = self.imm();
         let imm = self.read_cpu(addr) as i8;
         if status_flag_getter(self) == val {
-            if self.cpu.cross_page_i8(self.cpu.register.PC, imm) {
-                self.tick();
+            let pc = self.cpu.register.PC;
+            self.dummy_read(pc);
+            // a taken branch ignores an IRQ that shows up during its operand fetch,
+            // unless it crosses a page and polls again
+            if self.cpu.run_irq && !self.cpu.prev_run_irq {
+                self.cpu.run_irq = false;
             }
-            self.tick();
-            if imm >= 0 {
-                self.cpu.register.PC = self.cpu.register.PC.wrapping_add(imm as u16);
-            } else {
-                self.cpu.register.PC = self.cpu.register.PC.wrapping_sub(imm.wrapping_neg() as u16);
+            let target = pc.wrapping_add(imm as i16 as u16);
+            if self.cpu.cross_page_i8(pc, imm) {
+                self.dummy_read((pc & 0xFF00) | (target & 0x00FF));
             }
+            self.cpu.register.PC = target;
         }
     }
 
@@ -670,6 +766,7 @@ impl NesState {
         let addr = self.imm16();
         let imm = self.read16(addr);
         self.cpu.register.PC = self.read16_little_endian(imm, (imm & 0xFF00) | ((imm + 1) & 0xFF));
+        self.cdl.indirect_jump();
     }
 
     #[allow(non_snake_case)]
@@ -683,23 +780,28 @@ impl NesState {
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn JSR(&mut self) {
-        let pc = self.cpu.register.PC + 1;
+        let addr = self.imm();
+        let addr_l = self.read_cpu(addr);
+        self.dummy_read(0x100 + self.cpu.register.S as u16);
+        // the return address is the last byte of JSR
+        let pc = self.cpu.register.PC;
         self.push((pc >> 8) as u8);
         self.push(pc as u8);
-        let addr = self.imm16();
-        let imm = self.read16(addr);
-        self.cpu.register.PC = imm;
+        let addr = self.imm();
+        let addr_h = self.read_cpu(addr);
+        self.cpu.register.PC = get_little_endian(addr_l, addr_h);
     }
 
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn RTS(&mut self) {
-        self.tick();
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
+        self.dummy_read(0x100 + self.cpu.register.S as u16);
         let addr_l = self.pop() as u16;
         let addr_h = self.pop() as u16;
-        self.cpu.register.PC = ((addr_h << 8) | addr_l) + 1;
-        self.tick();
+        let pc = (addr_h << 8) | addr_l;
+        self.dummy_read(pc);
+        self.cpu.register.PC = pc.wrapping_add(1);
     }
 
     #[allow(non_snake_case)]
@@ -713,52 +815,62 @@ impl NesState {
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn flag(&mut self, status_flag_setter: fn(&mut Self, bool), val: bool) {
+        // the flag changes after the poll, so CLI/SEI take effect one instruction late
+        self.dummy_read(self.cpu.register.PC);
         status_flag_setter(self, val);
-        self.tick();
     }
 
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn INT(&mut self, t: InterruptionType) {
-        self.tick();
-        if t != InterruptionType::BRK {
-            self.tick();
+        // vector reads are data
+        self.cdl.code_start = self.cpu.register.PC;
+        // BRK skips its padding byte; IRQ and NMI replace an opcode fetch that
+        // is thrown away without moving PC
+        self.dummy_read(self.cpu.register.PC);
+        if t == InterruptionType::BRK {
+            self.cpu.register.PC = self.cpu.register.PC.wrapping_add(1);
+        } else {
+            self.dummy_read(self.cpu.register.PC);
         }
+        let mut nmi = t == InterruptionType::NMI;
         if t != InterruptionType::RESET {
             let pc = self.cpu.register.PC;
             self.push((pc >> 8) as u8);
             self.push(pc as u8);
-            let p = self.cpu.register.P.get_u8();
+            // an NMI showing up by now hijacks BRK and IRQ to its vector
+            nmi |= self.cpu.need_nmi;
+            // only BRK pushes the B flag set
+            let p = self.cpu.register.P.get_u8() & 0b1110_1111;
             self.push(p | ((t == InterruptionType::BRK).as_u8() << 4));
         } else {
-            self.cpu.register.S = self.cpu.register.S.wrapping_sub(3);
-            self.tick();
-            self.tick();
-            self.tick();
+            // reset reads the stack instead of writing it
+            for _ in 0..3 {
+                self.dummy_read(0x100 + self.cpu.register.S as u16);
+                self.cpu.register.S = self.cpu.register.S.wrapping_sub(1);
+            }
         }
         self.cpu.register.P.I = true;
         let addr = match t {
-            InterruptionType::NMI => 0xFFFA,
             InterruptionType::RESET => 0xFFFC,
-            InterruptionType::IRQ => 0xFFFE,
-            InterruptionType::BRK => 0xFFFE,
+            _ if nmi => 0xFFFA,
+            _ => 0xFFFE,
         };
-        self.cpu.register.PC = self.read16(addr);
-        if t == InterruptionType::NMI {
-            self.cpu.control.NMI = false;
+        if nmi {
+            self.cpu.need_nmi = false;
         }
+        self.cpu.register.PC = self.read16(addr);
     }
 
     #[allow(non_snake_case)]
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn NOP(&mut self) {
-        self.tick();
+        self.dummy_read(self.cpu.register.PC);
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn nop(&mut self, addr_fn: fn(&mut Self) -> u16) {
         self.G(addr_fn);
-        self.tick();
     }
 
     #[allow(non_snake_case)]
@@ -768,7 +880,7 @@ impl NesState {
         let (addr, val) = self.G(addr_fn);
         self.cpu.register.P.C = (val & 0x80).as_bool();
         let res = val << 1;
-        self.tick();
+        self.write_cpu(addr, val);
         self.write_cpu(addr, res);
         self.cpu.register.A |= res;
         self.cpu.update_NZ(self.cpu.register.A);
@@ -782,7 +894,7 @@ impl NesState {
         let c = self.cpu.register.P.C.as_u8();
         self.cpu.register.P.C = (val & 0x80).as_bool();
         let res = (val << 1) | c;
-        self.tick();
+        self.write_cpu(addr, val);
         self.write_cpu(addr, res);
         self.cpu.register.A &= res;
         self.cpu.update_NZ(self.cpu.register.A);
@@ -795,7 +907,7 @@ impl NesState {
         let (addr, val) = self.G(addr_fn);
         self.cpu.register.P.C = (val & 0x01).as_bool();
         let res = val >> 1;
-        self.tick();
+        self.write_cpu(addr, val);
         self.write_cpu(addr, res);
         self.cpu.register.A ^= res;
         self.cpu.update_NZ(self.cpu.register.A);
@@ -809,7 +921,7 @@ impl NesState {
         let c = self.cpu.register.P.C.as_u8() << 7;
         self.cpu.register.P.C = (val & 0x01).as_bool();
         let res = (val >> 1) | c;
-        self.tick();
+        self.write_cpu(addr, val);
         self.write_cpu(addr, res);
         let res_a = self.cpu.register.A as i16 + res as i16 + self.cpu.register.P.C as i16;
         self.cpu.register.A = res_a as u8;
@@ -838,7 +950,7 @@ impl NesState {
         // DEC + CMP
         let (addr, val) = self.G(addr_fn);
         let res = val.wrapping_sub(1);
-        self.tick();
+        self.write_cpu(addr, val);
         self.write_cpu(addr, res);
         self.cpu.update_NZ(self.cpu.register.A.wrapping_sub(res));
         self.cpu.register.P.C = self.cpu.register.A >= res;
@@ -850,7 +962,7 @@ impl NesState {
         // INC + SBC
         let (addr, val) = self.G(addr_fn);
         let res = val.wrapping_add(1);
-        self.tick();
+        self.write_cpu(addr, val);
         self.write_cpu(addr, res);
         self.cpu.update_NZ(res);
         let res = !res;
@@ -860,14 +972,150 @@ impl NesState {
         self.cpu.update_NZ(res_a as u8);
     }
 
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn ANC(&mut self) {
+        // AND + copy N to C
+        let (_, val) = self.G(Self::imm);
+        self.cpu.register.A &= val;
+        self.cpu.update_NZ(self.cpu.register.A);
+        self.cpu.register.P.C = self.cpu.register.P.N;
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn ALR(&mut self) {
+        // AND + LSR A
+        let (_, val) = self.G(Self::imm);
+        let res = self.cpu.register.A & val;
+        self.cpu.register.P.C = (res & 0x01).as_bool();
+        self.cpu.register.A = res >> 1;
+        self.cpu.update_NZ(self.cpu.register.A);
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn ARR(&mut self) {
+        // AND + ROR A, with C and V taken from bits 6 and 5 of the result
+        let (_, val) = self.G(Self::imm);
+        let c = self.cpu.register.P.C.as_u8() << 7;
+        let res = ((self.cpu.register.A & val) >> 1) | c;
+        self.cpu.register.A = res;
+        self.cpu.update_NZ(res);
+        self.cpu.register.P.C = (res & 0x40).as_bool();
+        self.cpu.register.P.V = ((res >> 6) ^ (res >> 5)) & 1 == 1;
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn AXS(&mut self) {
+        // X = (A & X) - imm, flags like CMP
+        let (_, val) = self.G(Self::imm);
+        let ax = self.cpu.register.A & self.cpu.register.X;
+        self.cpu.register.P.C = ax >= val;
+        self.cpu.register.X = ax.wrapping_sub(val);
+        self.cpu.update_NZ(self.cpu.register.X);
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn LAS(&mut self) {
+        let (_, val) = self.G(Self::aby);
+        let res = val & self.cpu.register.S;
+        self.cpu.register.A = res;
+        self.cpu.register.X = res;
+        self.cpu.register.S = res;
+        self.cpu.update_NZ(res);
+    }
+
+    /// ANE (XAA) and LXA (LAX #imm) mix A into the result through an unstable
+    /// constant that depends on the chip; $EE is the commonly documented one.
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn ANE(&mut self) {
+        let (_, val) = self.G(Self::imm);
+        self.cpu.register.A = (self.cpu.register.A | 0xEE) & self.cpu.register.X & val;
+        self.cpu.update_NZ(self.cpu.register.A);
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn LXA(&mut self) {
+        let (_, val) = self.G(Self::imm);
+        let res = (self.cpu.register.A | 0xEE) & val;
+        self.cpu.register.A = res;
+        self.cpu.register.X = res;
+        self.cpu.update_NZ(res);
+    }
+
+    /// Stores `val & (H + 1)`, H being the high byte of the unindexed address.
+    /// When indexing crosses a page, that value also replaces the high byte of the address.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn sh(&mut self, base: u16, index: u8, val: u8) {
+        let addr = base.wrapping_add(index as u16);
+        self.dummy_read((base & 0xFF00) | (addr & 0x00FF));
+        let val = val & ((base >> 8) as u8).wrapping_add(1);
+        let addr = if self.cpu.cross_page(base, index) {
+            ((val as u16) << 8) | (addr & 0x00FF)
+        } else {
+            addr
+        };
+        self.write_cpu(addr, val);
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn SHA(&mut self, base_fn: fn(&mut Self) -> u16) {
+        let base = base_fn(self);
+        self.sh(
+            base,
+            self.cpu.register.Y,
+            self.cpu.register.A & self.cpu.register.X,
+        );
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn SHX(&mut self) {
+        let base = self.abs();
+        self.sh(base, self.cpu.register.Y, self.cpu.register.X);
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn SHY(&mut self) {
+        let base = self.abs();
+        self.sh(base, self.cpu.register.X, self.cpu.register.Y);
+    }
+
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn TAS(&mut self) {
+        // S = A & X, then SHA
+        let base = self.abs();
+        self.cpu.register.S = self.cpu.register.A & self.cpu.register.X;
+        self.sh(base, self.cpu.register.Y, self.cpu.register.S);
+    }
+
+    /// KIL/JAM: the CPU locks up with PC on the opcode until it is reset.
+    #[allow(non_snake_case)]
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn JAM(&mut self) {
+        self.dummy_read(self.cpu.register.PC);
+        self.cpu.register.PC = self.cpu.register.PC.wrapping_sub(1);
+        self.cpu.jam = true;
+    }
+
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn exec(&mut self) {
         let pc = self.cpu.register.PC;
-        let val = self.read_cpu(pc);
+        self.cdl.begin_instruction(pc);
         self.cpu.register.PC += 1;
+        let val = self.read_cpu(pc);
         match val {
             0x00 => self.INT(InterruptionType::BRK),
             0x01 => self.OR(Self::izx),
+            0x02 => self.JAM(),
             0x03 => self.SLO(Self::izx),
             0x04 => self.nop(Self::zp),
             0x05 => self.OR(Self::zp),
@@ -876,27 +1124,30 @@ impl NesState {
             0x08 => self.PHP(),
             0x09 => self.OR(Self::imm),
             0x0A => self.ASL_A(),
+            0x0B => self.ANC(),
             0x0C => self.nop(Self::abs),
             0x0D => self.OR(Self::abs),
             0x0E => self.ASL(Self::abs),
             0x0F => self.SLO(Self::abs),
             0x10 => self.br(Self::get_P_N, false),
             0x11 => self.OR(Self::izy),
-            0x13 => self.SLO(Self::izy),
+            0x12 => self.JAM(),
+            0x13 => self.SLO(Self::_izy),
             0x14 => self.nop(Self::zpx),
             0x15 => self.OR(Self::zpx),
-            0x17 => self.SLO(Self::zpx),
             0x16 => self.ASL(Self::zpx),
+            0x17 => self.SLO(Self::zpx),
             0x18 => self.flag(Self::set_P_C, false),
             0x19 => self.OR(Self::aby),
             0x1A => self.NOP(),
-            0x1B => self.SLO(Self::aby),
+            0x1B => self.SLO(Self::_aby),
             0x1C => self.nop(Self::abx),
             0x1D => self.OR(Self::abx),
             0x1E => self.ASL(Self::_abx),
-            0x1F => self.SLO(Self::abx),
+            0x1F => self.SLO(Self::_abx),
             0x20 => self.JSR(),
             0x21 => self.AND(Self::izx),
+            0x22 => self.JAM(),
             0x23 => self.RLA(Self::izx),
             0x24 => self.BIT(Self::zp),
             0x25 => self.AND(Self::zp),
@@ -905,13 +1156,15 @@ impl NesState {
             0x28 => self.PLP(),
             0x29 => self.AND(Self::imm),
             0x2A => self.ROL_A(),
+            0x2B => self.ANC(),
             0x2C => self.BIT(Self::abs),
             0x2D => self.AND(Self::abs),
             0x2E => self.ROL(Self::abs),
             0x2F => self.RLA(Self::abs),
             0x30 => self.br(Self::get_P_N, true),
             0x31 => self.AND(Self::izy),
-            0x33 => self.RLA(Self::izy),
+            0x32 => self.JAM(),
+            0x33 => self.RLA(Self::_izy),
             0x34 => self.nop(Self::zpx),
             0x35 => self.AND(Self::zpx),
             0x36 => self.ROL(Self::zpx),
@@ -919,13 +1172,14 @@ impl NesState {
             0x38 => self.flag(Self::set_P_C, true),
             0x39 => self.AND(Self::aby),
             0x3A => self.NOP(),
-            0x3B => self.RLA(Self::aby),
+            0x3B => self.RLA(Self::_aby),
             0x3C => self.nop(Self::abx),
             0x3D => self.AND(Self::abx),
             0x3E => self.ROL(Self::_abx),
-            0x3F => self.RLA(Self::abx),
+            0x3F => self.RLA(Self::_abx),
             0x40 => self.RTI(),
             0x41 => self.XOR(Self::izx),
+            0x42 => self.JAM(),
             0x43 => self.SRE(Self::izx),
             0x44 => self.nop(Self::zp),
             0x45 => self.XOR(Self::zp),
@@ -934,13 +1188,15 @@ impl NesState {
             0x48 => self.PHA(),
             0x49 => self.XOR(Self::imm),
             0x4A => self.LSR_A(),
+            0x4B => self.ALR(),
             0x4C => self.JMP(),
             0x4D => self.XOR(Self::abs),
             0x4E => self.LSR(Self::abs),
             0x4F => self.SRE(Self::abs),
             0x50 => self.br(Self::get_P_V, false),
             0x51 => self.XOR(Self::izy),
-            0x53 => self.SRE(Self::izy),
+            0x52 => self.JAM(),
+            0x53 => self.SRE(Self::_izy),
             0x54 => self.nop(Self::zpx),
             0x55 => self.XOR(Self::zpx),
             0x56 => self.LSR(Self::zpx),
@@ -948,13 +1204,14 @@ impl NesState {
             0x58 => self.flag(Self::set_P_I, false),
             0x59 => self.XOR(Self::aby),
             0x5A => self.NOP(),
-            0x5B => self.SRE(Self::aby),
+            0x5B => self.SRE(Self::_aby),
             0x5C => self.nop(Self::abx),
             0x5D => self.XOR(Self::abx),
             0x5E => self.LSR(Self::_abx),
-            0x5F => self.SRE(Self::abx),
+            0x5F => self.SRE(Self::_abx),
             0x60 => self.RTS(),
             0x61 => self.ADC(Self::izx),
+            0x62 => self.JAM(),
             0x63 => self.RRA(Self::izx),
             0x64 => self.nop(Self::zp),
             0x65 => self.ADC(Self::zp),
@@ -963,13 +1220,15 @@ impl NesState {
             0x68 => self.PLA(),
             0x69 => self.ADC(Self::imm),
             0x6A => self.ROR_A(),
+            0x6B => self.ARR(),
             0x6C => self.JMP_IND(),
             0x6D => self.ADC(Self::abs),
             0x6E => self.ROR(Self::abs),
             0x6F => self.RRA(Self::abs),
             0x70 => self.br(Self::get_P_V, true),
             0x71 => self.ADC(Self::izy),
-            0x73 => self.RRA(Self::izy),
+            0x72 => self.JAM(),
+            0x73 => self.RRA(Self::_izy),
             0x74 => self.nop(Self::zpx),
             0x75 => self.ADC(Self::zpx),
             0x76 => self.ROR(Self::zpx),
@@ -977,11 +1236,11 @@ impl NesState {
             0x78 => self.flag(Self::set_P_I, true),
             0x79 => self.ADC(Self::aby),
             0x7A => self.NOP(),
-            0x7B => self.RRA(Self::aby),
+            0x7B => self.RRA(Self::_aby),
             0x7C => self.nop(Self::abx),
             0x7D => self.ADC(Self::abx),
             0x7E => self.ROR(Self::_abx),
-            0x7F => self.RRA(Self::abx),
+            0x7F => self.RRA(Self::_abx),
             0x80 => self.nop(Self::imm),
             0x81 => self.st(Self::get_A, Self::izx),
             0x82 => self.nop(Self::imm),
@@ -993,20 +1252,27 @@ impl NesState {
             0x88 => self.dec(Self::get_Y, Self::set_Y),
             0x89 => self.nop(Self::imm),
             0x8A => self.tr(Self::get_X, Self::set_A),
+            0x8B => self.ANE(),
             0x8C => self.st(Self::get_Y, Self::abs),
             0x8D => self.st(Self::get_A, Self::abs),
             0x8E => self.st(Self::get_X, Self::abs),
             0x8F => self.SAX(Self::abs),
             0x90 => self.br(Self::get_P_C, false),
-            0x91 => self.st_A_izy(),
+            0x91 => self.st(Self::get_A, Self::_izy),
+            0x92 => self.JAM(),
+            0x93 => self.SHA(Self::iz),
             0x94 => self.st(Self::get_Y, Self::zpx),
             0x95 => self.st(Self::get_A, Self::zpx),
             0x96 => self.st(Self::get_X, Self::zpy),
             0x97 => self.SAX(Self::zpy),
             0x98 => self.tr(Self::get_Y, Self::set_A),
-            0x99 => self.st_A_aby(),
+            0x99 => self.st(Self::get_A, Self::_aby),
             0x9A => self.tr_X_S(),
-            0x9D => self.st_A_abx(),
+            0x9B => self.TAS(),
+            0x9C => self.SHY(),
+            0x9D => self.st(Self::get_A, Self::_abx),
+            0x9E => self.SHX(),
+            0x9F => self.SHA(Self::abs),
             0xA0 => self.ld(Self::imm, Self::set_Y),
             0xA1 => self.ld(Self::izx, Self::set_A),
             0xA2 => self.ld(Self::imm, Self::set_X),
@@ -1018,13 +1284,14 @@ impl NesState {
             0xA8 => self.tr(Self::get_A, Self::set_Y),
             0xA9 => self.ld(Self::imm, Self::set_A),
             0xAA => self.tr(Self::get_A, Self::set_X),
-            0xAB => self.LAX(Self::imm),
+            0xAB => self.LXA(),
             0xAC => self.ld(Self::abs, Self::set_Y),
             0xAD => self.ld(Self::abs, Self::set_A),
             0xAE => self.ld(Self::abs, Self::set_X),
             0xAF => self.LAX(Self::abs),
             0xB0 => self.br(Self::get_P_C, true),
             0xB1 => self.ld(Self::izy, Self::set_A),
+            0xB2 => self.JAM(),
             0xB3 => self.LAX(Self::izy),
             0xB4 => self.ld(Self::zpx, Self::set_Y),
             0xB5 => self.ld(Self::zpx, Self::set_A),
@@ -1033,6 +1300,7 @@ impl NesState {
             0xB8 => self.flag(Self::set_P_V, false),
             0xB9 => self.ld(Self::aby, Self::set_A),
             0xBA => self.tr(Self::get_S, Self::set_X),
+            0xBB => self.LAS(),
             0xBC => self.ld(Self::abx, Self::set_Y),
             0xBD => self.ld(Self::abx, Self::set_A),
             0xBE => self.ld(Self::aby, Self::set_X),
@@ -1048,13 +1316,15 @@ impl NesState {
             0xC8 => self.inc(Self::get_Y, Self::set_Y),
             0xC9 => self.cmp(Self::imm, Self::get_A),
             0xCA => self.dec(Self::get_X, Self::set_X),
+            0xCB => self.AXS(),
             0xCC => self.cmp(Self::abs, Self::get_Y),
             0xCD => self.cmp(Self::abs, Self::get_A),
             0xCE => self.DEC(Self::abs),
             0xCF => self.DCP(Self::abs),
             0xD0 => self.br(Self::get_P_Z, false),
             0xD1 => self.cmp(Self::izy, Self::get_A),
-            0xD3 => self.DCP(Self::izy),
+            0xD2 => self.JAM(),
+            0xD3 => self.DCP(Self::_izy),
             0xD4 => self.nop(Self::zpx),
             0xD5 => self.cmp(Self::zpx, Self::get_A),
             0xD6 => self.DEC(Self::zpx),
@@ -1062,11 +1332,11 @@ impl NesState {
             0xD8 => self.flag(Self::set_P_D, false),
             0xD9 => self.cmp(Self::aby, Self::get_A),
             0xDA => self.NOP(),
-            0xDB => self.DCP(Self::aby),
+            0xDB => self.DCP(Self::_aby),
             0xDC => self.nop(Self::abx),
             0xDD => self.cmp(Self::abx, Self::get_A),
             0xDE => self.DEC(Self::_abx),
-            0xDF => self.DCP(Self::abx),
+            0xDF => self.DCP(Self::_abx),
             0xE0 => self.cmp(Self::imm, Self::get_X),
             0xE1 => self.SBC(Self::izx),
             0xE2 => self.nop(Self::imm),
@@ -1085,7 +1355,8 @@ impl NesState {
             0xEF => self.ISC(Self::abs),
             0xF0 => self.br(Self::get_P_Z, true),
             0xF1 => self.SBC(Self::izy),
-            0xF3 => self.ISC(Self::izy),
+            0xF2 => self.JAM(),
+            0xF3 => self.ISC(Self::_izy),
             0xF4 => self.nop(Self::zpx),
             0xF5 => self.SBC(Self::zpx),
             0xF6 => self.INC(Self::zpx),
@@ -1093,31 +1364,91 @@ impl NesState {
             0xF8 => self.flag(Self::set_P_D, true),
             0xF9 => self.SBC(Self::aby),
             0xFA => self.NOP(),
-            0xFB => self.ISC(Self::aby),
+            0xFB => self.ISC(Self::_aby),
             0xFC => self.nop(Self::abx),
             0xFD => self.SBC(Self::abx),
             0xFE => self.INC(Self::_abx),
-            0xFF => self.ISC(Self::abx),
-            _ => panic!("{:?}", (val, &self.cpu.register)),
+            0xFF => self.ISC(Self::_abx),
         }
     }
 
     pub fn power(&mut self) {
+        self.cpu.step = StepState::default();
+        self.cpu.jam = false;
         self.cpu.register.P.B = true;
         self.INT(InterruptionType::RESET);
     }
 
-    pub fn run_frame(&mut self) {
-        self.cpu.remaining_cycles += TOTAL_CYCLES as i32;
+    /// Executes one instruction, or enters a pending interrupt and executes the
+    /// first instruction of its handler. Returns the CPU cycles taken.
+    /// A jammed CPU only lets one cycle pass.
+    pub fn step_instruction(&mut self) -> u32 {
+        let start = self.cpu.cycles;
+        if self.cpu.step.start.is_some() {
+            // finish what step_cycle started
+            self.begin_step(StepMode::Run);
+            self.run_instruction();
+            self.end_step();
+        } else {
+            self.run_instruction();
+        }
+        (self.cpu.cycles - start) as u32
+    }
+
+    /// Advances the CPU by one cycle, stopping in the middle of an instruction or DMA
+    /// when needed. Returns the CPU cycles taken, which is 1.
+    pub fn step_cycle(&mut self) -> u32 {
+        let start = self.cpu.cycles;
+        self.begin_step(StepMode::Record);
+        self.cpu.step.ticks_left = 1;
+        self.run_instruction();
+        self.end_step();
+        (self.cpu.cycles - start) as u32
+    }
 
-        while self.cpu.remaining_cycles > 0 {
-            if self.cpu.control.NMI {
-                self.INT(InterruptionType::NMI)
-            } else if self.cpu.control.IRQ && !self.cpu.register.P.I {
-                self.INT(InterruptionType::IRQ)
+    fn run_instruction(&mut self) {
+        if self.cpu.jam {
+            // the clock keeps running while the CPU is stuck
+            self.dummy_read(0xFFFF);
+            return;
+        }
+        // what was polled before the last cycle of the previous instruction
+        if self.cpu.need_nmi {
+            self.INT(InterruptionType::NMI)
+        } else if self.cpu.run_irq {
+            self.INT(InterruptionType::IRQ)
+        }
+        self.exec();
+    }
+
+    /// Runs until the PPU enters `scanline` (0~261, or 0~311 on PAL/Dendy),
+    /// and returns the CPU cycles taken.
+    pub fn run_until_scanline(&mut self, scanline: u16) -> u32 {
+        assert!(
+            scanline < self.region.scanlines(),
+            "scanline {} out of range",
+            scanline
+        );
+        let mut cycles = 0;
+        let mut left = self.ppu.frame.scanline != scanline;
+        loop {
+            cycles += self.step_instruction();
+            if self.ppu.frame.scanline != scanline {
+                left = true;
+            } else if left {
+                return cycles;
             }
-            self.exec();
+        }
+    }
+
+    /// Runs until the PPU completes a picture (scanline 240 dot 0), and returns the CPU cycles taken.
+    pub fn run_frame(&mut self) -> u32 {
+        self.ppu.frame.ready = false;
+        let mut cycles = 0;
+        while !self.ppu.frame.ready {
+            cycles += self.step_instruction();
         }
         // apu::runframe
+        cycles
     }
 }
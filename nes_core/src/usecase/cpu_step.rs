@@ -0,0 +1,175 @@
+use crate::entity::cpu::{Control, Register};
+
+use super::{dma::DmaState, nes::NesState};
+
+/// How the cycles of the running instruction reach the bus.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    /// Every cycle runs.
+    #[default]
+    Run,
+    /// Cycles run and are logged while `ticks_left` allows.
+    Record,
+    /// Logged cycles are played back without ticking or touching the bus.
+    Replay,
+    /// The cycle budget is spent; the rest of the instruction only runs on paper.
+    Stop,
+}
+
+/// CPU state a cycle can change, apart from WRAM, which only changes on the bus.
+#[derive(Debug, Clone)]
+pub struct CpuSnapshot {
+    register: Register,
+    control: Control,
+    cycles: u64,
+    ppu_clock_phase: u8,
+    dma: DmaState,
+    open_bus: u8,
+    jam: bool,
+    prev_nmi: bool,
+    need_nmi: bool,
+    run_irq: bool,
+    prev_run_irq: bool,
+    cdl_code_start: u16,
+    cdl_indirect_data: bool,
+    cdl_indirect_code: bool,
+    cdl_indirect_jump: bool,
+}
+
+/// Lets `step_cycle` stop in the middle of an instruction.
+///
+/// Instructions run to their end in one call, so an unfinished instruction is resumed by
+/// running it again from its first cycle: the cycles already done are replayed from the
+/// log, which holds each cycle's bus value and the CPU state after it, and only the new
+/// cycles tick the PPU and APU and reach the bus.
+#[derive(Debug, Default)]
+pub struct StepState {
+    pub mode: StepMode,
+    /// Mode to go on in once the replay has caught up.
+    pub live_mode: StepMode,
+    /// CPU state before the first cycle of an unfinished instruction.
+    pub start: Option<CpuSnapshot>,
+    pub log: Vec<(u8, CpuSnapshot)>,
+    /// Next log entry to replay.
+    pub cursor: usize,
+    pub ticks_left: u32,
+}
+
+impl NesState {
+    pub fn cpu_snapshot(&self) -> CpuSnapshot {
+        CpuSnapshot {
+            register: self.cpu.register.clone(),
+            control: self.cpu.control.clone(),
+            cycles: self.cpu.cycles,
+            ppu_clock_phase: self.cpu.ppu_clock_phase,
+            dma: self.cpu.dma.clone(),
+            open_bus: self.cpu.open_bus,
+            jam: self.cpu.jam,
+            prev_nmi: self.cpu.prev_nmi,
+            need_nmi: self.cpu.need_nmi,
+            run_irq: self.cpu.run_irq,
+            prev_run_irq: self.cpu.prev_run_irq,
+            cdl_code_start: self.cdl.code_start,
+            cdl_indirect_data: self.cdl.indirect_data,
+            cdl_indirect_code: self.cdl.indirect_code,
+            cdl_indirect_jump: self.cdl.indirect_jump,
+        }
+    }
+
+    pub fn restore_cpu(&mut self, snapshot: &CpuSnapshot) {
+        self.cpu.register = snapshot.register.clone();
+        self.cpu.control = snapshot.control.clone();
+        self.cpu.cycles = snapshot.cycles;
+        self.cpu.ppu_clock_phase = snapshot.ppu_clock_phase;
+        self.cpu.dma = snapshot.dma.clone();
+        self.cpu.open_bus = snapshot.open_bus;
+        self.cpu.jam = snapshot.jam;
+        self.cpu.prev_nmi = snapshot.prev_nmi;
+        self.cpu.need_nmi = snapshot.need_nmi;
+        self.cpu.run_irq = snapshot.run_irq;
+        self.cpu.prev_run_irq = snapshot.prev_run_irq;
+        self.cdl.code_start = snapshot.cdl_code_start;
+        self.cdl.indirect_data = snapshot.cdl_indirect_data;
+        self.cdl.indirect_code = snapshot.cdl_indirect_code;
+        self.cdl.indirect_jump = snapshot.cdl_indirect_jump;
+    }
+
+    /// Called by `tick` outside `StepMode::Run`; returns whether the cycle really runs.
+    /// Cycles that do not run are still counted, so DMA keeps its get/put alignment.
+    pub fn step_tick(&mut self) -> bool {
+        let step = &mut self.cpu.step;
+        match step.mode {
+            StepMode::Run => true,
+            StepMode::Record if step.ticks_left > 0 => {
+                step.ticks_left -= 1;
+                true
+            }
+            StepMode::Record | StepMode::Stop => {
+                step.mode = StepMode::Stop;
+                self.cpu.cycles += 1;
+                false
+            }
+            // the state after the cycle comes with its log entry
+            StepMode::Replay => false,
+        }
+    }
+
+    /// Runs the bus side of a cycle, `access`, according to the step mode.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn step_access(&mut self, access: impl FnOnce(&mut Self) -> u8) -> u8 {
+        match self.cpu.step.mode {
+            StepMode::Run => access(self),
+            StepMode::Record => {
+                let value = access(self);
+                let snapshot = self.cpu_snapshot();
+                self.cpu.step.log.push((value, snapshot));
+                value
+            }
+            StepMode::Replay => {
+                let step = &mut self.cpu.step;
+                let (value, snapshot) = step.log[step.cursor].clone();
+                step.cursor += 1;
+                if step.cursor == step.log.len() {
+                    step.mode = step.live_mode;
+                }
+                self.restore_cpu(&snapshot);
+                value
+            }
+            StepMode::Stop => self.cpu.open_bus,
+        }
+    }
+
+    /// Prepares to run the current instruction from its first cycle. An instruction
+    /// `step_cycle` left unfinished is rewound and replayed before going on in `live_mode`.
+    pub fn begin_step(&mut self, live_mode: StepMode) {
+        self.cpu.step.live_mode = live_mode;
+        self.cpu.step.cursor = 0;
+        match self.cpu.step.start.take() {
+            Some(start) => {
+                self.restore_cpu(&start);
+                self.cpu.step.start = Some(start);
+                self.cpu.step.mode = StepMode::Replay;
+            }
+            None => {
+                if live_mode != StepMode::Run {
+                    self.cpu.step.start = Some(self.cpu_snapshot());
+                }
+                self.cpu.step.mode = live_mode;
+            }
+        }
+    }
+
+    /// Goes back to `StepMode::Run`. An instruction stopped by the cycle budget keeps
+    /// its log and is left in the state after its last real cycle.
+    pub fn end_step(&mut self) {
+        let step = &mut self.cpu.step;
+        if step.mode == StepMode::Stop {
+            let (_, snapshot) = step.log.last().unwrap().clone();
+            self.restore_cpu(&snapshot);
+        } else {
+            step.start = None;
+            step.log.clear();
+        }
+        self.cpu.step.mode = StepMode::Run;
+    }
+}
@@ -0,0 +1,109 @@
+use crate::entity::cdl::PRG_PCM;
+
+use super::nes::NesState;
+
+/// DMA unit of the 2A03. It halts the CPU on its next read cycle and then
+/// alternates get (read) and put (write) cycles until every transfer is done.
+#[derive(Debug, Default, Clone)]
+pub struct DmaState {
+    /// A transfer is pending and the CPU has not been halted yet.
+    pub need_halt: bool,
+    /// DMC transfers spend one more cycle before they can read.
+    pub need_dummy_read: bool,
+    pub oam_transfer: bool,
+    pub oam_page: u8,
+    pub dmc_running: bool,
+}
+
+impl NesState {
+    /// Get cycles read, put cycles write; DMA reads can only happen on get cycles.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn is_get_cycle(&self) -> bool {
+        self.cpu.cycles & 1 == 0
+    }
+
+    /// Starts OAM DMA from page `page` ($4014 write).
+    pub fn start_oam_dma(&mut self, page: u8) {
+        self.cpu.dma.oam_page = page;
+        self.cpu.dma.oam_transfer = true;
+        self.cpu.dma.need_halt = true;
+    }
+
+    /// Requests a DMC sample fetch.
+    pub fn start_dmc_dma(&mut self) {
+        if !self.cpu.dma.dmc_running {
+            self.cpu.dma.dmc_running = true;
+            self.cpu.dma.need_halt = true;
+            self.cpu.dma.need_dummy_read = true;
+        }
+    }
+
+    /// Runs pending transfers before the CPU reads `addr`. The halted CPU keeps
+    /// repeating that read, which is why DMA can clock registers like $4016 twice.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn process_pending_dma(&mut self, addr: u16) {
+        if !self.cpu.dma.need_halt {
+            return;
+        }
+        self.tick();
+        self.step_access(|nes| nes.read_cpu_bus(addr));
+        self.cpu.dma.need_halt = false;
+
+        let mut oam_counter: u16 = 0;
+        let mut oam_addr: u8 = 0;
+        let mut oam_value = 0;
+        while self.cpu.dma.dmc_running || self.cpu.dma.oam_transfer {
+            if self.is_get_cycle() {
+                if self.cpu.dma.dmc_running
+                    && !self.cpu.dma.need_halt
+                    && !self.cpu.dma.need_dummy_read
+                {
+                    self.dma_cycle();
+                    self.cpu.dma.dmc_running = false;
+                    self.step_access(|nes| {
+                        let dmc_addr = nes.apu.dmc.current_addr;
+                        if nes.cdl.enabled {
+                            let offset = nes.cartridge.prg_addr(dmc_addr);
+                            nes.cdl.mark_prg(dmc_addr, offset, PRG_PCM);
+                        }
+                        let value = nes.read_cpu_bus(dmc_addr);
+                        nes.set_dmc_sample_buffer(value);
+                        value
+                    });
+                } else if self.cpu.dma.oam_transfer {
+                    self.dma_cycle();
+                    let oam_page = self.cpu.dma.oam_page;
+                    oam_value = self.step_access(|nes| {
+                        nes.read_cpu_bus((oam_page as u16) << 8 | oam_addr as u16)
+                    });
+                    oam_addr = oam_addr.wrapping_add(1);
+                    oam_counter += 1;
+                } else {
+                    self.dma_cycle();
+                    self.step_access(|nes| nes.read_cpu_bus(addr));
+                }
+            } else if self.cpu.dma.oam_transfer && oam_counter & 1 == 1 {
+                self.dma_cycle();
+                self.step_access(|nes| nes.write_ppu(0x2004, oam_value));
+                oam_counter += 1;
+                if oam_counter == 0x200 {
+                    self.cpu.dma.oam_transfer = false;
+                }
+            } else {
+                // alignment cycle
+                self.dma_cycle();
+                self.step_access(|nes| nes.read_cpu_bus(addr));
+            }
+        }
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn dma_cycle(&mut self) {
+        if self.cpu.dma.need_halt {
+            self.cpu.dma.need_halt = false;
+        } else if self.cpu.dma.need_dummy_read {
+            self.cpu.dma.need_dummy_read = false;
+        }
+        self.tick();
+    }
+}
@@ -11,18 +11,27 @@ pub struct JoyPadState {
 }
 
 impl NesState {
+    /// Returns D0 of a $4016/$4017 read; the upper 3 bits come from the open bus.
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn read_joypad_state(&mut self, is_player2: bool) -> u8 {
         if self.joypad.strobe {
-            return 0x40 | (self.get_joypad_state(is_player2) & 1);
+            return self.get_joypad_state(is_player2) & 1;
         }
 
-        let shift = 0x40 | (self.joypad.shift_register[is_player2.as_u8() as usize] & 1);
+        let shift = self.joypad.shift_register[is_player2.as_u8() as usize] & 1;
         self.joypad.shift_register[is_player2.as_u8() as usize] =
             0x80 | (self.joypad.shift_register[is_player2.as_u8() as usize] >> 1);
         shift
     }
 
+    /// Returns what a read of $4016/$4017 would return, without shifting the register.
+    pub fn peek_joypad_state(&self, is_player2: bool) -> u8 {
+        if self.joypad.strobe {
+            return self.get_joypad_state(is_player2) & 1;
+        }
+        self.joypad.shift_register[is_player2.as_u8() as usize] & 1
+    }
+
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn write_joypad_strobe(&mut self, val: bool) {
         if self.joypad.strobe && !val {
@@ -32,7 +41,7 @@ impl NesState {
         self.joypad.strobe = val;
     }
 
-    fn get_joypad_state(&mut self, is_player2: bool) -> u8 {
+    fn get_joypad_state(&self, is_player2: bool) -> u8 {
         if !is_player2 {
             self.joypad.state_1p.get_u8()
         } else {
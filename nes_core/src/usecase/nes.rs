@@ -1,4 +1,10 @@
-use crate::{adapter::nes::NesAdapter, entity::cartridge::Cartridge};
+use crate::{
+    adapter::nes::NesAdapter,
+    entity::{
+        cartridge::Cartridge, cdl::CodeDataLog, cheat::Cheat, region::Region, sampler::Sampler,
+        symbol::SymbolTable,
+    },
+};
 
 use super::{apu::ApuState, cpu::CpuState, joypad::JoyPadState, ppu_state::PpuState};
 
@@ -8,19 +14,41 @@ pub struct NesState {
     pub apu: ApuState,
     pub cartridge: Cartridge,
     pub joypad: JoyPadState,
+    pub region: Region,
+    pub symbols: SymbolTable,
+    pub cdl: CodeDataLog,
+    pub cheats: Vec<Cheat>,
     pub adapter: NesAdapter,
 }
 
 impl NesState {
     pub fn new(adapter: NesAdapter) -> Self {
         let cartridge = Cartridge::new(adapter.cartridge.read_file());
+        let region = cartridge.region;
+        let cdl = CodeDataLog::new(cartridge.prg_rom.len(), cartridge.chr_rom.len());
         Self {
             cpu: CpuState::default(),
-            ppu: PpuState::new(cartridge.vertical_mirroring),
+            ppu: PpuState::new(cartridge.vertical_mirroring, adapter.video.pixel_format()),
             apu: ApuState::default(),
             cartridge,
             joypad: JoyPadState::default(),
+            region,
+            symbols: SymbolTable::default(),
+            cdl,
+            cheats: Vec::new(),
             adapter,
         }
     }
+
+    /// Overrides the TV system detected from the ROM header.
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu.ppu_clock_phase = 0;
+        if self.ppu.frame.scanline >= region.scanlines() {
+            self.ppu.frame.scanline = 0;
+        }
+        if let Some(sampler) = &mut self.apu.sampler {
+            *sampler = Sampler::new(sampler.rate, region.cpu_clock_hz());
+        }
+    }
 }
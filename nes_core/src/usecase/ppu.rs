@@ -1,11 +1,11 @@
 use crate::{
-    entity::nes_rgb::NES_RGB,
+    entity::cdl::{CHR_READ, CHR_RENDERED},
     util::bit::{AsU16, AsU8, PartialBit, Zero},
 };
 
 use super::{
     nes::NesState,
-    ppu_state::{PpuState, ScanlineMode},
+    ppu_state::{ImaginarySprite, PpuState, ScanlineMode},
 };
 
 impl PpuState {
@@ -131,38 +131,115 @@ impl PpuState {
             self.oam.secondary[i].data_l = 0;
             self.oam.secondary[i].data_h = 0;
         }
+        self.oam.eval = Default::default();
     }
 
+    /// CHR address of the row of `sprite` on the current scanline, low plane.
     #[cfg_attr(not(debug_assertions), inline(always))]
-    pub fn eval_sprites(&mut self) {
-        let mut n = 0;
-        for i in 0..64 {
-            let line: i32 = if self.frame.scanline == 261 {
-                -1
+    pub fn spr_pattern_addr(&self, sprite: &ImaginarySprite) -> u16 {
+        let mut addr = if self.spr_height() == 16 {
+            (sprite.tile as u16 & 1) * 0x1000 + (sprite.tile as u16 & !1) * 16
+        } else {
+            self.register.PPU_CTRL.spr_tbl.as_u16() * 0x1000 + sprite.tile as u16 * 16
+        };
+        let mut spr_y = self.frame.scanline.wrapping_sub(sprite.y as u16) % self.spr_height();
+        if (sprite.attr & 0x80).as_bool() {
+            spr_y ^= self.spr_height() - 1;
+        }
+        addr += spr_y + (spr_y & 8);
+        addr
+    }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn spr_in_range(&self, y: u8) -> bool {
+        let line = self.frame.scanline as i32 - y as i32;
+        line >= 0 && line < self.spr_height() as i32
+    }
+
+    /// One read/write pair of sprite evaluation, run on the even dots 66~256.
+    /// With secondary OAM full, the overflow check steps `m` along with `n`,
+    /// so it compares tile, attribute and X bytes as Y: the hardware overflow bug.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn eval_sprite_step(&mut self) {
+        if self.oam.eval.done {
+            return;
+        }
+        let n = self.oam.eval.n as usize;
+        let m = self.oam.eval.m as usize;
+        let value = self.oam.primary[n * 4 + m];
+        self.oam.eval.buffer = value;
+        let count = self.oam.eval.count as usize;
+
+        if count < 8 {
+            let sprite = &mut self.oam.secondary[count];
+            match m {
+                0 => sprite.y = value,
+                1 => sprite.tile = value,
+                2 => sprite.attr = value,
+                _ => sprite.x = value,
+            }
+            if m == 0 && !self.spr_in_range(value) {
+                self.next_eval_sprite();
             } else {
-                self.frame.scanline as i32
-            } - self.oam.primary[i * 4] as i32;
-            if line >= 0 && line < self.spr_height() as i32 {
-                self.oam.secondary[n].y = self.oam.primary[i * 4];
-                self.oam.secondary[n].tile = self.oam.primary[i * 4 + 1];
-                self.oam.secondary[n].attr = self.oam.primary[i * 4 + 2];
-                self.oam.secondary[n].x = self.oam.primary[i * 4 + 3];
-                self.oam.secondary[n].id = i as u8;
-                n += 1;
-                if n >= 8 {
-                    self.register.PPU_STATUS.spr_ovf = true;
-                    break;
+                self.oam.secondary[count].id = n as u8;
+                self.oam.eval.m += 1;
+                if self.oam.eval.m == 4 {
+                    self.oam.eval.count += 1;
+                    self.next_eval_sprite();
                 }
             }
+        } else if self.spr_in_range(value) {
+            self.register.PPU_STATUS.spr_ovf = true;
+            self.oam.eval.done = true;
+        } else {
+            let m = (self.oam.eval.m + 1) & 3;
+            self.next_eval_sprite();
+            self.oam.eval.m = m;
         }
     }
+
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn next_eval_sprite(&mut self) {
+        self.oam.eval.m = 0;
+        self.oam.eval.n += 1;
+        self.oam.eval.done = self.oam.eval.n == 64;
+    }
 }
 
 impl NesState {
     #[cfg_attr(not(debug_assertions), inline(always))]
-    fn read_ppu_bus(&self, addr: u16) -> u8 {
+    fn read_ppu_bus(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => {
+                if self.cdl.enabled {
+                    self.log_chr(addr, CHR_RENDERED);
+                }
+                self.cartridge.read_chr(addr)
+            }
+            _ => self.peek_ppu(addr),
+        }
+    }
+
+    /// CPU access to PPU memory through $2007.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn read_ppu_data(&mut self, addr: u16) -> u8 {
+        let addr = addr & 0x3FFF;
         match addr {
-            0x0000..=0x1FFF => self.cartridge.read_chr(addr),
+            0x0000..=0x1FFF => {
+                if self.cdl.enabled {
+                    self.log_chr(addr, CHR_READ);
+                }
+                self.cartridge.read_chr(addr)
+            }
+            _ => self.peek_ppu(addr),
+        }
+    }
+
+    /// Returns what the PPU would read from `addr` ($0000~$3FFF), without side effects.
+    pub fn peek_ppu(&self, addr: u16) -> u8 {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => self.cartridge.peek_chr(addr),
             0x2000..=0x3EFF => self.ppu.vram[self.ppu.nt_mirror(addr) as usize],
             0x3F00..=0x3FFF => {
                 let addr_palette = if addr & 0x13 == 0x10 {
@@ -171,11 +248,7 @@ impl NesState {
                     addr
                 };
                 self.ppu.palette_ram[(addr_palette & 0x1F) as usize]
-                    & if self.ppu.register.PPU_MASK.gray {
-                        0x30
-                    } else {
-                        0xFF
-                    }
+                    & self.ppu.register.PPU_MASK.gray_mask()
             }
             _ => 0,
         }
@@ -202,27 +275,74 @@ impl NesState {
         }
     }
 
+    /// Overwrites PPU memory at `addr` ($0000~$3FFF), including CHR-ROM.
+    pub fn poke_ppu(&mut self, addr: u16, value: u8) {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => self.cartridge.poke_chr(addr, value),
+            _ => self.write_ppu_bus(addr, value),
+        }
+    }
+
+    /// Returns what a CPU read of a PPU register would return, without clearing
+    /// vblank, resetting the write toggle or advancing the VRAM address.
+    pub fn peek_ppu_register(&self, addr: u16) -> u8 {
+        match addr % 8 {
+            2 => (self.ppu.bus_latch.result & 0x1F) | self.ppu.register.PPU_STATUS.get_u8(),
+            4 => self.ppu.oam.primary[self.ppu.register.OAM_ADDR as usize],
+            7 => {
+                if self.ppu.loopy.v_addr.get_addr() <= 0x3EFF {
+                    self.ppu.bus_latch.buffer
+                } else {
+                    (self.ppu.bus_latch.result & 0xC0)
+                        | (self.peek_ppu(self.ppu.loopy.v_addr.get_addr()) & 0x3F)
+                }
+            }
+            _ => self.ppu.bus_latch.result,
+        }
+    }
+
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn read_ppu(&mut self, addr: u16) -> u8 {
         match addr % 8 {
             2 => {
-                self.ppu.bus_latch.result =
-                    (self.ppu.bus_latch.result & 0x1F) | self.ppu.register.PPU_STATUS.get_u8();
+                // reading just before vblank starts hides the flag for this frame
+                if self.ppu.frame.scanline == self.region.vblank_scanline()
+                    && self.ppu.frame.dot == 1
+                {
+                    self.ppu.frame.suppress_vblank = true;
+                }
+                let status = self.ppu.register.PPU_STATUS.get_u8();
                 self.ppu.register.PPU_STATUS.vblank = false;
+                self.update_nmi();
                 self.ppu.bus_latch.strobe = false;
+                self.drive_ppu_bus(0xE0, status)
             }
             4 => {
-                self.ppu.bus_latch.result =
-                    self.ppu.oam.primary[self.ppu.register.OAM_ADDR as usize];
+                let value = if self.ppu.frame.scanline < 240 && self.ppu.is_rendering() {
+                    match self.ppu.frame.dot {
+                        // secondary OAM is being cleared
+                        1..=64 => 0xFF,
+                        65..=256 => self.ppu.oam.eval.buffer,
+                        _ => self.ppu.oam.primary[self.ppu.register.OAM_ADDR as usize],
+                    }
+                } else {
+                    self.ppu.oam.primary[self.ppu.register.OAM_ADDR as usize]
+                };
+                self.drive_ppu_bus(0xFF, value)
             }
             7 => {
-                if self.ppu.loopy.v_addr.get_addr() <= 0x3EFF {
-                    self.ppu.bus_latch.result = self.ppu.bus_latch.buffer;
-                    self.ppu.bus_latch.buffer = self.read_ppu_bus(self.ppu.loopy.v_addr.get_addr());
+                let (mask, value) = if self.ppu.loopy.v_addr.get_addr() <= 0x3EFF {
+                    let value = self.ppu.bus_latch.buffer;
+                    self.ppu.bus_latch.buffer =
+                        self.read_ppu_data(self.ppu.loopy.v_addr.get_addr());
+                    (0xFF, value)
                 } else {
-                    self.ppu.bus_latch.buffer = self.read_ppu_bus(self.ppu.loopy.v_addr.get_addr());
-                    self.ppu.bus_latch.result = self.ppu.bus_latch.buffer;
-                }
+                    self.ppu.bus_latch.buffer =
+                        self.read_ppu_data(self.ppu.loopy.v_addr.get_addr());
+                    // palette entries are 6bit
+                    (0x3F, self.ppu.bus_latch.buffer)
+                };
                 self.ppu.loopy.v_addr.set_addr(
                     self.ppu.loopy.v_addr.get_addr()
                         + if self.ppu.register.PPU_CTRL.incr {
@@ -231,19 +351,50 @@ impl NesState {
                             1
                         },
                 );
+                self.drive_ppu_bus(mask, value)
             }
-            _ => {}
-        };
-        self.ppu.bus_latch.result
+            _ => self.drive_ppu_bus(0x00, 0),
+        }
+    }
+
+    /// PPUMASK emphasis as a palette index, with red and green swapped on PAL and Dendy.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn emphasis(&self) -> u16 {
+        let emphasis = self.ppu.register.PPU_MASK.emphasis();
+        if self.region.swaps_red_green_emphasis() {
+            (emphasis & 0b100) | ((emphasis & 1) << 1) | ((emphasis >> 1) & 1)
+        } else {
+            emphasis
+        }
+    }
+
+    /// Puts the `mask` bits of `value` on the I/O latch and returns the latch.
+    /// Bits nobody drove for about 600ms have discharged to 0.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn drive_ppu_bus(&mut self, mask: u8, value: u8) -> u8 {
+        let frame = self.ppu.frame.count;
+        let decay_frames = (self.region.frame_rate() * 0.6) as u64;
+        let latch = &mut self.ppu.bus_latch;
+        for bit in 0..8 {
+            let flag = 1 << bit;
+            if mask & flag != 0 {
+                latch.result = (latch.result & !flag) | (value & flag);
+                latch.refreshed[bit] = frame;
+            } else if frame - latch.refreshed[bit] > decay_frames {
+                latch.result &= !flag;
+            }
+        }
+        latch.result
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     pub fn write_ppu(&mut self, addr: u16, value: u8) -> u8 {
-        self.ppu.bus_latch.result = value;
+        self.drive_ppu_bus(0xFF, value);
         match addr % 8 {
             0 => {
                 self.ppu.register.PPU_CTRL.set_u8(value);
                 self.ppu.loopy.t_addr.nt = self.ppu.register.PPU_CTRL.nt;
+                self.update_nmi();
             }
             1 => {
                 self.ppu.register.PPU_MASK.set_u8(value);
@@ -252,6 +403,12 @@ impl NesState {
                 self.ppu.register.OAM_ADDR = value;
             }
             4 => {
+                // bits 2-4 of the attribute byte do not exist
+                let value = if self.ppu.register.OAM_ADDR & 3 == 2 {
+                    value & 0xE3
+                } else {
+                    value
+                };
                 self.ppu.oam.primary[self.ppu.register.OAM_ADDR as usize] = value;
                 self.ppu.register.OAM_ADDR = self.ppu.register.OAM_ADDR.wrapping_add(1);
             }
@@ -295,31 +452,40 @@ impl NesState {
 
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn load_sprites(&mut self) {
-        let mut addr: u16;
         for i in 0..8 {
             self.ppu.oam.imaginary[i] = self.ppu.oam.secondary[i].clone();
-            if self.ppu.spr_height() == 16 {
-                addr = (self.ppu.oam.imaginary[i].tile as u16 & 1) * 0x1000
-                    + (self.ppu.oam.imaginary[i].tile as u16 & !1) * 16;
-            } else {
-                addr = self.ppu.register.PPU_CTRL.spr_tbl.as_u16() * 0x1000
-                    + self.ppu.oam.imaginary[i].tile as u16 * 16;
-            }
-
-            let mut spr_y = self
-                .ppu
-                .frame
-                .scanline
-                .wrapping_sub(self.ppu.oam.imaginary[i].y as u16)
-                % self.ppu.spr_height();
-            if (self.ppu.oam.imaginary[i].attr & 0x80).as_bool() {
-                spr_y ^= self.ppu.spr_height() - 1;
-            }
-            addr += spr_y + (spr_y & 8);
-
+            let addr = self.ppu.spr_pattern_addr(&self.ppu.oam.imaginary[i]);
             self.ppu.oam.imaginary[i].data_l = self.read_ppu_bus(addr);
             self.ppu.oam.imaginary[i].data_h = self.read_ppu_bus(addr + 8);
         }
+        self.ppu.oam.extra.clear();
+        if self.ppu.layers.no_sprite_limit && self.ppu.oam.eval.count == 8 {
+            self.load_extra_sprites();
+        }
+    }
+
+    /// Finds the sprites of the line that did not fit in secondary OAM. Their patterns are
+    /// peeked, so the mapper and the code/data log see only the real fetches.
+    fn load_extra_sprites(&mut self) {
+        let first = self.ppu.oam.secondary[7].id as usize + 1;
+        for n in first..64 {
+            let [y, tile, attr, x] = [0, 1, 2, 3].map(|m| self.ppu.oam.primary[n * 4 + m]);
+            if !self.ppu.spr_in_range(y) {
+                continue;
+            }
+            let mut sprite = ImaginarySprite {
+                x,
+                attr,
+                tile,
+                y,
+                id: n as u8,
+                ..Default::default()
+            };
+            let addr = self.ppu.spr_pattern_addr(&sprite);
+            sprite.data_l = self.peek_ppu(addr);
+            sprite.data_h = self.peek_ppu(addr + 8);
+            self.ppu.oam.extra.push(sprite);
+        }
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -361,6 +527,14 @@ impl NesState {
             }
             // spr
             if self.ppu.register.PPU_MASK.spr && (self.ppu.register.PPU_MASK.spr_left || x >= 8) {
+                // sprites past the 8th are behind all of the first 8
+                for sprite in self.ppu.oam.extra.iter().rev() {
+                    let spr_palette = sprite.pixel(x as u8);
+                    if spr_palette != 0 {
+                        obj_palette = (sprite.attr & 3) << 2 | spr_palette | 16;
+                        obj_priority = sprite.attr & 0x20;
+                    }
+                }
                 for i in (0..=7).rev() {
                     if self.ppu.oam.imaginary[i].id == 64 {
                         continue;
@@ -387,20 +561,34 @@ impl NesState {
                     obj_priority = self.ppu.oam.imaginary[i].attr & 0x20;
                 }
             }
+            // hidden layers are dropped once sprite 0 hit has seen them
+            if self.ppu.layers.hide_background {
+                palette = 0;
+            }
+            if self.ppu.layers.hide_sprites {
+                obj_palette = 0;
+            }
             // eval priority
             if obj_palette.as_bool() && (palette == 0 || obj_priority == 0) {
                 palette = obj_palette;
             }
 
-            self.ppu.pixels[self.ppu.frame.scanline as usize * 256 + x as usize] =
-                NES_RGB[self.read_ppu_bus(
-                    0x3F00
-                        + if self.ppu.is_rendering() {
-                            palette as u16
-                        } else {
-                            0
-                        },
-                ) as usize];
+            let addr = if self.ppu.is_rendering() {
+                0x3F00 + palette as u16
+            } else {
+                // with rendering off the backdrop comes from v when it points at palette RAM
+                match self.ppu.loopy.v_addr.get_u16() & 0x3FFF {
+                    addr @ 0x3F00..=0x3FFF => addr,
+                    _ => 0x3F00,
+                }
+            };
+            // peek_ppu already applies grayscale to palette reads
+            let colour = self.read_ppu_bus(addr);
+            self.ppu.frame_buffer.set(
+                self.ppu.frame.scanline as usize * 256 + x as usize,
+                self.emphasis() << 6 | colour as u16,
+                &self.ppu.palette,
+            );
         }
         self.ppu.background_shift_register.bg_shift_l <<= 1;
         self.ppu.background_shift_register.bg_shift_h <<= 1;
@@ -412,15 +600,25 @@ impl NesState {
                 | self.ppu.background_shift_register.at_latch_h.as_u8();
     }
 
+    /// Drives the NMI line: asserted while both vblank and the NMI enable are set.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    fn update_nmi(&mut self) {
+        self.cpu.control.NMI =
+            self.ppu.register.PPU_STATUS.vblank && self.ppu.register.PPU_CTRL.nmi;
+    }
+
     #[cfg_attr(not(debug_assertions), inline(always))]
     fn scanline_cycle(&mut self, mode: ScanlineMode) {
         if mode == ScanlineMode::NMI && self.ppu.frame.dot == 1 {
-            self.ppu.register.PPU_STATUS.vblank = true;
-            if self.ppu.register.PPU_CTRL.nmi {
-                self.cpu.control.NMI = true;
+            if !std::mem::take(&mut self.ppu.frame.suppress_vblank) {
+                self.ppu.register.PPU_STATUS.vblank = true;
+                self.update_nmi();
             }
         } else if mode == ScanlineMode::POST && self.ppu.frame.dot == 0 {
-            self.adapter.video.draw_frame(self.ppu.pixels);
+            self.ppu.frame_buffer.phase = self.ppu.frame.phase;
+            self.adapter.video.draw_frame(&self.ppu.frame_buffer);
+            self.ppu.frame.count += 1;
+            self.ppu.frame.ready = true;
         } else if mode == ScanlineMode::VISIBLE || mode == ScanlineMode::PRE {
             match self.ppu.frame.dot {
                 1 => {
@@ -430,7 +628,13 @@ impl NesState {
                         self.ppu.register.PPU_STATUS.spr_ovf = false;
                     }
                 }
-                257 => self.ppu.eval_sprites(),
+                66..=256
+                    if mode == ScanlineMode::VISIBLE
+                        && self.ppu.frame.dot.is_multiple_of(2)
+                        && self.ppu.is_rendering() =>
+                {
+                    self.ppu.eval_sprite_step()
+                }
                 321 => self.load_sprites(),
                 _ => {}
             };
@@ -496,6 +700,7 @@ impl NesState {
                     self.ppu.addr = self.ppu.nt_addr();
                     if mode == ScanlineMode::PRE {
                         self.ppu.register.PPU_STATUS.vblank = false;
+                        self.update_nmi();
                     }
                 }
                 321 | 339 => {
@@ -506,9 +711,13 @@ impl NesState {
                 }
                 340 => {
                     self.ppu.background_shift_register.nt = self.read_ppu_bus(self.ppu.addr);
-                    if mode == ScanlineMode::PRE && self.ppu.is_rendering() && self.ppu.frame.is_odd
+                    if mode == ScanlineMode::PRE
+                        && self.ppu.is_rendering()
+                        && self.ppu.frame.is_odd
+                        && self.region.skips_odd_frame_dot()
                     {
                         self.ppu.frame.dot += 1;
+                        self.ppu.frame.phase = (self.ppu.frame.phase + 12 - 8) % 12;
                     }
                 }
                 _ => {}
@@ -523,17 +732,23 @@ impl NesState {
         match self.ppu.frame.scanline {
             0..=239 => self.scanline_cycle(ScanlineMode::VISIBLE),
             240 => self.scanline_cycle(ScanlineMode::POST),
-            241 => self.scanline_cycle(ScanlineMode::NMI),
-            261 => self.scanline_cycle(ScanlineMode::PRE),
+            scanline if scanline == self.region.vblank_scanline() => {
+                self.scanline_cycle(ScanlineMode::NMI)
+            }
+            scanline if scanline == self.region.pre_render_scanline() => {
+                self.scanline_cycle(ScanlineMode::PRE)
+            }
             _ => {}
         };
         self.ppu.frame.dot += 1;
         if self.ppu.frame.dot > 340 {
             self.ppu.frame.dot %= 341;
             self.ppu.frame.scanline += 1;
-            if self.ppu.frame.scanline > 261 {
+            if self.ppu.frame.scanline >= self.region.scanlines() {
                 self.ppu.frame.scanline = 0;
                 self.ppu.frame.is_odd = !self.ppu.frame.is_odd;
+                let dots = self.region.scanlines() as u32 * 341;
+                self.ppu.frame.phase = ((self.ppu.frame.phase as u32 + dots * 8) % 12) as u8;
             }
         }
     }
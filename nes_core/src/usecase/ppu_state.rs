@@ -1,6 +1,10 @@
 use crate::{
-    entity::ppu::{BusLatch, Oam, PaletteRam, Register, VRam, VerticalMirroring},
-    util::bit::PartialBit,
+    entity::{
+        frame_buffer::{FrameBuffer, PixelFormat},
+        palette::Palette,
+        ppu::{BusLatch, Oam, PaletteRam, Register, VRam, VerticalMirroring},
+    },
+    util::bit::{PartialBit, Zero},
 };
 
 #[derive(Debug)]
@@ -14,12 +18,15 @@ pub struct PpuState {
     pub loopy: LoopyState,
     pub background_shift_register: BackgroundShiftRegister,
     pub frame: FrameState,
-    pub pixels: [[u8; 3]; 256 * 240],
+    pub frame_buffer: FrameBuffer,
+    /// Colours the PPU output is converted with.
+    pub palette: Palette,
+    pub layers: LayerOptions,
     pub addr: u16,
 }
 
 impl PpuState {
-    pub fn new(vertical_mirroring: bool) -> Self {
+    pub fn new(vertical_mirroring: bool, pixel_format: PixelFormat) -> Self {
         Self {
             vram: [0; 0x800],
             palette_ram: [0; 0x20],
@@ -28,18 +35,34 @@ impl PpuState {
                 primary: [0; 0x100],
                 imaginary: [0; 8].map(|_| ImaginarySprite::default()),
                 secondary: [0; 8].map(|_| ImaginarySprite::default()),
+                extra: Vec::new(),
+                eval: SpriteEval::default(),
             },
             register: Register::default(),
             bus_latch: BusLatch::default(),
             loopy: LoopyState::default(),
             background_shift_register: BackgroundShiftRegister::default(),
             frame: FrameState::default(),
-            pixels: [[0; 3]; 256 * 240],
+            frame_buffer: FrameBuffer::new(pixel_format),
+            palette: Palette::default(),
+            layers: LayerOptions::default(),
             addr: 0,
         }
     }
 }
 
+/// Changes to what is drawn, for debugging and screenshots. The game sees no difference:
+/// sprite 0 hit and sprite overflow behave as without them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayerOptions {
+    /// Draw the backdrop where the background would be.
+    pub hide_background: bool,
+    pub hide_sprites: bool,
+    /// Draw every sprite of a line instead of the first 8, which removes the flicker games
+    /// use to cycle through them.
+    pub no_sprite_limit: bool,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ImaginarySprite {
     pub x: u8,
@@ -51,11 +74,43 @@ pub struct ImaginarySprite {
     pub data_h: u8,
 }
 
+impl ImaginarySprite {
+    /// 2bit colour at screen column `x`, 0 where the sprite is transparent or absent.
+    #[cfg_attr(not(debug_assertions), inline(always))]
+    pub fn pixel(&self, x: u8) -> u8 {
+        let mut spr_x = x.wrapping_sub(self.x);
+        if self.id == 64 || spr_x >= 8 {
+            return 0;
+        }
+        if (self.attr & 0x40).as_bool() {
+            spr_x ^= 7;
+        }
+        (self.data_h.bit(7 - spr_x) << 1) | self.data_l.bit(7 - spr_x)
+    }
+}
+
 #[derive(Debug)]
 pub struct OamState {
     pub primary: Oam,
     pub imaginary: [ImaginarySprite; 8],
     pub secondary: [ImaginarySprite; 8],
+    /// Sprites of the line past the 8th, drawn behind the others with `no_sprite_limit`.
+    pub extra: Vec<ImaginarySprite>,
+    pub eval: SpriteEval,
+}
+
+/// Progress of sprite evaluation over dots 65~256 of a visible scanline.
+#[derive(Debug, Default)]
+pub struct SpriteEval {
+    /// Sprite (0~63) and byte (0~3) of primary OAM read next.
+    pub n: u8,
+    pub m: u8,
+    /// Sprites copied to secondary OAM.
+    pub count: u8,
+    /// All 64 sprites were visited; the rest of the scanline does nothing.
+    pub done: bool,
+    /// Last byte read from primary OAM, which is what $2004 returns meanwhile.
+    pub buffer: u8,
 }
 
 #[derive(Debug, Default)]
@@ -79,6 +134,15 @@ pub struct FrameState {
     pub scanline: u16,
     pub dot: u16,
     pub is_odd: bool,
+    /// Pictures completed since power on.
+    pub count: u64,
+    /// Set when a picture is completed, cleared by `run_frame`.
+    pub ready: bool,
+    /// $2002 was read one dot before vblank, so this vblank raises no flag and no NMI.
+    pub suppress_vblank: bool,
+    /// Colour subcarrier phase at the start of the picture, in 1/12 cycles.
+    /// A dot lasts 8/12 of a cycle, so it moves with the frame length.
+    pub phase: u8,
 }
 
 #[allow(clippy::upper_case_acronyms)]
@@ -0,0 +1,161 @@
+use crate::entity::{debug_view::DebugView, ppu::Sprite};
+
+use super::nes::NesState;
+
+/// Colour of the scroll window outline in the nametable view.
+const SCROLL_OUTLINE: [u8; 3] = [0xFF, 0x00, 0xFF];
+/// Grid lines of the OAM view; sprites behind the background get the second colour.
+const GRID: [u8; 3] = [0x40, 0x40, 0x40];
+const GRID_BEHIND: [u8; 3] = [0x20, 0x40, 0xC0];
+
+/// Size of an OAM view cell, which fits an 8x16 sprite and a grid line.
+const OAM_CELL: (usize, usize) = (9, 17);
+
+/// Debug pictures of PPU memory. They read through `peek_ppu`, so CHR comes from the
+/// banks currently mapped and nothing is logged or changed.
+impl NesState {
+    /// Colour of palette RAM entry `entry` (0~31) as the PPU would output it.
+    fn palette_rgb(&self, entry: u8) -> [u8; 3] {
+        let colour = self.peek_ppu(0x3F00 | entry as u16) & 0x3F;
+        self.ppu.palette.rgb[colour as usize]
+    }
+
+    /// Draws the 8x8 tile at CHR address `addr` at (`x`, `y`) with `palette` (0~7, 4~7 for
+    /// sprites). Colour 0 is drawn as the backdrop unless `transparent`.
+    fn draw_tile(
+        &self,
+        view: &mut DebugView,
+        addr: u16,
+        palette: u8,
+        (x, y): (usize, usize),
+        (flip_h, flip_v): (bool, bool),
+        transparent: bool,
+    ) {
+        for row in 0..8 {
+            let plane_l = self.peek_ppu(addr + row);
+            let plane_h = self.peek_ppu(addr + row + 8);
+            let dy = if flip_v { 7 - row } else { row } as usize;
+            for col in 0..8 {
+                let pixel = (plane_l >> (7 - col)) & 1 | ((plane_h >> (7 - col)) & 1) << 1;
+                if pixel == 0 && transparent {
+                    continue;
+                }
+                let entry = if pixel == 0 { 0 } else { palette * 4 + pixel };
+                let dx = if flip_h { 7 - col } else { col } as usize;
+                view.set(x + dx, y + dy, self.palette_rgb(entry));
+            }
+        }
+    }
+
+    /// All four nametables in a 512x480 picture, with the area the next frame scrolls to
+    /// (from the t register and fine X) outlined.
+    pub fn nametable_view(&self) -> DebugView {
+        let mut view = DebugView::new(512, 480);
+        let bg_table = if self.ppu.register.PPU_CTRL.bg_tbl {
+            0x1000
+        } else {
+            0
+        };
+        for nt in 0..4_u16 {
+            let base = 0x2000 + nt * 0x400;
+            let (left, top) = ((nt & 1) as usize * 256, (nt >> 1) as usize * 240);
+            for tile_y in 0..30_u16 {
+                for tile_x in 0..32_u16 {
+                    let tile = self.peek_ppu(base + tile_y * 32 + tile_x) as u16;
+                    let attr = self.peek_ppu(base + 0x3C0 + tile_y / 4 * 8 + tile_x / 4);
+                    let shift = (tile_y & 2) << 1 | (tile_x & 2);
+                    self.draw_tile(
+                        &mut view,
+                        bg_table + tile * 16,
+                        (attr >> shift) & 3,
+                        (left + tile_x as usize * 8, top + tile_y as usize * 8),
+                        (false, false),
+                        false,
+                    );
+                }
+            }
+        }
+        let t = &self.ppu.loopy.t_addr;
+        let x = (t.nt as usize & 1) * 256 + t.c_x as usize * 8 + self.ppu.loopy.f_x as usize;
+        let y = (t.nt as usize >> 1) * 240 + t.c_y as usize * 8 + t.f_y as usize;
+        view.outline(x, y, 256, 240, SCROLL_OUTLINE);
+        view
+    }
+
+    /// Both pattern tables side by side in a 256x128 picture, coloured with `palette`
+    /// (0~3 background, 4~7 sprites).
+    pub fn pattern_table_view(&self, palette: u8) -> DebugView {
+        let mut view = DebugView::new(256, 128);
+        for tile in 0..512_u16 {
+            let (table, n) = ((tile / 256) as usize, (tile % 256) as usize);
+            self.draw_tile(
+                &mut view,
+                tile * 16,
+                palette & 7,
+                (table * 128 + n % 16 * 8, n / 16 * 8),
+                (false, false),
+                false,
+            );
+        }
+        view
+    }
+
+    /// The 64 sprites of OAM.
+    pub fn sprites(&self) -> [Sprite; 64] {
+        std::array::from_fn(|n| Sprite::from_oam(&self.ppu.oam.primary, n))
+    }
+
+    /// The 64 sprites of OAM in an 8x8 grid of 8x16 cells, flipped and coloured as they
+    /// are drawn. The cell border is blue for sprites behind the background.
+    pub fn oam_view(&self) -> DebugView {
+        let (cell_w, cell_h) = OAM_CELL;
+        let mut view = DebugView::new(cell_w * 8 + 1, cell_h * 8 + 1);
+        view.fill(0, 0, view.width, view.height, GRID);
+        for (n, sprite) in self.sprites().iter().enumerate() {
+            let (x, y) = (n % 8 * cell_w, n / 8 * cell_h);
+            if sprite.behind_background() {
+                view.outline(x, y, cell_w + 1, cell_h + 1, GRID_BEHIND);
+            }
+            view.fill(x + 1, y + 1, 8, 16, self.palette_rgb(0));
+            let tiles = if self.ppu.register.PPU_CTRL.spr_sz {
+                let addr = (sprite.tile as u16 & 1) * 0x1000 + (sprite.tile as u16 & 0xFE) * 16;
+                vec![addr, addr + 16]
+            } else {
+                let table = if self.ppu.register.PPU_CTRL.spr_tbl {
+                    0x1000
+                } else {
+                    0
+                };
+                vec![table + sprite.tile as u16 * 16]
+            };
+            for (half, &addr) in tiles.iter().enumerate() {
+                // a vertically flipped 8x16 sprite also swaps its two tiles
+                let half = if sprite.flip_v() {
+                    tiles.len() - 1 - half
+                } else {
+                    half
+                };
+                self.draw_tile(
+                    &mut view,
+                    addr,
+                    4 + sprite.palette(),
+                    (x + 1, y + 1 + half * 8),
+                    (sprite.flip_h(), sprite.flip_v()),
+                    true,
+                );
+            }
+        }
+        view
+    }
+
+    /// Palette RAM in a 256x32 picture: background palettes on the top row, sprite
+    /// palettes below, 16x16 per entry.
+    pub fn palette_view(&self) -> DebugView {
+        let mut view = DebugView::new(256, 32);
+        for (entry, &colour) in self.ppu.palette_ram.iter().enumerate() {
+            let rgb = self.ppu.palette.rgb[(colour & 0x3F) as usize];
+            view.fill(entry % 16 * 16, entry / 16 * 16, 16, 16, rgb);
+        }
+        view
+    }
+}
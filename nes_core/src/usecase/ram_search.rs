@@ -0,0 +1,237 @@
+use super::nes::NesState;
+
+/// CPU address where PRG-RAM starts in the searched memory.
+const PRG_RAM_ADDR: u16 = 0x6000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueSize {
+    Byte,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// How candidate bytes are read as numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchView {
+    pub size: ValueSize,
+    pub signed: bool,
+    pub endian: Endian,
+}
+
+impl Default for SearchView {
+    fn default() -> Self {
+        Self {
+            size: ValueSize::Byte,
+            signed: false,
+            endian: Endian::Little,
+        }
+    }
+}
+
+impl SearchView {
+    fn len(&self) -> usize {
+        match self.size {
+            ValueSize::Byte => 1,
+            ValueSize::Word => 2,
+        }
+    }
+
+    /// Reads the value at `offset`; `None` when it runs past the end of `memory`.
+    pub fn read(&self, memory: &[u8], offset: usize) -> Option<i32> {
+        let bytes = memory.get(offset..offset + self.len())?;
+        Some(match (self.size, self.signed) {
+            (ValueSize::Byte, false) => bytes[0] as i32,
+            (ValueSize::Byte, true) => bytes[0] as i8 as i32,
+            (ValueSize::Word, signed) => {
+                let word = match self.endian {
+                    Endian::Little => u16::from_le_bytes([bytes[0], bytes[1]]),
+                    Endian::Big => u16::from_be_bytes([bytes[0], bytes[1]]),
+                };
+                if signed {
+                    word as i16 as i32
+                } else {
+                    word as i32
+                }
+            }
+        })
+    }
+}
+
+/// Comparison of a candidate's current value against the previous snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    EqualTo(i32),
+}
+
+impl Comparison {
+    fn matches(&self, previous: i32, current: i32) -> bool {
+        match self {
+            Comparison::Equal => current == previous,
+            Comparison::Changed => current != previous,
+            Comparison::Increased => current > previous,
+            Comparison::Decreased => current < previous,
+            Comparison::EqualTo(value) => current == *value,
+        }
+    }
+}
+
+/// Narrows down the RAM addresses holding a game variable across frames.
+/// Searched memory is WRAM ($0000~$07FF) followed by PRG-RAM ($6000~).
+#[derive(Debug)]
+pub struct RamSearch {
+    pub view: SearchView,
+    snapshot: Vec<u8>,
+    wram_len: usize,
+    candidates: Vec<usize>,
+}
+
+impl RamSearch {
+    /// Starts a search over `wram ++ prg_ram` with every offset as a candidate.
+    pub fn new(view: SearchView, wram: &[u8], prg_ram: &[u8]) -> Self {
+        let mut snapshot = wram.to_vec();
+        snapshot.extend_from_slice(prg_ram);
+        let wram_len = wram.len();
+        // a word must not straddle WRAM and PRG-RAM
+        let candidates = (0..snapshot.len())
+            .filter(|offset| {
+                let end = offset + view.len();
+                end <= snapshot.len() && (*offset >= wram_len || end <= wram_len)
+            })
+            .collect();
+        Self {
+            view,
+            snapshot,
+            wram_len,
+            candidates,
+        }
+    }
+
+    /// Keeps the candidates whose value compares true against the last snapshot,
+    /// then takes `memory` as the new snapshot.
+    pub fn filter(&mut self, comparison: Comparison, wram: &[u8], prg_ram: &[u8]) {
+        let mut memory = wram.to_vec();
+        memory.extend_from_slice(prg_ram);
+        let view = self.view;
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|offset| {
+            match (view.read(snapshot, *offset), view.read(&memory, *offset)) {
+                (Some(previous), Some(current)) => comparison.matches(previous, current),
+                _ => false,
+            }
+        });
+        self.snapshot = memory;
+    }
+
+    /// Changes how values are read without dropping candidates.
+    pub fn set_view(&mut self, view: SearchView) {
+        self.view = view;
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// Remaining candidates as CPU addresses with their value in the last snapshot.
+    pub fn candidates(&self) -> Vec<(u16, i32)> {
+        self.candidates
+            .iter()
+            .filter_map(|offset| {
+                let value = self.view.read(&self.snapshot, *offset)?;
+                Some((self.cpu_addr(*offset), value))
+            })
+            .collect()
+    }
+
+    fn cpu_addr(&self, offset: usize) -> u16 {
+        if offset < self.wram_len {
+            offset as u16
+        } else {
+            PRG_RAM_ADDR + (offset - self.wram_len) as u16
+        }
+    }
+}
+
+impl NesState {
+    pub fn start_ram_search(&self, view: SearchView) -> RamSearch {
+        RamSearch::new(view, &self.cpu.wram, &self.cartridge.prg_ram)
+    }
+
+    pub fn filter_ram_search(&self, search: &mut RamSearch, comparison: Comparison) {
+        search.filter(comparison, &self.cpu.wram, &self.cartridge.prg_ram);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _narrow_byte() {
+        let mut wram = [0u8; 8];
+        wram[3] = 3;
+        let prg_ram = [0u8; 4];
+        let mut search = RamSearch::new(SearchView::default(), &wram, &prg_ram);
+        assert_eq!(12, search.len());
+
+        wram[3] = 2;
+        search.filter(Comparison::Decreased, &wram, &prg_ram);
+        assert_eq!(vec![(0x0003, 2)], search.candidates());
+
+        search.filter(Comparison::Equal, &wram, &prg_ram);
+        assert_eq!(1, search.len());
+        search.filter(Comparison::EqualTo(1), &wram, &prg_ram);
+        assert!(search.is_empty());
+    }
+
+    #[test]
+    fn _word_views() {
+        let wram = [0x00, 0xFF, 0xFE, 0x00];
+        let prg_ram = [0x12, 0x34];
+        let view = SearchView {
+            size: ValueSize::Word,
+            signed: true,
+            endian: Endian::Big,
+        };
+        let mut search = RamSearch::new(view, &wram, &prg_ram);
+        // 3 words in WRAM, 1 in PRG-RAM
+        assert_eq!(4, search.len());
+        search.filter(Comparison::EqualTo(-2), &wram, &prg_ram);
+        assert_eq!(vec![(0x0001, -2)], search.candidates());
+
+        let mut search = RamSearch::new(
+            SearchView {
+                signed: false,
+                endian: Endian::Little,
+                ..view
+            },
+            &wram,
+            &prg_ram,
+        );
+        search.filter(Comparison::EqualTo(0x3412), &wram, &prg_ram);
+        assert_eq!(vec![(0x6000, 0x3412)], search.candidates());
+    }
+
+    #[test]
+    fn _signed_byte() {
+        let view = SearchView {
+            signed: true,
+            ..SearchView::default()
+        };
+        let mut search = RamSearch::new(view, &[0x01], &[]);
+        search.filter(Comparison::Decreased, &[0xFF], &[]);
+        assert_eq!(vec![(0x0000, -1)], search.candidates());
+    }
+}
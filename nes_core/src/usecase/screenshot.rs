@@ -0,0 +1,74 @@
+use crate::{
+    entity::{frame_buffer::FrameBuffer, overscan::Overscan},
+    util::png::{encode_png, text_chunk},
+};
+
+use super::nes::NesState;
+
+/// Private PNG chunk with the palette index (`emphasis << 6 | colour`) of every pixel of
+/// the full 256x240 frame, as little endian u16s.
+pub const INDEX_CHUNK: [u8; 4] = *b"nfRm";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScreenshotOptions {
+    /// Edges left out of the picture; `Overscan::NONE` keeps the whole frame.
+    pub overscan: Overscan,
+    /// Also store the palette indices and the emphasis bits, so the exact frame can be
+    /// recovered whatever palette the picture was taken with.
+    pub metadata: bool,
+}
+
+impl NesState {
+    /// RGB24 rows of the last completed frame without the edges `overscan` hides; the size
+    /// is `overscan.size()`.
+    pub fn frame_rgb(&self, overscan: &Overscan) -> Vec<u8> {
+        let frame = &self.ppu.frame_buffer;
+        let (left, top, width, height) = overscan.rect(FrameBuffer::WIDTH, FrameBuffer::HEIGHT);
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        for y in top..top + height {
+            for x in left..left + width {
+                rgb.extend(frame.rgb(x, y, &self.ppu.palette));
+            }
+        }
+        rgb
+    }
+
+    /// The last completed frame as a PNG file.
+    pub fn screenshot(&self, options: &ScreenshotOptions) -> Vec<u8> {
+        let frame = &self.ppu.frame_buffer;
+        let (width, height) = options.overscan.size();
+        let rgb = self.frame_rgb(&options.overscan);
+        let mut chunks = vec![text_chunk("Software", "aries")];
+        if options.metadata {
+            let mask = &self.ppu.register.PPU_MASK;
+            chunks.push(text_chunk("Frame", &self.ppu.frame.count.to_string()));
+            // the PPUMASK bits when the picture was taken; each index has its own
+            let emphasis: Vec<&str> = [
+                ("red", mask.red),
+                ("green", mask.green),
+                ("blue", mask.blue),
+            ]
+            .iter()
+            .filter(|&&(_, on)| on)
+            .map(|&(name, _)| name)
+            .collect();
+            let emphasis = if emphasis.is_empty() {
+                String::from("none")
+            } else {
+                emphasis.join(",")
+            };
+            chunks.push(text_chunk("Emphasis", &emphasis));
+            let overscan = &options.overscan;
+            chunks.push(text_chunk(
+                "Overscan",
+                &format!(
+                    "{},{},{},{}",
+                    overscan.top, overscan.bottom, overscan.left, overscan.right
+                ),
+            ));
+            let indices = frame.indices().iter().flat_map(|i| i.to_le_bytes());
+            chunks.push((INDEX_CHUNK, indices.collect()));
+        }
+        encode_png(width, height, &rgb, &chunks)
+    }
+}
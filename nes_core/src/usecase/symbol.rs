@@ -0,0 +1,42 @@
+use crate::entity::symbol::{Symbol, SymbolLocation, PRG_BANK_SIZE};
+
+use super::nes::NesState;
+
+impl NesState {
+    /// Resolves a CPU address to a symbol location using the banks currently mapped.
+    pub fn symbol_location(&self, addr: u16) -> SymbolLocation {
+        match addr {
+            0x0000..=0x1FFF => SymbolLocation::Cpu(addr % 0x800),
+            0x8000..=0xFFFF => SymbolLocation::prg(self.cartridge.prg_addr(addr)),
+            _ => SymbolLocation::Cpu(addr),
+        }
+    }
+
+    /// Returns the label covering `addr` and the distance from its start.
+    pub fn label_at(&self, addr: u16) -> Option<(&Symbol, u16)> {
+        self.symbols.find(self.symbol_location(addr))
+    }
+
+    /// Returns the CPU address of a label, or `None` when it is unknown or its
+    /// bank is not mapped right now.
+    pub fn resolve_label(&self, name: &str) -> Option<u16> {
+        match self.symbols.lookup(name)? {
+            SymbolLocation::Cpu(addr) => Some(addr),
+            SymbolLocation::Prg { bank, offset } => self
+                .cartridge
+                .prg_map
+                .iter()
+                .position(|base| *base as usize / PRG_BANK_SIZE == bank)
+                .map(|window| 0x8000 + (window * PRG_BANK_SIZE) as u16 + offset),
+        }
+    }
+
+    /// Formats `addr` for traces and disassembly: `NMI_Handler`, `table+2` or `$C0A3`.
+    pub fn format_addr(&self, addr: u16) -> String {
+        match self.label_at(addr) {
+            Some((symbol, 0)) => symbol.name.clone(),
+            Some((symbol, distance)) => format!("{}+{}", symbol.name, distance),
+            None => format!("${:04X}", addr),
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod bit;
+pub mod crc32;
+pub mod png;
 pub mod vec;
@@ -0,0 +1,289 @@
+//! PNG writer for 8bit RGB pictures. The image data is compressed with LZ77 and the fixed
+//! Huffman codes of deflate, which is plenty for the large flat areas of NES frames.
+
+use super::crc32::Crc32;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+const WINDOW: usize = 32768;
+const HASH_BITS: u32 = 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Candidates tried per position before settling for the longest match so far.
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Encodes a `width`x`height` picture of RGB24 `rgb` rows. `chunks` (type and data) are
+/// stored before the image data, e.g. from `text_chunk`.
+pub fn encode_png(
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+    chunks: &[([u8; 4], Vec<u8>)],
+) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8bit truecolour, deflate, adaptive filtering, no interlace
+    header.extend([8, 2, 0, 0, 0]);
+    write_chunk(&mut png, *b"IHDR", &header);
+    for (kind, data) in chunks {
+        write_chunk(&mut png, *kind, data);
+    }
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in rgb.chunks_exact(width * 3) {
+        // filter type 0: the row as it is
+        raw.push(0);
+        raw.extend(row);
+    }
+    write_chunk(&mut png, *b"IDAT", &zlib(&raw));
+    write_chunk(&mut png, *b"IEND", &[]);
+    png
+}
+
+/// A `tEXt` chunk: Latin-1 `keyword` and `text` separated by a zero byte.
+pub fn text_chunk(keyword: &str, text: &str) -> ([u8; 4], Vec<u8>) {
+    let mut data = keyword.as_bytes().to_vec();
+    data.push(0);
+    data.extend(text.as_bytes());
+    (*b"tEXt", data)
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    let mut crc = Crc32::new();
+    crc.update(&kind);
+    crc.update(data);
+    png.extend(crc.finish().to_be_bytes());
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+/// zlib stream: header, one deflate block and the Adler-32 of `data`.
+fn zlib(data: &[u8]) -> Vec<u8> {
+    // 32KiB window, no dictionary; the header is a multiple of 31
+    let mut out = vec![0x78, 0x01];
+    out.extend(deflate(data));
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+/// Deflate bit stream, least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: u32, len: u32) {
+        self.bits |= value << self.len;
+        self.len += len;
+        while self.len >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// Huffman codes go most significant bit first.
+    fn push_code(&mut self, code: u32, len: u32) {
+        self.push(code.reverse_bits() >> (32 - len), len);
+    }
+
+    /// Fixed Huffman code of literal/length symbol `symbol`.
+    fn push_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.push_code(0x30 + symbol, 8),
+            144..=255 => self.push_code(0x190 + symbol - 144, 9),
+            256..=279 => self.push_code(symbol - 256, 7),
+            _ => self.push_code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn push_match(&mut self, len: usize, distance: usize) {
+        let code = LENGTH_BASE
+            .iter()
+            .rposition(|&base| base as usize <= len)
+            .unwrap();
+        self.push_symbol(257 + code as u32);
+        self.push(
+            (len - LENGTH_BASE[code] as usize) as u32,
+            LENGTH_EXTRA[code] as u32,
+        );
+        let code = DISTANCE_BASE
+            .iter()
+            .rposition(|&base| base as usize <= distance)
+            .unwrap();
+        self.push_code(code as u32, 5);
+        self.push(
+            (distance - DISTANCE_BASE[code] as usize) as u32,
+            DISTANCE_EXTRA[code] as u32,
+        );
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let key = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+    (key.wrapping_mul(0x9E3779B1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Hash chains of the positions seen so far, to find earlier copies of the upcoming bytes.
+struct Matcher {
+    /// Most recent position of each hash.
+    head: Vec<usize>,
+    /// Position before each one with the same hash.
+    prev: Vec<usize>,
+}
+
+impl Matcher {
+    fn new() -> Self {
+        Self {
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            prev: vec![usize::MAX; WINDOW],
+        }
+    }
+
+    fn insert(&mut self, data: &[u8], i: usize) {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(data, i);
+            self.prev[i % WINDOW] = self.head[h];
+            self.head[h] = i;
+        }
+    }
+
+    /// Length and distance of the longest earlier copy of the bytes at `i`.
+    fn longest(&self, data: &[u8], i: usize) -> (usize, usize) {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max_len = MAX_MATCH.min(data.len() - i);
+        let mut candidate = self.head[hash(data, i)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == usize::MAX || i - candidate > WINDOW {
+                break;
+            }
+            let len = data[candidate..]
+                .iter()
+                .zip(&data[i..i + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best_len {
+                (best_len, best_distance) = (len, i - candidate);
+                if len == max_len {
+                    break;
+                }
+            }
+            let next = self.prev[candidate % WINDOW];
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        (best_len, best_distance)
+    }
+}
+
+/// One final block with the fixed Huffman codes.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.push(1, 1);
+    writer.push(1, 2);
+    let mut matcher = Matcher::new();
+    let mut i = 0;
+    while i < data.len() {
+        let (len, distance) = matcher.longest(data, i);
+        if len >= MIN_MATCH {
+            writer.push_match(len, distance);
+            for j in i..i + len {
+                matcher.insert(data, j);
+            }
+            i += len;
+        } else {
+            writer.push_symbol(data[i] as u32);
+            matcher.insert(data, i);
+            i += 1;
+        }
+    }
+    writer.push_symbol(256);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _adler32() {
+        assert_eq!(0x11E60398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(b""));
+    }
+
+    #[test]
+    fn _zlib_known_answer() {
+        // checked against zlib's inflate
+        assert_eq!(
+            vec![0x78, 0x01, 0x4B, 0x4C, 0x4A, 0x46, 0x43, 0x29, 0x00, 0x48, 0xC5, 0x07, 0x49],
+            zlib(b"abcabcabcabcabcabcd")
+        );
+    }
+
+    #[test]
+    fn _chunks() {
+        let rgb = vec![0x80; 16 * 8 * 3];
+        let png = encode_png(16, 8, &rgb, &[text_chunk("Software", "aries")]);
+        assert_eq!(SIGNATURE, png[..8]);
+        let mut kinds = Vec::new();
+        let mut at = 8;
+        while at < png.len() {
+            let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+            let body = &png[at + 4..at + 8 + len];
+            let crc = u32::from_be_bytes(png[at + 8 + len..at + 12 + len].try_into().unwrap());
+            assert_eq!(Crc32::checksum(body), crc);
+            kinds.push(String::from_utf8(body[..4].to_vec()).unwrap());
+            at += len + 12;
+        }
+        assert_eq!(vec!["IHDR", "tEXt", "IDAT", "IEND"], kinds);
+        assert_eq!([0, 0, 0, 16, 0, 0, 0, 8, 8, 2], png[16..26]);
+        // a flat picture compresses to almost nothing
+        assert!(png.len() < 100, "{}", png.len());
+    }
+}
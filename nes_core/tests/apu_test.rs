@@ -0,0 +1,105 @@
+mod common;
+
+use common::TestRom;
+use nes_core::{entity::region::Region, usecase::nes::NesState};
+
+fn boot() -> NesState {
+    TestRom::idle().boot()
+}
+
+/// Ticks until the frame IRQ is raised, up to `limit` cycles.
+fn ticks_until_frame_irq(nes: &mut NesState, limit: u32) -> Option<u32> {
+    (1..=limit).find(|_| {
+        nes.tick();
+        nes.apu.frame_counter.irq
+    })
+}
+
+#[test]
+fn frame_irq_in_4_step_mode() {
+    let mut nes = boot();
+    nes.write_apu(0x4017, 0x00);
+    // 3 or 4 cycles until the restart, then 29828 to the first IRQ cycle
+    let cycles = ticks_until_frame_irq(&mut nes, 40000).unwrap();
+    assert!(cycles == 29831 || cycles == 29832, "{}", cycles);
+    assert!(nes.cpu.control.IRQ);
+    assert_eq!(0x40, nes.peek_cpu(0x4015) & 0x40);
+    assert!(nes.apu.frame_counter.irq, "peeking leaves the flag set");
+
+    // the flag is raised again on the next two cycles, then a read acknowledges it
+    nes.tick();
+    nes.tick();
+    assert_eq!(0x40, nes.read_cpu_bus(0x4015) & 0x40);
+    assert!(!nes.cpu.control.IRQ);
+    assert_eq!(0x00, nes.read_cpu_bus(0x4015) & 0x40);
+
+    let cycles = ticks_until_frame_irq(&mut nes, 40000).unwrap();
+    assert_eq!(29830 - 2, cycles);
+}
+
+#[test]
+fn no_frame_irq_when_inhibited_or_in_5_step_mode() {
+    for val in [0x40, 0x80, 0xC0] {
+        let mut nes = boot();
+        nes.write_apu(0x4017, 0x00);
+        ticks_until_frame_irq(&mut nes, 40000).unwrap();
+        // setting the inhibit flag also acknowledges a pending IRQ
+        nes.write_apu(0x4017, val);
+        assert_eq!(val & 0x40 != 0, !nes.cpu.control.IRQ);
+        nes.read_cpu_bus(0x4015);
+        assert_eq!(None, ticks_until_frame_irq(&mut nes, 80000));
+    }
+}
+
+#[test]
+fn pal_frame_counter_is_slower() {
+    let mut nes = boot();
+    nes.set_region(Region::Pal);
+    nes.write_apu(0x4017, 0x00);
+    let cycles = ticks_until_frame_irq(&mut nes, 40000).unwrap();
+    assert!(cycles == 33255 || cycles == 33256, "{}", cycles);
+}
+
+#[test]
+fn noise_length_counter_runs_out() {
+    let mut nes = boot();
+    nes.write_apu(0x4017, 0x00);
+    // length index 3 loads 2 half frames
+    nes.write_apu(0x400F, 0x18);
+    assert_eq!(0x00, nes.peek_cpu(0x4015) & 0x08, "the channel is disabled");
+    nes.write_apu(0x4015, 0x08);
+    nes.write_apu(0x400F, 0x18);
+    assert_eq!(0x08, nes.peek_cpu(0x4015) & 0x08);
+
+    // the first half frame is at 14913 cycles, the second at 29829
+    for _ in 0..20000 {
+        nes.tick();
+    }
+    assert_eq!(1, nes.apu.noise.length.counter);
+    for _ in 0..10000 {
+        nes.tick();
+    }
+    assert_eq!(0x00, nes.peek_cpu(0x4015) & 0x08);
+
+    // the halt flag stops the count, and disabling the channel clears it
+    nes.write_apu(0x400C, 0x20);
+    nes.write_apu(0x400F, 0x18);
+    for _ in 0..30000 {
+        nes.tick();
+    }
+    assert_eq!(0x08, nes.peek_cpu(0x4015) & 0x08);
+    nes.write_apu(0x4015, 0x00);
+    assert_eq!(0x00, nes.peek_cpu(0x4015) & 0x08);
+}
+
+#[test]
+fn status_shows_length_counters() {
+    let mut nes = boot();
+    nes.write_apu(0x4015, 0x0F);
+    for addr in [0x4003, 0x4007, 0x400B, 0x400F] {
+        nes.write_apu(addr, 0x08);
+    }
+    assert_eq!(0x0F, nes.peek_cpu(0x4015) & 0x0F);
+    nes.write_apu(0x4015, 0x05);
+    assert_eq!(0x05, nes.peek_cpu(0x4015) & 0x0F);
+}
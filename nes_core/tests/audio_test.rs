@@ -0,0 +1,134 @@
+mod common;
+
+use common::TestRom;
+use nes_core::entity::region::Region;
+
+/// $8000 LDA #$7F / STA $4011 / $8005 JMP $8005
+const DMC_LOAD: [u8; 8] = [0xA9, 0x7F, 0x8D, 0x11, 0x40, 0x4C, 0x05, 0x80];
+
+#[test]
+fn no_samples_unless_started() {
+    let mut nes = TestRom::new(&DMC_LOAD).boot();
+    nes.run_frame();
+    assert!(nes.take_samples().is_empty());
+}
+
+#[test]
+fn samples_follow_emulated_cycles() {
+    for region in [Region::Ntsc, Region::Pal] {
+        let mut nes = TestRom::new(&DMC_LOAD).boot();
+        nes.set_region(region);
+        nes.start_audio(44100);
+        let mut cycles = 0;
+        let mut samples = 0;
+        for n in 0..120 {
+            cycles += nes.run_frame() as u64;
+            let frame = nes.take_samples().len() as f64;
+            // 734 per NTSC frame, 882 per PAL frame; power on is not at a frame boundary
+            if n > 0 {
+                assert!(
+                    (frame - 44100.0 / region.frame_rate()).abs() < 2.0,
+                    "{}",
+                    frame
+                );
+            }
+            samples += frame as u64;
+        }
+        let expected = cycles * 44100 / region.cpu_clock_hz() as u64;
+        assert!(samples.abs_diff(expected) <= 1, "{} {}", samples, expected);
+    }
+}
+
+#[test]
+fn dmc_output_level_is_heard() {
+    let mut nes = TestRom::new(&DMC_LOAD).boot();
+    nes.start_audio(44100);
+    nes.run_frame();
+    let samples = nes.take_samples();
+    // the step to level 127 makes a pop that the high-pass filter takes away again
+    let peak = samples.iter().copied().max().unwrap();
+    assert!(peak > 10000, "{}", peak);
+    for _ in 0..30 {
+        nes.run_frame();
+    }
+    let samples = nes.take_samples();
+    assert!(samples.last().unwrap().abs() < 100, "{:?}", samples.last());
+}
+
+/// Peak level of a frame after `writes` to the APU, which is silent before.
+fn peak_after(writes: &[(u16, u8)]) -> i16 {
+    let mut nes = TestRom::idle().boot();
+    nes.start_audio(44100);
+    // the triangle rests at level 15, so power on thumps until the high-pass settles
+    for _ in 0..10 {
+        nes.run_frame();
+    }
+    let rest = nes.take_samples();
+    assert!(rest.last().unwrap().abs() < 100, "{:?}", rest.last());
+    for &(addr, val) in writes {
+        nes.write_apu(addr, val);
+    }
+    nes.run_frame();
+    nes.take_samples().iter().map(|s| s.abs()).max().unwrap()
+}
+
+#[test]
+fn channels_are_heard() {
+    // constant volume 15 with halted length counters
+    let pulse = peak_after(&[
+        (0x4015, 0x01),
+        (0x4000, 0xBF),
+        (0x4002, 0xFD),
+        (0x4003, 0x00),
+    ]);
+    let triangle = peak_after(&[
+        (0x4015, 0x04),
+        (0x4008, 0xFF),
+        (0x400A, 0xFD),
+        (0x400B, 0x00),
+    ]);
+    let noise = peak_after(&[
+        (0x4015, 0x08),
+        (0x400C, 0x3F),
+        (0x400E, 0x00),
+        (0x400F, 0x00),
+    ]);
+    for peak in [pulse, triangle, noise] {
+        assert!(peak > 1000, "{} {} {}", pulse, triangle, noise);
+    }
+    // the square wave swings the full volume, noise half as much through its filter
+    assert!(pulse > noise, "{} {}", pulse, noise);
+
+    // the same wave on both pulse channels is louder, though not twice as loud
+    let both = peak_after(&[
+        (0x4015, 0x03),
+        (0x4000, 0xBF),
+        (0x4002, 0xFD),
+        (0x4003, 0x00),
+        (0x4004, 0xBF),
+        (0x4006, 0xFD),
+        (0x4007, 0x00),
+    ]);
+    assert!(both > pulse && both < pulse * 2, "{} {}", pulse, both);
+}
+
+#[test]
+fn muted_pulse_is_silent() {
+    // a period under 8, and an enabled channel without a length
+    assert!(
+        peak_after(&[
+            (0x4015, 0x01),
+            (0x4000, 0xBF),
+            (0x4002, 0x07),
+            (0x4003, 0x00)
+        ]) < 100
+    );
+    assert!(
+        peak_after(&[
+            (0x4000, 0xBF),
+            (0x4002, 0xFD),
+            (0x4003, 0x00),
+            (0x4015, 0x01)
+        ]) < 100
+    );
+}
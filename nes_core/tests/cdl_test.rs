@@ -0,0 +1,63 @@
+mod common;
+
+use common::TestRom;
+use nes_core::entity::{
+    cdl::{CHR_READ, CHR_RENDERED, PRG_CODE, PRG_DATA, PRG_INDIRECT_CODE, PRG_INDIRECT_DATA},
+    cpu::InterruptionType,
+};
+
+/// $8000 LDA $8040 / $8003 LDA #$00 / $8005 STA $2006 / $8008 STA $2006
+/// $800B LDA $2007 / $800E JMP ($8012) / $8020 LDA ($00),Y
+fn cdl_rom() -> TestRom {
+    TestRom::new(&[
+        0xAD, 0x40, 0x80, 0xA9, 0x00, 0x8D, 0x06, 0x20, 0x8D, 0x06, 0x20, 0xAD, 0x07, 0x20, 0x6C,
+        0x12, 0x80,
+    ])
+    .at(0x8012, &[0x20, 0x80])
+    .at(0x8020, &[0xB1, 0x00])
+}
+
+#[test]
+fn cdl_logs_code_and_data() {
+    let mut nes = cdl_rom().boot();
+    nes.cdl.enabled = true;
+    nes.INT(InterruptionType::RESET);
+    nes.cpu.wram[0x00] = 0x30;
+    nes.cpu.wram[0x01] = 0x80;
+    nes.cpu.register.Y = 0;
+    for _ in 0..8 {
+        nes.exec();
+    }
+
+    let prg = &nes.cdl.prg;
+    for (offset, flags) in prg[..=0x0010].iter().enumerate() {
+        assert_eq!(PRG_CODE, flags & 0x03, "offset {:04X}", offset);
+    }
+    assert_eq!(PRG_DATA, prg[0x0040] & 0x03);
+    assert_eq!(PRG_DATA, prg[0x0012] & 0x03);
+    assert_eq!(PRG_DATA, prg[0x0013] & 0x03);
+    assert_eq!(PRG_CODE | PRG_INDIRECT_CODE, prg[0x0020] & 0x13);
+    assert_eq!(PRG_DATA | PRG_INDIRECT_DATA, prg[0x0030] & 0x23);
+    assert_eq!(0, prg[0x0050]);
+    // the reset vector window is $E000
+    assert_eq!(PRG_DATA | 0x0C, prg[0x3FFC]);
+
+    assert_eq!(CHR_READ, nes.cdl.chr[0x0000] & CHR_READ);
+    nes.ppu.register.PPU_MASK.bg = true;
+    nes.run_frame();
+    assert!(nes.cdl.chr.iter().any(|flags| flags & CHR_RENDERED != 0));
+}
+
+#[test]
+fn cdl_save_and_load() {
+    let mut nes = cdl_rom().boot();
+    nes.cdl.enabled = true;
+    nes.exec();
+    let file = nes.cdl.save();
+    assert_eq!(0x4000 + 0x2000, file.len());
+
+    let mut other = cdl_rom().boot();
+    other.cdl.load(&file).unwrap();
+    assert_eq!(nes.cdl.prg, other.cdl.prg);
+    assert!(other.cdl.load(&file[1..]).is_err());
+}
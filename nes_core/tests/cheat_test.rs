@@ -0,0 +1,31 @@
+mod common;
+
+use common::TestRom;
+
+/// $8000 LDA $9000 / $8003 STA $10 / $8005 LDA $75
+fn cheat_rom() -> TestRom {
+    TestRom::new(&[0xAD, 0x00, 0x90, 0x85, 0x10, 0xA5, 0x75]).at(0x9000, &[0x01])
+}
+
+#[test]
+fn cheats_patch_reads() {
+    let mut nes = cheat_rom().boot();
+    // $9000 = $05 when the ROM holds $01
+    nes.add_cheat("IAEPAAPA").unwrap();
+    nes.add_cheat("0075:09").unwrap();
+    nes.cpu.wram[0x75] = 0x03;
+
+    nes.exec();
+    assert_eq!(0x05, nes.cpu.register.A);
+    nes.exec();
+    nes.exec();
+    assert_eq!(0x09, nes.cpu.register.A);
+    assert_eq!(0x03, nes.peek_cpu(0x0075));
+
+    assert_eq!(Some(false), nes.toggle_cheat("0075:09"));
+    assert!(nes.remove_cheat("IAEPAAPA"));
+    assert!(!nes.remove_cheat("IAEPAAPA"));
+    nes.cpu.register.PC = 0x8000;
+    nes.exec();
+    assert_eq!(0x01, nes.cpu.register.A);
+}
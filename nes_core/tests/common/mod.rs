@@ -0,0 +1,98 @@
+#![allow(dead_code)]
+
+use nes_core::{
+    adapter::{
+        audio::AudioAdapter, cartridge::CartridgeAdapter, nes::NesAdapter, video::VideoAdapter,
+    },
+    entity::frame_buffer::{FrameBuffer, PixelFormat},
+    usecase::nes::NesState,
+};
+
+pub struct CartridgeCtx {
+    file_bytes: Vec<u8>,
+}
+
+impl CartridgeAdapter for CartridgeCtx {
+    fn read_file(&self) -> Vec<u8> {
+        self.file_bytes.clone()
+    }
+}
+
+#[derive(Default)]
+pub struct VideoCtx(pub PixelFormat);
+impl VideoAdapter for VideoCtx {
+    fn pixel_format(&self) -> PixelFormat {
+        self.0
+    }
+
+    fn draw_frame(&mut self, _frame: &FrameBuffer) {}
+}
+
+#[derive(Default)]
+pub struct AudioCtx;
+impl AudioAdapter for AudioCtx {}
+
+/// In-memory NROM image (16KiB PRG, 8KiB CHR) for tests.
+/// All vectors point to $8000 unless overridden.
+pub struct TestRom {
+    header: [u8; 16],
+    prg: Vec<u8>,
+    chr: Vec<u8>,
+}
+
+impl TestRom {
+    pub fn new(program: &[u8]) -> Self {
+        let mut prg = vec![0xEA; 0x4000];
+        prg[..program.len()].copy_from_slice(program);
+        prg[0x3FFA..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+        Self {
+            header: [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            prg,
+            chr: vec![0; 0x2000],
+        }
+    }
+
+    /// `$8000 JMP $8000`, for tests that drive the console from outside.
+    pub fn idle() -> Self {
+        Self::new(&[0x4C, 0x00, 0x80])
+    }
+
+    /// Places `bytes` at CPU address `addr` ($8000~$FFFF, mirrored every 16KiB).
+    pub fn at(mut self, addr: u16, bytes: &[u8]) -> Self {
+        let start = (addr as usize - 0x8000) % 0x4000;
+        self.prg[start..start + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    pub fn chr(mut self, addr: u16, bytes: &[u8]) -> Self {
+        self.chr[addr as usize..addr as usize + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    pub fn header(mut self, i: usize, value: u8) -> Self {
+        self.header[i] = value;
+        self
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut file = self.header.to_vec();
+        file.extend(&self.prg);
+        file.extend(&self.chr);
+        file
+    }
+
+    pub fn boot(self) -> NesState {
+        self.boot_with_format(PixelFormat::default())
+    }
+
+    pub fn boot_with_format(self, format: PixelFormat) -> NesState {
+        NesAdapter {
+            cartridge: Box::new(CartridgeCtx {
+                file_bytes: self.bytes(),
+            }),
+            video: Box::new(VideoCtx(format)),
+            audio: Box::new(AudioCtx),
+        }
+        .init()
+    }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::TestRom;
+use nes_core::usecase::nes::NesState;
+
+/// Cycles per opcode with no page crossed and branches not taken; 0 is JAM.
+#[rustfmt::skip]
+const CYCLES: [u32; 256] = [
+    7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+];
+
+/// Runs `opcode $00 $00` at $8000 from a clean register state.
+fn run(nes: &mut NesState, opcode: u8) -> u32 {
+    nes.poke_cpu(0x8000, opcode);
+    nes.cpu.register.PC = 0x8000;
+    nes.cpu.register.S = 0xFD;
+    nes.cpu.register.X = 0;
+    nes.cpu.register.Y = 0;
+    nes.cpu.register.P.set_u8(0x24);
+    nes.step_instruction()
+}
+
+#[test]
+fn opcode_cycles() {
+    let mut nes = TestRom::new(&[0xEA, 0x00, 0x00]).boot();
+    for opcode in 0..=255u8 {
+        let cycles = CYCLES[opcode as usize];
+        if cycles == 0 {
+            continue;
+        }
+        // with P = $24 only BNE, BPL, BVC and BCC are taken
+        let taken = matches!(opcode, 0x10 | 0x50 | 0x90 | 0xD0) as u32;
+        assert_eq!(
+            cycles + taken,
+            run(&mut nes, opcode),
+            "opcode {:02X}",
+            opcode
+        );
+    }
+}
+
+#[test]
+fn page_cross_penalty() {
+    let mut nes = TestRom::new(&[0xEA]).boot();
+    nes.poke_cpu(0x8001, 0xFF);
+    nes.poke_cpu(0x8002, 0x00);
+    for (opcode, cycles) in [(0xBD, 5), (0xB9, 5), (0x9D, 5), (0x1E, 7)] {
+        nes.poke_cpu(0x8000, opcode);
+        nes.cpu.register.PC = 0x8000;
+        nes.cpu.register.X = 1;
+        nes.cpu.register.Y = 1;
+        assert_eq!(cycles, nes.step_instruction(), "opcode {:02X}", opcode);
+    }
+    // BNE -128 from $8002 crosses into $7F82
+    nes.poke_cpu(0x8000, 0xD0);
+    nes.poke_cpu(0x8001, 0x80);
+    nes.cpu.register.PC = 0x8000;
+    nes.cpu.register.P.Z = false;
+    assert_eq!(4, nes.step_instruction());
+    assert_eq!(0x7F82, nes.cpu.register.PC);
+}
+
+#[test]
+fn dummy_reads_touch_registers() {
+    // LDA $20F2,X with X = $10 first reads $2002, clearing vblank
+    let mut nes = TestRom::new(&[0xBD, 0xF2, 0x20]).boot();
+    nes.cpu.register.X = 0x10;
+    nes.ppu.register.PPU_STATUS.vblank = true;
+    nes.step_instruction();
+    assert!(!nes.ppu.register.PPU_STATUS.vblank);
+
+    // STA $20FF,X with X = 8 first reads $2007, moving the VRAM address, then writes $2107
+    let mut nes = TestRom::new(&[0x9D, 0xFF, 0x20]).boot();
+    nes.write_ppu(0x2006, 0x20);
+    nes.write_ppu(0x2006, 0x00);
+    nes.cpu.register.X = 8;
+    nes.cpu.register.A = 0x55;
+    nes.step_instruction();
+    assert_eq!(0x00, nes.peek_ppu(0x2000));
+    assert_eq!(0x55, nes.peek_ppu(0x2001));
+}
+
+#[test]
+fn rmw_writes_twice() {
+    // INC $2007 writes the old value, then the incremented one
+    let mut nes = TestRom::new(&[0xEE, 0x07, 0x20]).boot();
+    nes.write_ppu(0x2006, 0x20);
+    nes.write_ppu(0x2006, 0x00);
+    // every access moves the VRAM address: the read returns the buffer (0),
+    // then 0 lands on $2001 and 1 on $2002
+    nes.poke_ppu(0x2001, 0xFF);
+    nes.step_instruction();
+    assert_eq!(0x00, nes.peek_ppu(0x2001));
+    assert_eq!(0x01, nes.peek_ppu(0x2002));
+}
+
+#[test]
+fn brk_skips_padding_byte() {
+    let mut nes = TestRom::new(&[0x00, 0xFF]).at(0xFFFE, &[0x00, 0x90]).boot();
+    nes.step_instruction();
+    let s = nes.cpu.register.S as usize;
+    let pc = u16::from_le_bytes([nes.cpu.wram[0x100 + s + 2], nes.cpu.wram[0x100 + s + 3]]);
+    assert_eq!(0x8002, pc);
+    assert_eq!(0x10, nes.cpu.wram[0x100 + s + 1] & 0x10);
+}
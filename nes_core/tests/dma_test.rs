@@ -0,0 +1,77 @@
+mod common;
+
+use common::TestRom;
+use nes_core::entity::{cdl::PRG_PCM, cpu::IrqSource};
+
+/// $8000 LDA #$02 / $8002 STA $4014 / $8005 NOP
+const OAM_DMA: [u8; 6] = [0xA9, 0x02, 0x8D, 0x14, 0x40, 0xEA];
+/// The same after a 3 cycle `LDA $00`, which moves the DMA to the other cycle parity.
+const OAM_DMA_SHIFTED: [u8; 8] = [0xA5, 0x00, 0xA9, 0x02, 0x8D, 0x14, 0x40, 0xEA];
+
+#[test]
+fn oam_dma_takes_513_or_514_cycles() {
+    let mut seen = [false; 2];
+    for program in [&OAM_DMA[..], &OAM_DMA_SHIFTED[..]] {
+        let mut nes = TestRom::new(program).boot();
+        for i in 0..256 {
+            nes.cpu.wram[0x200 + i] = i as u8;
+        }
+        while nes.cpu.register.PC != 0x8000 + program.len() as u16 - 4 {
+            nes.step_instruction();
+        }
+        assert_eq!(4, nes.step_instruction());
+        // the cycle parity after the write decides whether DMA needs an alignment cycle
+        let extra_cycle = nes.is_get_cycle();
+        seen[extra_cycle as usize] = true;
+
+        let cycles = nes.step_instruction();
+        assert_eq!(2 + if extra_cycle { 514 } else { 513 }, cycles);
+        assert_eq!(0x00, nes.ppu.oam.primary[0]);
+        assert_eq!(0xFF, nes.ppu.oam.primary[0xFF]);
+    }
+    assert_eq!([true, true], seen);
+}
+
+/// $8000 LDA #$8F / STA $4010 / LDA #$00 / STA $4012 / STA $4013 / LDA #$10 / STA $4015 / NOP
+const DMC_DMA: [u8; 19] = [
+    0xA9, 0x8F, 0x8D, 0x10, 0x40, 0xA9, 0x00, 0x8D, 0x12, 0x40, 0x8D, 0x13, 0x40, 0xA9, 0x10, 0x8D,
+    0x15, 0x40, 0xEA,
+];
+
+#[test]
+fn dmc_dma_fetches_sample() {
+    let mut nes = TestRom::new(&DMC_DMA).boot();
+    nes.cdl.enabled = true;
+    for _ in 0..7 {
+        nes.step_instruction();
+    }
+    assert!(nes.cpu.dma.dmc_running);
+    assert_eq!(0x10, nes.peek_cpu(0x4015) & 0x10);
+
+    // halt, dummy read, optional alignment and the fetch itself
+    let cycles = nes.step_instruction() - 2;
+    assert!(cycles == 3 || cycles == 4, "{}", cycles);
+    assert!(!nes.cpu.dma.dmc_running);
+    assert_eq!(Some(0xA9), nes.apu.dmc.sample_buffer);
+    assert_eq!(0x80, nes.peek_cpu(0x4015) & 0x90);
+    assert!(nes.cpu.control.IRQ);
+    assert_eq!(PRG_PCM, nes.cdl.prg[0x0000] & PRG_PCM);
+
+    nes.write_apu(0x4015, 0x00);
+    assert!(!nes.cpu.control.IRQ);
+}
+
+#[test]
+fn dmc_irq_leaves_other_sources_alone() {
+    let mut nes = TestRom::new(&DMC_DMA).boot();
+    nes.cpu.set_irq(IrqSource::Mapper, true);
+    for _ in 0..8 {
+        nes.step_instruction();
+    }
+    assert_eq!(0x80, nes.peek_cpu(0x4015) & 0x80);
+    // acknowledging the DMC keeps the line low for the mapper
+    nes.write_apu(0x4015, 0x00);
+    assert!(nes.cpu.control.IRQ);
+    nes.cpu.set_irq(IrqSource::Mapper, false);
+    assert!(!nes.cpu.control.IRQ);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::TestRom;
+use nes_core::entity::region::Region;
+
+/// NTSC frame: 341 dots * 262 scanlines / 3 dots per CPU cycle.
+const CYCLES_PER_FRAME: u32 = 29781;
+
+#[test]
+fn run_frame_ends_on_picture() {
+    let mut nes = TestRom::idle().boot();
+    nes.run_frame();
+    assert_eq!(1, nes.ppu.frame.count);
+    assert_eq!(240, nes.ppu.frame.scanline);
+
+    let mut total = 0;
+    for count in 2..=4 {
+        let cycles = nes.run_frame();
+        assert_eq!(count, nes.ppu.frame.count);
+        assert!(cycles.abs_diff(CYCLES_PER_FRAME) <= 3, "{}", cycles);
+        total += cycles;
+    }
+    // three frames are 341 * 262 CPU cycles; rendering is off, so no dot is skipped
+    assert!(total.abs_diff(341 * 262) <= 3, "{}", total);
+}
+
+#[test]
+fn step_apis_count_cycles() {
+    // $8000 LDA #$00 / $8002 JMP $8000
+    let mut nes = TestRom::new(&[0xA9, 0x00, 0x4C, 0x00, 0x80]).boot();
+    let start = nes.cpu.cycles;
+    assert_eq!(2, nes.step_instruction());
+    assert_eq!(3, nes.step_instruction());
+    assert_eq!(start + 5, nes.cpu.cycles);
+
+    // the opcode fetch, then the operand
+    assert_eq!(1, nes.step_cycle());
+    assert_eq!(0x8001, nes.cpu.register.PC);
+    assert_eq!(1, nes.step_cycle());
+    assert_eq!(0x8002, nes.cpu.register.PC);
+    // JMP stops after each of its three reads
+    assert_eq!(1, nes.step_cycle());
+    assert_eq!(1, nes.step_cycle());
+    assert_ne!(0x8000, nes.cpu.register.PC);
+    assert_eq!(1, nes.step_cycle());
+    assert_eq!(0x8000, nes.cpu.register.PC);
+    assert_eq!(start + 10, nes.cpu.cycles);
+
+    // step_instruction finishes an instruction step_cycle started
+    assert_eq!(1, nes.step_cycle());
+    assert_eq!(1, nes.step_instruction());
+    assert_eq!(0x8002, nes.cpu.register.PC);
+    assert_eq!(start + 12, nes.cpu.cycles);
+
+    let cycles = nes.run_until_scanline(241);
+    assert_eq!(241, nes.ppu.frame.scanline);
+    assert_eq!(start + 12 + cycles as u64, nes.cpu.cycles);
+    let cycles = nes.run_until_scanline(241);
+    assert!(cycles.abs_diff(CYCLES_PER_FRAME) <= 3, "{}", cycles);
+}
+
+#[test]
+fn step_cycle_matches_step_instruction() {
+    let program = [
+        // $8000 LDX #$00 / $8002 TXA / STA $0200,X / INX / BNE $8002
+        0xA2, 0x00, 0x8A, 0x9D, 0x00, 0x02, 0xE8, 0xD0, 0xF9,
+        // $8009 LDA #$02 / STA $4014
+        0xA9, 0x02, 0x8D, 0x14, 0x40,
+        // $800E JSR $8014 / $8011 JMP $800E / $8014 INC $10 / RTS
+        0x20, 0x14, 0x80, 0x4C, 0x0E, 0x80, 0xE6, 0x10, 0x60,
+    ];
+    let mut by_instruction = TestRom::new(&program).boot();
+    let mut by_cycle = TestRom::new(&program).boot();
+    while by_instruction.cpu.cycles < 5000 {
+        by_instruction.step_instruction();
+    }
+    while by_cycle.cpu.cycles < by_instruction.cpu.cycles {
+        assert_eq!(1, by_cycle.step_cycle());
+    }
+
+    assert_eq!(by_instruction.cpu.cycles, by_cycle.cpu.cycles);
+    assert_eq!(by_instruction.cpu.register.PC, by_cycle.cpu.register.PC);
+    assert_eq!(by_instruction.cpu.register.S, by_cycle.cpu.register.S);
+    assert_eq!(by_instruction.cpu.wram, by_cycle.cpu.wram);
+    assert_eq!(by_instruction.ppu.oam.primary, by_cycle.ppu.oam.primary);
+    assert_eq!(by_instruction.ppu.frame.dot, by_cycle.ppu.frame.dot);
+    assert_eq!(0xFF, by_cycle.ppu.oam.primary[0xFF]);
+}
+
+#[test]
+fn step_cycle_stops_inside_dma() {
+    // $8000 LDA #$02 / STA $4014 / $8005 JMP $8005
+    let mut nes = TestRom::new(&[0xA9, 0x02, 0x8D, 0x14, 0x40, 0x4C, 0x05, 0x80]).boot();
+    // attribute bytes drop bits 2-4, so pick a value every OAM byte keeps
+    nes.cpu.wram[0x200..0x300].fill(0x43);
+    nes.step_instruction();
+    nes.step_instruction();
+    let start = nes.cpu.cycles;
+    // halt, maybe an alignment cycle, then a read and a write per byte
+    for _ in 0..200 {
+        nes.step_cycle();
+    }
+    assert_eq!(start + 200, nes.cpu.cycles);
+    let copied = nes.ppu.oam.primary.iter().filter(|&&v| v == 0x43).count();
+    assert!((98..=100).contains(&copied), "{}", copied);
+
+    let cycles = nes.step_instruction();
+    assert_eq!(start + 200 + cycles as u64, nes.cpu.cycles);
+    assert!(
+        (513 + 3 - 200..=514 + 3 - 200).contains(&cycles),
+        "{}",
+        cycles
+    );
+    assert!(nes.ppu.oam.primary.iter().all(|&v| v == 0x43));
+}
+
+#[test]
+fn region_frame_timing() {
+    // NES 2.0 header, byte 12 = 1 (PAL)
+    let mut nes = TestRom::idle().header(7, 0x08).header(12, 1).boot();
+    assert_eq!(Region::Pal, nes.region);
+    nes.run_frame();
+    // 341 * 312 dots at 3.2 dots per CPU cycle
+    let cycles = nes.run_frame();
+    assert!(cycles.abs_diff(33248) <= 3, "{}", cycles);
+
+    nes.set_region(Region::Dendy);
+    nes.run_frame();
+    // 341 * 312 dots at 3 dots per CPU cycle
+    let cycles = nes.run_frame();
+    assert!(cycles.abs_diff(35464) <= 3, "{}", cycles);
+    // vblank starts late on Dendy
+    nes.run_until_scanline(290);
+    assert!(!nes.ppu.register.PPU_STATUS.vblank);
+    nes.run_until_scanline(292);
+    assert!(nes.ppu.register.PPU_STATUS.vblank);
+}
+
+#[test]
+fn subcarrier_phase_alternates_with_rendering() {
+    // $8000 LDA #$18 / $8002 STA $2001 / $8005 JMP $8005
+    let mut nes = TestRom::new(&[0xA9, 0x18, 0x8D, 0x01, 0x20, 0x4C, 0x05, 0x80]).boot();
+    nes.run_frame();
+    let phases: Vec<u8> = (0..4)
+        .map(|_| {
+            nes.run_frame();
+            nes.ppu.frame_buffer.phase
+        })
+        .collect();
+    // a full frame moves the phase by 4/12 cycle, the skipped dot of odd frames by 8/12 more
+    assert_eq!(phases[0], phases[2]);
+    assert_eq!(phases[1], phases[3]);
+    let step = (phases[1] + 12 - phases[0]) % 12;
+    assert!(step == 4 || step == 8, "{:?}", phases);
+    assert_eq!(256 * 240, nes.ppu.frame_buffer.indices().len());
+}
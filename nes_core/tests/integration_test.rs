@@ -1,7 +1,10 @@
 use std::{fs::File, io::Read};
 
-use nes_core::adapter::{
-    audio::AudioAdapter, cartridge::CartridgeAdapter, nes::NesAdapter, video::VideoAdapter,
+use nes_core::{
+    adapter::{
+        audio::AudioAdapter, cartridge::CartridgeAdapter, nes::NesAdapter, video::VideoAdapter,
+    },
+    entity::frame_buffer::FrameBuffer,
 };
 
 pub struct CartridgeCtx {
@@ -27,7 +30,7 @@ impl CartridgeAdapter for CartridgeCtx {
 #[derive(Default)]
 pub struct VideoCtx;
 impl VideoAdapter for VideoCtx {
-    fn draw_frame(&mut self, _pixels: [[u8; 3]; 256 * 240]) {}
+    fn draw_frame(&mut self, _frame: &FrameBuffer) {}
 }
 
 #[derive(Default)]
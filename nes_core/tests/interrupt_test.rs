@@ -0,0 +1,82 @@
+mod common;
+
+use common::TestRom;
+use nes_core::usecase::nes::NesState;
+
+/// Boots `program` at $8000 with NMI and IRQ pointing to a NOP sled at $9000.
+fn boot(program: &[u8]) -> NesState {
+    TestRom::new(program)
+        .at(0xFFFA, &[0x00, 0x90])
+        .at(0xFFFE, &[0x00, 0x90])
+        .boot()
+}
+
+/// Return address and status pushed by the last interrupt.
+fn pushed(nes: &NesState) -> (u16, u8) {
+    let s = nes.cpu.register.S as usize;
+    let p = nes.cpu.wram[0x100 + s + 1];
+    let pc = u16::from_le_bytes([nes.cpu.wram[0x100 + s + 2], nes.cpu.wram[0x100 + s + 3]]);
+    (pc, p)
+}
+
+#[test]
+fn cli_delays_irq_by_one_instruction() {
+    // $8000 CLI / $8001 NOP / $8002 NOP
+    let mut nes = boot(&[0x58, 0xEA, 0xEA]);
+    nes.cpu.control.IRQ = true;
+    nes.step_instruction();
+    nes.step_instruction();
+    assert_eq!(0x8002, nes.cpu.register.PC);
+    nes.step_instruction();
+    assert_eq!(0x9001, nes.cpu.register.PC);
+    assert_eq!(0x8002, pushed(&nes).0);
+}
+
+#[test]
+fn irq_taken_right_after_sei() {
+    // $8000 SEI / $8001 NOP
+    let mut nes = boot(&[0x78, 0xEA]);
+    nes.cpu.register.P.I = false;
+    nes.cpu.control.IRQ = true;
+    nes.step_instruction();
+    nes.step_instruction();
+    assert_eq!(0x9001, nes.cpu.register.PC);
+    let (pc, p) = pushed(&nes);
+    assert_eq!(0x8001, pc);
+    // the pushed status already has I set
+    assert_eq!(0x04, p & 0x14);
+}
+
+#[test]
+fn enabling_nmi_during_vblank() {
+    // $8000 LDA #$80 / $8002 STA $2000 / $8005 NOP / $8006 NOP
+    let mut nes = boot(&[0xA9, 0x80, 0x8D, 0x00, 0x20, 0xEA, 0xEA]);
+    nes.ppu.register.PPU_STATUS.vblank = true;
+    nes.step_instruction();
+    nes.step_instruction();
+    assert!(nes.cpu.control.NMI);
+    // the edge is polled during the next instruction, not the write cycle
+    nes.step_instruction();
+    assert_eq!(0x8006, nes.cpu.register.PC);
+    nes.step_instruction();
+    assert_eq!(0x9001, nes.cpu.register.PC);
+    assert_eq!(0x8006, pushed(&nes).0);
+
+    // the line stays high, so no second NMI
+    nes.step_instruction();
+    assert_eq!(0x9002, nes.cpu.register.PC);
+}
+
+#[test]
+fn reading_status_before_vblank_suppresses_nmi() {
+    let mut nes = TestRom::idle().at(0xFFFA, &[0x00, 0x90]).boot();
+    nes.write_ppu(0x2000, 0x80);
+    nes.run_until_scanline(241);
+    assert_eq!(1, nes.ppu.frame.dot);
+    assert_eq!(0x00, nes.read_ppu(0x2002) & 0x80);
+    nes.step_instruction();
+    nes.step_instruction();
+    assert!(!nes.ppu.register.PPU_STATUS.vblank);
+    assert!(!nes.cpu.control.NMI);
+    assert_eq!(0x8000, nes.cpu.register.PC);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::TestRom;
+
+#[test]
+fn cpu_open_bus() {
+    // $8000 LDA $4000 / $8003 LDA $5000 / $8006 LDA $4016 / $8009 LDA $4015
+    let mut nes = TestRom::new(&[
+        0xAD, 0x00, 0x40, 0xAD, 0x00, 0x50, 0xAD, 0x16, 0x40, 0xAD, 0x15, 0x40,
+    ])
+    .boot();
+    // the last byte on the bus is the high byte of the operand
+    nes.step_instruction();
+    assert_eq!(0x40, nes.cpu.register.A);
+    nes.step_instruction();
+    assert_eq!(0x50, nes.cpu.register.A);
+    nes.step_instruction();
+    assert_eq!(0x40, nes.cpu.register.A & 0xE0);
+
+    // $4015 does not drive the bus
+    nes.cpu.open_bus = 0xFF;
+    nes.cpu.register.PC = 0x8009;
+    nes.step_instruction();
+    assert_eq!(0x00, nes.cpu.register.A & 0x20);
+    assert_eq!(0x40, nes.cpu.open_bus);
+}
+
+#[test]
+fn execute_open_bus() {
+    // $8000 JMP $4060: the opcode fetch at $4060 reads $40, RTI
+    let mut nes = TestRom::new(&[0x4C, 0x60, 0x40]).boot();
+    nes.cpu.register.S = 0xFD;
+    nes.cpu.wram[0x1FF] = 0x10;
+    nes.cpu.wram[0x100] = 0x80;
+    nes.step_instruction();
+    assert_eq!(0x4060, nes.cpu.register.PC);
+    nes.step_instruction();
+    assert_eq!(0x8010, nes.cpu.register.PC);
+}
+
+#[test]
+fn ppu_latch_decays() {
+    let mut nes = TestRom::idle().boot();
+    nes.write_ppu(0x2005, 0xFF);
+    assert_eq!(0xFF, nes.read_ppu(0x2000));
+    assert_eq!(0x1F, nes.read_ppu(0x2002) & 0x1F);
+
+    for _ in 0..30 {
+        nes.run_frame();
+    }
+    assert_eq!(0x1F, nes.read_ppu(0x2001) & 0x1F);
+    for _ in 0..10 {
+        nes.run_frame();
+    }
+    // the status bits were driven by the $2002 read and have decayed too
+    assert_eq!(0x00, nes.read_ppu(0x2001));
+}
+
+#[test]
+fn palette_reads_keep_upper_latch_bits() {
+    let mut nes = TestRom::new(&[]).boot();
+    nes.poke_ppu(0x3F00, 0x2A);
+    nes.write_ppu(0x2006, 0x3F);
+    nes.write_ppu(0x2006, 0x00);
+    assert_eq!(0x2A, nes.read_ppu(0x2007));
+    nes.write_ppu(0x2006, 0x3F);
+    nes.write_ppu(0x2006, 0x00);
+    // any write loads the latch; OAMADDR is harmless here
+    nes.write_ppu(0x2003, 0xFF);
+    assert_eq!(0xC0 | 0x2A, nes.read_ppu(0x2007));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::TestRom;
+use nes_core::{
+    entity::{frame_buffer::PixelFormat, nes_rgb::NES_RGB_EMPHASIS},
+    usecase::nes::NesState,
+};
+
+/// Boots with rendering off, `colour` in $3F00 and $3F01 = `other`, and v left at `v`.
+fn boot(rom: TestRom, colour: u8, other: u8, v: u16) -> NesState {
+    setup(rom.boot(), colour, other, v)
+}
+
+fn setup(mut nes: NesState, colour: u8, other: u8, v: u16) -> NesState {
+    nes.write_ppu(0x2006, 0x3F);
+    nes.write_ppu(0x2006, 0x00);
+    nes.write_ppu(0x2007, colour);
+    nes.write_ppu(0x2007, other);
+    nes.write_ppu(0x2006, (v >> 8) as u8);
+    nes.write_ppu(0x2006, v as u8);
+    nes
+}
+
+fn pixel(nes: &mut NesState) -> [u8; 3] {
+    nes.run_until_scanline(11);
+    nes.ppu.frame_buffer.rgb(128, 10, &nes.ppu.palette)
+}
+
+fn rom() -> TestRom {
+    TestRom::idle()
+}
+
+#[test]
+fn emphasis_selects_palette_bank() {
+    for emphasis in 0..8_u8 {
+        let mut nes = boot(rom(), 0x30, 0x0F, 0x2000);
+        nes.write_ppu(0x2001, emphasis << 5);
+        assert_eq!(
+            NES_RGB_EMPHASIS[(emphasis as usize) << 6 | 0x30],
+            pixel(&mut nes)
+        );
+    }
+}
+
+#[test]
+fn pal_swaps_red_and_green_emphasis() {
+    let mut nes = boot(rom().header(9, 1), 0x30, 0x0F, 0x2000);
+    // PPUMASK bit5 is green on the 2C07
+    nes.write_ppu(0x2001, 0x20);
+    assert_eq!(NES_RGB_EMPHASIS[2 << 6 | 0x30], pixel(&mut nes));
+}
+
+#[test]
+fn grayscale_backdrop_with_rendering_disabled() {
+    let mut nes = boot(rom(), 0x16, 0x0F, 0x2000);
+    nes.write_ppu(0x2001, 0x01);
+    assert_eq!(NES_RGB_EMPHASIS[0x10], pixel(&mut nes));
+}
+
+#[test]
+fn backdrop_follows_v_into_palette_ram() {
+    let mut nes = boot(rom(), 0x16, 0x2A, 0x3F01);
+    assert_eq!(NES_RGB_EMPHASIS[0x2A], pixel(&mut nes));
+    nes.write_ppu(0x2001, 0x01);
+    assert_eq!(NES_RGB_EMPHASIS[0x20], pixel(&mut nes));
+}
+
+#[test]
+fn indexed_frames_keep_emphasis() {
+    let nes = rom().boot_with_format(PixelFormat::Indexed);
+    let mut nes = setup(nes, 0x16, 0x0F, 0x2000);
+    nes.write_ppu(0x2001, 0xA0);
+    nes.run_until_scanline(11);
+    let i = (10 * 256 + 128) * 2;
+    assert_eq!(
+        (5 << 6 | 0x16_u16).to_le_bytes(),
+        nes.ppu.frame_buffer.data()[i..i + 2]
+    );
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::TestRom;
+
+#[test]
+fn peek_has_no_side_effects() {
+    let mut nes = TestRom::new(&[]).chr(0x0010, &[0xAB]).boot();
+
+    nes.ppu.register.PPU_STATUS.vblank = true;
+    assert_eq!(0x80, nes.peek_cpu(0x2002) & 0x80);
+    assert_eq!(0x80, nes.peek_cpu(0x2002) & 0x80);
+    assert_eq!(0x80, nes.read_ppu(0x2002) & 0x80);
+    assert_eq!(0x00, nes.peek_cpu(0x2002) & 0x80);
+
+    nes.ppu.loopy.v_addr.set_addr(0x0010);
+    nes.peek_cpu(0x2007);
+    assert_eq!(0x0010, nes.ppu.loopy.v_addr.get_addr());
+    assert_eq!(0xAB, nes.peek_ppu(0x0010));
+
+    nes.joypad.state_1p.B = true;
+    nes.write_joypad_strobe(true);
+    nes.write_joypad_strobe(false);
+    assert_eq!(0x00, nes.peek_cpu(0x4016) & 0x1F);
+    assert_eq!(0x00, nes.peek_cpu(0x4016) & 0x1F);
+    assert_eq!(nes.cartridge.read_prg(0xFFFC), Some(nes.peek_cpu(0xFFFC)));
+}
+
+#[test]
+fn poke_edits_memory() {
+    let mut nes = TestRom::new(&[]).boot();
+
+    nes.poke_cpu(0x0801, 0x12);
+    assert_eq!(0x12, nes.cpu.wram[0x0001]);
+    nes.poke_cpu(0xC000, 0x56);
+    assert_eq!(0x56, nes.peek_cpu(0x8000));
+
+    nes.poke_ppu(0x3F10, 0x21);
+    assert_eq!(0x21, nes.peek_ppu(0x3F00));
+    nes.poke_ppu(0x0000, 0x78);
+    assert_eq!(0x78, nes.peek_ppu(0x0000));
+}
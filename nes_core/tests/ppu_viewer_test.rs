@@ -0,0 +1,76 @@
+mod common;
+
+use common::TestRom;
+use nes_core::usecase::nes::NesState;
+
+/// Tile 1 is solid colour 3, tile 2 has colour 1 in its top left pixel only.
+fn boot() -> NesState {
+    let mut nes = TestRom::idle()
+        .chr(0x10, &[0xFF; 16])
+        .chr(0x20, &[0x80])
+        .boot();
+    for (entry, colour) in [(0x00, 0x0F), (0x07, 0x16), (0x0F, 0x2A), (0x11, 0x30)] {
+        nes.poke_ppu(0x3F00 + entry, colour);
+    }
+    nes
+}
+
+fn rgb(nes: &NesState, colour: u8) -> [u8; 3] {
+    nes.ppu.palette.rgb[colour as usize]
+}
+
+#[test]
+fn nametable_view_uses_attributes_and_outlines_scroll() {
+    let mut nes = boot();
+    // tile 1 at the top left of the second nametable ($2400), attribute palette 1
+    nes.poke_ppu(0x2400, 1);
+    nes.poke_ppu(0x27C0, 0x01);
+    // scroll to x = 12, y = 20
+    nes.write_ppu(0x2005, 12);
+    nes.write_ppu(0x2005, 20);
+    let view = nes.nametable_view();
+    assert_eq!((512, 480), (view.width, view.height));
+    assert_eq!(rgb(&nes, 0x16), view.get(256 + 3, 3));
+    assert_eq!(rgb(&nes, 0x0F), view.get(256 + 8, 8));
+    assert_eq!([0xFF, 0x00, 0xFF], view.get(12, 20));
+    assert_eq!([0xFF, 0x00, 0xFF], view.get(12 + 255, 100));
+    assert_eq!([0xFF, 0x00, 0xFF], view.get(100, 20 + 239));
+    assert_ne!([0xFF, 0x00, 0xFF], view.get(100, 100));
+}
+
+#[test]
+fn pattern_table_view_uses_chosen_palette() {
+    let nes = boot();
+    let view = nes.pattern_table_view(3);
+    assert_eq!((256, 128), (view.width, view.height));
+    assert_eq!(rgb(&nes, 0x2A), view.get(8 + 4, 4));
+    assert_eq!(rgb(&nes, 0x0F), view.get(4, 4));
+    // the palette is chosen by the caller, not by the game
+    assert_eq!(rgb(&nes, 0x00), nes.pattern_table_view(0).get(8 + 4, 4));
+}
+
+#[test]
+fn oam_view_applies_attributes() {
+    let mut nes = boot();
+    // sprite 1: tile 2, palette 0, flipped both ways, behind the background
+    nes.ppu.oam.primary[4..8].copy_from_slice(&[0x10, 0x02, 0xE0, 0x20]);
+    let sprites = nes.sprites();
+    assert_eq!(2, sprites[1].tile);
+    assert!(sprites[1].flip_h() && sprites[1].flip_v() && sprites[1].behind_background());
+    let view = nes.oam_view();
+    assert_eq!((73, 137), (view.width, view.height));
+    // its only pixel moved to the bottom right of the 8x8 tile in the second cell
+    assert_eq!(rgb(&nes, 0x30), view.get(9 + 1 + 7, 1 + 7));
+    assert_eq!(rgb(&nes, 0x0F), view.get(9 + 1, 1));
+    assert_eq!([0x20, 0x40, 0xC0], view.get(9, 5));
+    assert_eq!([0x40, 0x40, 0x40], view.get(0, 5));
+}
+
+#[test]
+fn palette_view_shows_palette_ram() {
+    let nes = boot();
+    let view = nes.palette_view();
+    assert_eq!((256, 32), (view.width, view.height));
+    assert_eq!(rgb(&nes, 0x16), view.get(7 * 16 + 8, 8));
+    assert_eq!(rgb(&nes, 0x30), view.get(16 + 8, 16 + 8));
+}
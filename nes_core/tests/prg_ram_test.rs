@@ -0,0 +1,38 @@
+mod common;
+
+use common::TestRom;
+use nes_core::usecase::ram_search::{Comparison, SearchView};
+
+// $8000 LDA #$34 / STA $6001 / LDA $6001 / STA $10 / $800A JMP $800A
+const PROGRAM: [u8; 13] = [
+    0xA9, 0x34, 0x8D, 0x01, 0x60, 0xAD, 0x01, 0x60, 0x85, 0x10, 0x4C, 0x0A, 0x80,
+];
+
+#[test]
+fn cpu_reads_and_writes_prg_ram() {
+    // iNES byte 8: 1 x 8KiB of PRG-RAM
+    let mut nes = TestRom::new(&PROGRAM).header(8, 1).boot();
+    let mut search = nes.start_ram_search(SearchView::default());
+    for _ in 0..4 {
+        nes.step_instruction();
+    }
+    assert_eq!(0x34, nes.cartridge.prg_ram[1]);
+    assert_eq!(0x34, nes.cpu.wram[0x10]);
+    assert_eq!(0x34, nes.peek_cpu(0x6001));
+    nes.poke_cpu(0x6002, 0x56);
+    assert_eq!(0x56, nes.cartridge.prg_ram[2]);
+
+    nes.filter_ram_search(&mut search, Comparison::EqualTo(0x34));
+    assert_eq!(vec![(0x0010, 0x34), (0x6001, 0x34)], search.candidates());
+}
+
+#[test]
+fn no_prg_ram_leaves_the_bus_open() {
+    let mut nes = TestRom::new(&PROGRAM).boot();
+    for _ in 0..4 {
+        nes.step_instruction();
+    }
+    // LDA $6001 reads back the high byte of its operand
+    assert_eq!(0x60, nes.cpu.wram[0x10]);
+    assert!(nes.cartridge.prg_ram.is_empty());
+}
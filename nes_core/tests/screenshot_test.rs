@@ -0,0 +1,76 @@
+mod common;
+
+use common::TestRom;
+use nes_core::{
+    entity::overscan::Overscan,
+    usecase::{
+        nes::NesState,
+        screenshot::{ScreenshotOptions, INDEX_CHUNK},
+    },
+};
+
+/// Chunk types and data of a PNG file.
+fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+    let mut chunks = Vec::new();
+    let mut at = 8;
+    while at < png.len() {
+        let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+        let kind = png[at + 4..at + 8].try_into().unwrap();
+        chunks.push((kind, png[at + 8..at + 8 + len].to_vec()));
+        at += len + 12;
+    }
+    chunks
+}
+
+/// Rendering off with a $16 backdrop and blue emphasis.
+fn boot() -> NesState {
+    let mut nes = TestRom::idle().boot();
+    nes.poke_ppu(0x3F00, 0x16);
+    nes.write_ppu(0x2001, 0x80);
+    nes.run_frame();
+    nes.run_frame();
+    nes
+}
+
+#[test]
+fn screenshot_size_follows_overscan() {
+    let nes = boot();
+    let png = nes.screenshot(&ScreenshotOptions::default());
+    let header = &chunks(&png)[0];
+    assert_eq!(*b"IHDR", header.0);
+    assert_eq!([0, 0, 1, 0, 0, 0, 0, 240], header.1[..8]);
+
+    let png = nes.screenshot(&ScreenshotOptions {
+        overscan: Overscan::NTSC,
+        metadata: false,
+    });
+    let chunks = chunks(&png);
+    assert_eq!([0, 0, 1, 0, 0, 0, 0, 224], chunks[0].1[..8]);
+    assert!(chunks.iter().all(|(kind, _)| *kind != INDEX_CHUNK));
+}
+
+#[test]
+fn screenshot_metadata_keeps_palette_indices() {
+    let nes = boot();
+    let png = nes.screenshot(&ScreenshotOptions {
+        overscan: Overscan::NTSC,
+        metadata: true,
+    });
+    let chunks = chunks(&png);
+    let text = |key: &str| {
+        chunks
+            .iter()
+            .filter(|(kind, _)| kind == b"tEXt")
+            .find_map(|(_, data)| data.strip_prefix(format!("{}\0", key).as_bytes()))
+            .map(|value| String::from_utf8(value.to_vec()).unwrap())
+    };
+    assert_eq!(Some(String::from("blue")), text("Emphasis"));
+    assert_eq!(Some(String::from("8,8,0,0")), text("Overscan"));
+    let (_, indices) = chunks
+        .iter()
+        .find(|(kind, _)| *kind == INDEX_CHUNK)
+        .unwrap();
+    // the whole frame, whatever the overscan
+    assert_eq!(256 * 240 * 2, indices.len());
+    assert_eq!((4 << 6 | 0x16_u16).to_le_bytes(), indices[..2]);
+}
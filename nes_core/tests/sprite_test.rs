@@ -0,0 +1,120 @@
+mod common;
+
+use common::TestRom;
+use nes_core::usecase::nes::NesState;
+
+/// Boots with rendering on and OAM filled with `sprites` (4 bytes each), the rest $FF.
+fn boot(sprites: &[[u8; 4]]) -> NesState {
+    let mut nes = TestRom::idle().boot();
+    nes.ppu.oam.primary.fill(0xFF);
+    for (i, sprite) in sprites.iter().enumerate() {
+        nes.ppu.oam.primary[i * 4..i * 4 + 4].copy_from_slice(sprite);
+    }
+    nes.write_ppu(0x2001, 0x18);
+    nes
+}
+
+#[test]
+fn eight_sprites_do_not_overflow() {
+    let mut nes = boot(&[[10, 0, 0, 0]; 8]);
+    nes.run_until_scanline(11);
+    assert!(!nes.ppu.register.PPU_STATUS.spr_ovf);
+    assert_eq!(7, nes.ppu.oam.imaginary[7].id);
+}
+
+#[test]
+fn nine_sprites_overflow() {
+    let mut nes = boot(&[[10, 0, 0, 0]; 9]);
+    nes.run_until_scanline(10);
+    assert!(!nes.ppu.register.PPU_STATUS.spr_ovf);
+    nes.run_until_scanline(11);
+    assert!(nes.ppu.register.PPU_STATUS.spr_ovf);
+}
+
+#[test]
+fn overflow_bug_false_positive() {
+    // after 8 hits, sprite 8 misses and the check reads sprite 9's tile as Y
+    let mut sprites = [[10, 0, 0, 0]; 10];
+    sprites[8] = [100, 0, 0, 0];
+    sprites[9] = [100, 10, 0, 0];
+    let mut nes = boot(&sprites);
+    nes.run_until_scanline(11);
+    assert!(nes.ppu.register.PPU_STATUS.spr_ovf);
+}
+
+#[test]
+fn overflow_bug_false_negative() {
+    // sprite 9 is on the line, but the check reads its tile byte instead of Y
+    let mut sprites = [[10, 0, 0, 0]; 10];
+    sprites[8] = [100, 0, 0, 0];
+    let mut nes = boot(&sprites);
+    nes.run_until_scanline(11);
+    assert!(!nes.ppu.register.PPU_STATUS.spr_ovf);
+}
+
+#[test]
+fn oam_data_reads_during_rendering() {
+    let mut nes = boot(&[[10, 0x22, 0, 0]]);
+    nes.run_until_scanline(10);
+    assert!((1..=64).contains(&nes.ppu.frame.dot));
+    assert_eq!(0xFF, nes.read_ppu(0x2004));
+
+    // outside rendering, $2004 reads OAM
+    nes.write_ppu(0x2001, 0x00);
+    nes.write_ppu(0x2003, 0x01);
+    assert_eq!(0x22, nes.read_ppu(0x2004));
+}
+
+/// Tile 0 is solid colour 3, so the background is opaque everywhere. The background
+/// draws $16, sprites $2A and the backdrop is $0F.
+fn boot_visible(sprites: &[[u8; 4]], mask: u8) -> NesState {
+    let mut nes = boot(sprites);
+    for addr in 0x0000..0x0010 {
+        nes.poke_ppu(addr, 0xFF);
+    }
+    for (entry, colour) in [(0x00, 0x0F), (0x03, 0x16), (0x13, 0x2A)] {
+        nes.poke_ppu(0x3F00 + entry, colour);
+    }
+    nes.write_ppu(0x2001, mask);
+    nes
+}
+
+fn colour(nes: &NesState, x: usize, y: usize) -> [u8; 3] {
+    nes.ppu.frame_buffer.rgb(x, y, &nes.ppu.palette)
+}
+
+#[test]
+fn hidden_background_still_hits_sprite_0() {
+    let mut nes = boot_visible(&[[10, 0, 0, 20]], 0x1E);
+    nes.ppu.layers.hide_background = true;
+    nes.run_until_scanline(20);
+    assert!(nes.ppu.register.PPU_STATUS.spr_hit);
+    assert_eq!(nes.ppu.palette.rgb[0x0F], colour(&nes, 100, 14));
+    assert_eq!(nes.ppu.palette.rgb[0x2A], colour(&nes, 24, 14));
+}
+
+#[test]
+fn hidden_sprites_still_hit_and_overflow() {
+    let mut nes = boot_visible(&[[10, 0, 0, 20]; 9], 0x1E);
+    nes.ppu.layers.hide_sprites = true;
+    nes.run_until_scanline(20);
+    assert!(nes.ppu.register.PPU_STATUS.spr_hit);
+    assert!(nes.ppu.register.PPU_STATUS.spr_ovf);
+    assert_eq!(nes.ppu.palette.rgb[0x16], colour(&nes, 24, 14));
+}
+
+#[test]
+fn no_sprite_limit_draws_every_sprite() {
+    let sprites: Vec<[u8; 4]> = (0..10).map(|i| [10, 0, 0, i * 16]).collect();
+    // sprites only, so the backdrop shows around them
+    for no_sprite_limit in [false, true] {
+        let mut nes = boot_visible(&sprites, 0x14);
+        nes.ppu.layers.no_sprite_limit = no_sprite_limit;
+        nes.run_until_scanline(20);
+        assert!(nes.ppu.register.PPU_STATUS.spr_ovf);
+        assert_eq!(nes.ppu.palette.rgb[0x2A], colour(&nes, 7 * 16 + 4, 14));
+        let expected = if no_sprite_limit { 0x2A } else { 0x0F };
+        assert_eq!(nes.ppu.palette.rgb[expected], colour(&nes, 8 * 16 + 4, 14));
+        assert_eq!(nes.ppu.palette.rgb[expected], colour(&nes, 9 * 16 + 4, 14));
+    }
+}
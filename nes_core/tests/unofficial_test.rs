@@ -0,0 +1,105 @@
+mod common;
+
+use common::TestRom;
+use nes_core::usecase::nes::NesState;
+
+/// Runs `program` at $8000 one instruction at a time.
+fn run(program: &[u8], a: u8, x: u8, y: u8) -> NesState {
+    let mut nes = TestRom::new(program).boot();
+    nes.cpu.register.A = a;
+    nes.cpu.register.X = x;
+    nes.cpu.register.Y = y;
+    nes.step_instruction();
+    nes
+}
+
+#[test]
+fn immediate_ops() {
+    // ANC #$80
+    let nes = run(&[0x0B, 0x80], 0xC0, 0, 0);
+    assert_eq!(0x80, nes.cpu.register.A);
+    assert!(nes.cpu.register.P.C && nes.cpu.register.P.N);
+
+    // ALR #$03
+    let nes = run(&[0x4B, 0x03], 0xFF, 0, 0);
+    assert_eq!(0x01, nes.cpu.register.A);
+    assert!(nes.cpu.register.P.C);
+
+    // ARR #$FF with C clear: $C0 >> 1 = $60, C = bit 6, V = bit 6 ^ bit 5
+    let nes = run(&[0x6B, 0xFF], 0xC0, 0, 0);
+    assert_eq!(0x60, nes.cpu.register.A);
+    assert!(nes.cpu.register.P.C);
+    assert!(!nes.cpu.register.P.V);
+
+    // AXS #$01: X = ($0F & $F3) - 1
+    let nes = run(&[0xCB, 0x01], 0x0F, 0xF3, 0);
+    assert_eq!(0x02, nes.cpu.register.X);
+    assert!(nes.cpu.register.P.C);
+
+    // ANE #$FF and LXA #$0F with the $EE constant
+    let nes = run(&[0x8B, 0xFF], 0x00, 0x0F, 0);
+    assert_eq!(0x0E, nes.cpu.register.A);
+    let nes = run(&[0xAB, 0x0F], 0x01, 0, 0);
+    assert_eq!(0x0F, nes.cpu.register.A);
+    assert_eq!(0x0F, nes.cpu.register.X);
+}
+
+#[test]
+fn las_and_tas() {
+    // LAS $0200,Y
+    let mut nes = TestRom::new(&[0xBB, 0x00, 0x02]).boot();
+    nes.cpu.wram[0x200] = 0xF0;
+    nes.cpu.register.S = 0x3C;
+    nes.step_instruction();
+    assert_eq!(0x30, nes.cpu.register.A);
+    assert_eq!(0x30, nes.cpu.register.X);
+    assert_eq!(0x30, nes.cpu.register.S);
+
+    // TAS $0200,Y: S = A & X = $33, stores $33 & $03
+    let nes = run(&[0x9B, 0x00, 0x02], 0x37, 0x3B, 0);
+    assert_eq!(0x33, nes.cpu.register.S);
+    assert_eq!(0x03, nes.cpu.wram[0x200]);
+}
+
+#[test]
+fn sh_stores() {
+    // SHX $0300,Y stores X & $04
+    let nes = run(&[0x9E, 0x00, 0x03], 0, 0xFF, 0x10);
+    assert_eq!(0x04, nes.cpu.wram[0x310]);
+    // SHY $0300,X
+    let nes = run(&[0x9C, 0x00, 0x03], 0, 0x10, 0x07);
+    assert_eq!(0x04, nes.cpu.wram[0x310]);
+    // SHA $0300,Y stores A & X & $04
+    let nes = run(&[0x9F, 0x00, 0x03], 0x0C, 0x06, 0x01);
+    assert_eq!(0x04, nes.cpu.wram[0x301]);
+
+    // crossing a page replaces the high byte: SHX $01FF,Y with Y = 1 and X = 5
+    // stores 5 & $02 = 0 at $0000
+    let mut nes = TestRom::new(&[0x9E, 0xFF, 0x01]).boot();
+    nes.cpu.wram[0] = 0xAA;
+    nes.cpu.register.X = 0x05;
+    nes.cpu.register.Y = 0x01;
+    nes.step_instruction();
+    assert_eq!(0x00, nes.cpu.wram[0]);
+    assert_eq!(0x00, nes.cpu.wram[0x200]);
+}
+
+#[test]
+fn jam_halts_cpu() {
+    // $8000 NOP / $8001 JAM
+    let mut nes = TestRom::new(&[0xEA, 0x02]).boot();
+    nes.step_instruction();
+    nes.step_instruction();
+    assert!(nes.cpu.jam);
+    assert_eq!(0x8001, nes.cpu.register.PC);
+
+    // the rest of the console keeps running
+    let cycles = nes.cpu.cycles;
+    nes.run_frame();
+    assert!(nes.cpu.cycles > cycles);
+    assert_eq!(0x8001, nes.cpu.register.PC);
+
+    nes.power();
+    assert!(!nes.cpu.jam);
+    assert_eq!(0x8000, nes.cpu.register.PC);
+}
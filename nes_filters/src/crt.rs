@@ -0,0 +1,56 @@
+use crate::image::{dim, Image};
+
+/// Darkens the last output row of every source line. `scale` is how many rows a source line
+/// covers; at 1x every row is doubled first so the gaps have somewhere to go.
+pub fn scanlines(src: &Image, scale: usize) -> Image {
+    let (mut dst, scale) = if scale < 2 {
+        let mut dst = Image::new(src.width, src.height * 2);
+        for (y, row) in dst.pixels.chunks_exact_mut(src.width).enumerate() {
+            row.copy_from_slice(&src.pixels[y / 2 * src.width..][..src.width]);
+        }
+        (dst, 2)
+    } else {
+        (src.clone(), scale)
+    };
+    for y in (scale - 1..dst.height).step_by(scale) {
+        for pixel in &mut dst.pixels[y * dst.width..(y + 1) * dst.width] {
+            *pixel = dim(*pixel, [5; 3], 8);
+        }
+    }
+    dst
+}
+
+/// Vertical red, green and blue phosphor stripes, one output column each.
+pub fn aperture_grille(src: &Image) -> Image {
+    const MASK: [[u32; 3]; 3] = [[8, 5, 5], [5, 8, 5], [5, 5, 8]];
+    let mut dst = src.clone();
+    for row in dst.pixels.chunks_exact_mut(dst.width) {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = dim(*pixel, MASK[x % 3], 8);
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _scanlines() {
+        let mut src = Image::new(1, 2);
+        src.pixels.fill(0xFFFFFF);
+        let dst = scanlines(&src, 1);
+        assert_eq!(vec![0xFFFFFF, 0x9F9F9F, 0xFFFFFF, 0x9F9F9F], dst.pixels);
+    }
+
+    #[test]
+    fn _aperture_grille() {
+        let mut src = Image::new(3, 1);
+        src.pixels.fill(0xFFFFFF);
+        assert_eq!(
+            vec![0xFF9F9F, 0x9FFF9F, 0x9F9FFF],
+            aperture_grille(&src).pixels
+        );
+    }
+}
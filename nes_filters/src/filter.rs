@@ -0,0 +1,255 @@
+use std::str::FromStr;
+
+use nes_core::entity::{
+    frame_buffer::FrameBuffer,
+    palette::{NtscParams, Palette},
+};
+
+use crate::{
+    crt, hqx,
+    image::Image,
+    ntsc::{self, NtscPreset, NtscSettings},
+    scale, smooth, xbrz,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Plain pixel repetition by the given factor.
+    Nearest(usize),
+    Scale2x,
+    Scale3x,
+    /// hq2x and hq3x by Maxim Stepin.
+    Hq2x,
+    Hq3x,
+    /// Edge-aware smoothing, in the spirit of hqx.
+    Smooth2x,
+    Smooth3x,
+    /// xBRZ by the given factor (2 or 3).
+    Xbrz(usize),
+    /// Overlay darkening the gap between lines.
+    Scanlines,
+    /// Overlay of RGB phosphor stripes.
+    ApertureGrille,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nearest2x" => Ok(Filter::Nearest(2)),
+            "nearest3x" => Ok(Filter::Nearest(3)),
+            "nearest4x" => Ok(Filter::Nearest(4)),
+            "scale2x" => Ok(Filter::Scale2x),
+            "scale3x" => Ok(Filter::Scale3x),
+            "hq2x" => Ok(Filter::Hq2x),
+            "hq3x" => Ok(Filter::Hq3x),
+            "smooth2x" => Ok(Filter::Smooth2x),
+            "smooth3x" => Ok(Filter::Smooth3x),
+            "xbrz2x" => Ok(Filter::Xbrz(2)),
+            "xbrz3x" => Ok(Filter::Xbrz(3)),
+            "scanlines" => Ok(Filter::Scanlines),
+            "aperture" => Ok(Filter::ApertureGrille),
+            _ => Err(format!("unknown filter {}", s)),
+        }
+    }
+}
+
+impl Filter {
+    /// Output size for an input of `size`, whose lines are `line_scale` rows tall.
+    pub fn output_size(self, (width, height): (usize, usize), line_scale: usize) -> (usize, usize) {
+        match self {
+            Filter::Nearest(n) | Filter::Xbrz(n) => (width * n, height * n),
+            Filter::Scale2x | Filter::Hq2x | Filter::Smooth2x => (width * 2, height * 2),
+            Filter::Scale3x | Filter::Hq3x | Filter::Smooth3x => (width * 3, height * 3),
+            Filter::Scanlines if line_scale < 2 => (width, height * 2),
+            Filter::Scanlines | Filter::ApertureGrille => (width, height),
+        }
+    }
+
+    pub fn apply(self, image: &Image, line_scale: usize) -> Image {
+        match self {
+            Filter::Nearest(n) => scale::nearest(image, n),
+            Filter::Scale2x => scale::scale2x(image),
+            Filter::Scale3x => scale::scale3x(image),
+            Filter::Hq2x => hqx::hq2x(image),
+            Filter::Hq3x => hqx::hq3x(image),
+            Filter::Smooth2x => smooth::smooth2x(image),
+            Filter::Smooth3x => smooth::smooth3x(image),
+            Filter::Xbrz(n) => xbrz::xbrz(image, n),
+            Filter::Scanlines => crt::scanlines(image, line_scale),
+            Filter::ApertureGrille => crt::aperture_grille(image),
+        }
+    }
+}
+
+/// Filters applied one after another, e.g. `"scale2x,scanlines"`. The NTSC filter reads palette
+/// indices rather than colours, so it can only come first: `"ntsc-composite,scanlines"`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterChain {
+    pub ntsc: Option<NtscSettings>,
+    pub filters: Vec<Filter>,
+}
+
+impl FromStr for FilterChain {
+    type Err = String;
+
+    /// "none" or an empty string gives an empty chain.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chain = Self::default();
+        let names = s
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("none"));
+        for (n, name) in names.enumerate() {
+            let lower = name.to_ascii_lowercase();
+            if let Some(preset) = lower.strip_prefix("ntsc") {
+                if n > 0 {
+                    return Err(format!("{} must be the first filter", name));
+                }
+                let preset: NtscPreset = match preset.strip_prefix('-') {
+                    Some(preset) => preset.parse()?,
+                    None if preset.is_empty() => NtscPreset::default(),
+                    None => return Err(format!("unknown filter {}", name)),
+                };
+                chain.ntsc = Some(preset.settings());
+            } else {
+                chain.filters.push(name.parse()?);
+            }
+        }
+        Ok(chain)
+    }
+}
+
+impl FilterChain {
+    pub fn is_empty(&self) -> bool {
+        self.ntsc.is_none() && self.filters.is_empty()
+    }
+
+    /// Sets the TV adjustments the NTSC filter decodes with, if the chain starts with it.
+    pub fn set_ntsc_params(&mut self, params: NtscParams) {
+        if let Some(settings) = &mut self.ntsc {
+            settings.params = params;
+        }
+    }
+
+    /// Size of the pictures the chain produces from a PPU frame.
+    pub fn output_size(&self) -> (usize, usize) {
+        let mut size = (FrameBuffer::WIDTH, FrameBuffer::HEIGHT);
+        if self.ntsc.is_some() {
+            size.0 = ntsc::OUT_WIDTH;
+        }
+        for filter in &self.filters {
+            size = filter.output_size(size, size.1 / FrameBuffer::HEIGHT);
+        }
+        size
+    }
+
+    /// Runs the chain on a PPU frame; `palette` colours it unless the NTSC filter decodes it.
+    pub fn apply_frame(&self, frame: &FrameBuffer, palette: &Palette) -> Image {
+        let image = match &self.ntsc {
+            Some(settings) => ntsc::ntsc(frame, settings),
+            None => Image::from_frame(frame, palette),
+        };
+        self.apply(&image)
+    }
+
+    /// Runs the image filters, treating `image` as one row per line.
+    pub fn apply(&self, image: &Image) -> Image {
+        let lines = image.height;
+        let mut image = image.clone();
+        for filter in &self.filters {
+            image = filter.apply(&image, image.height / lines);
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::pack;
+
+    use super::*;
+
+    #[test]
+    fn _parse() {
+        let chain: FilterChain = "Scale2x, scanlines".parse().unwrap();
+        assert_eq!(None, chain.ntsc);
+        assert_eq!(vec![Filter::Scale2x, Filter::Scanlines], chain.filters);
+        assert!("none".parse::<FilterChain>().unwrap().is_empty());
+        assert!("blur".parse::<FilterChain>().is_err());
+    }
+
+    #[test]
+    fn _every_filter_keeps_flat_colour_and_scales() {
+        let mut src = Image::new(8, 6);
+        src.pixels.fill(0x204060);
+        for name in [
+            "nearest2x",
+            "nearest3x",
+            "nearest4x",
+            "scale2x",
+            "scale3x",
+            "hq2x",
+            "hq3x",
+            "smooth2x",
+            "smooth3x",
+            "xbrz2x",
+            "xbrz3x",
+        ] {
+            let filter: Filter = name.parse().unwrap();
+            let dst = FilterChain {
+                ntsc: None,
+                filters: vec![filter],
+            }
+            .apply(&src);
+            assert_eq!(
+                (dst.width, dst.height),
+                filter.output_size((8, 6), 1),
+                "{}",
+                name
+            );
+            assert!(dst.pixels.iter().all(|&p| p == 0x204060), "{}", name);
+        }
+    }
+
+    #[test]
+    fn _output_size() {
+        let chain: FilterChain = "scanlines".parse().unwrap();
+        assert_eq!((256, 480), chain.output_size());
+        let chain: FilterChain = "hq3x,scanlines,aperture".parse().unwrap();
+        assert_eq!((768, 720), chain.output_size());
+        let frame = FrameBuffer::new(Default::default());
+        let dst = chain.apply_frame(&frame, &Palette::default());
+        assert_eq!((768, 720), (dst.width, dst.height));
+        let chain: FilterChain = "ntsc-svideo,scanlines".parse().unwrap();
+        assert_eq!((602, 480), chain.output_size());
+    }
+
+    #[test]
+    fn _ntsc_decodes_with_params() {
+        let mut frame = FrameBuffer::new(Default::default());
+        let palette = Palette::default();
+        for i in 0..256 * 240 {
+            frame.set(i, 0x16, &palette);
+        }
+        let mut chain: FilterChain = "ntsc-rgb".parse().unwrap();
+        let default = chain.apply_frame(&frame, &palette);
+        let params = NtscParams {
+            hue: 30.0,
+            ..NtscParams::default()
+        };
+        chain.set_ntsc_params(params);
+        let shifted = chain.apply_frame(&frame, &palette);
+        assert_ne!(default.pixels, shifted.pixels);
+        let generated = Palette::from_ntsc(&params).rgb[0x16];
+        assert_eq!(pack(generated), shifted.pixels[100 * ntsc::OUT_WIDTH + 300]);
+    }
+
+    #[test]
+    fn _ntsc_comes_first() {
+        assert!("ntsc".parse::<FilterChain>().unwrap().ntsc.is_some());
+        assert!("scale2x,ntsc".parse::<FilterChain>().is_err());
+        assert!("ntsc-vhs".parse::<FilterChain>().is_err());
+    }
+}
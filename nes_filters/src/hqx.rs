@@ -0,0 +1,345 @@
+//! hq2x and hq3x by Maxim Stepin. Each of the 8 neighbours is compared with the centre pixel
+//! in YUV, which gives an 8 bit pattern, and the pattern picks how every output corner blends
+//! with its neighbours. The original spells the 256 cases out for each output pixel; the cases
+//! are symmetric, so here one table covers the top left corner and the other corners look it
+//! up on a mirrored neighbourhood.
+
+use crate::image::{mix, unpack, Image};
+
+#[inline(always)]
+fn yuv(pixel: u32) -> [i32; 3] {
+    let [r, g, b] = unpack(pixel).map(|c| c as i32);
+    [
+        (299 * r + 587 * g + 114 * b) / 1000,
+        (-169 * r - 331 * g + 500 * b) / 1000 + 128,
+        (500 * r - 419 * g - 81 * b) / 1000 + 128,
+    ]
+}
+
+/// Whether two colours are visibly different, by the hqx thresholds.
+#[inline(always)]
+pub fn differ(a: u32, b: u32) -> bool {
+    if a == b {
+        return false;
+    }
+    let [ya, ua, va] = yuv(a);
+    let [yb, ub, vb] = yuv(b);
+    (ya - yb).abs() > 48 || (ua - ub).abs() > 7 || (va - vb).abs() > 6
+}
+
+/// Neighbourhood of (`x`, `y`): `[w1, .., w9]`, row by row, `w5` being the pixel itself.
+#[inline(always)]
+pub fn kernel(src: &Image, x: isize, y: isize) -> [u32; 9] {
+    let mut k = [0; 9];
+    for (i, k) in k.iter_mut().enumerate() {
+        *k = src.get(x + i as isize % 3 - 1, y + i as isize / 3 - 1);
+    }
+    k
+}
+
+/// How the top left output corner blends, given which of its edges `w2` (top) and `w4` (left)
+/// and its corner `w1` differ from `w5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    /// Neither edge differs.
+    Flat,
+    /// Only the top edge differs, `w1` does not.
+    Top,
+    /// The top edge and `w1` differ.
+    TopCorner,
+    /// As `TopCorner`, unless `w2` and `w6` match: then the top right corner is the end of a
+    /// shallow slope that reaches into this one.
+    TopSlope,
+    Left,
+    LeftCorner,
+    LeftSlope,
+    /// Both edges differ: a diagonal edge runs across the corner unless `w2` and `w4` differ
+    /// from each other too.
+    Diagonal,
+    DiagonalCorner,
+    /// A diagonal edge that goes on as a shallow slope into the top right corner.
+    ShallowTop,
+    ShallowTopCorner,
+    /// A diagonal edge that goes on as a steep slope into the bottom left corner.
+    ShallowLeft,
+    ShallowLeftCorner,
+    /// Both edges differ but a neighbouring corner is a real corner, so `w1` is a thin line.
+    Notch,
+}
+
+use Rule::*;
+
+const RULES: [Rule; 14] = [
+    Flat,
+    Top,
+    TopCorner,
+    TopSlope,
+    Left,
+    LeftCorner,
+    LeftSlope,
+    Diagonal,
+    DiagonalCorner,
+    ShallowTop,
+    ShallowTopCorner,
+    ShallowLeft,
+    ShallowLeftCorner,
+    Notch,
+];
+
+/// Index into `RULES` for every pattern. Bits 0 to 7 are set when `w1`, `w2`, `w3`, `w4`,
+/// `w6`, `w7`, `w8` and `w9` differ from `w5`.
+#[rustfmt::skip]
+const PATTERNS: [u8; 256] = [
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5,  7,  8,  4,  5,  9, 10,
+    0,  0,  1,  3,  0,  0,  1,  3,  4,  5,  7,  8,  4,  5, 13,  8,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5, 11, 12,  4,  5,  7,  8,
+    0,  0,  1,  3,  0,  0,  1,  3,  4,  5, 11, 12,  4,  5, 13, 12,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  6,  7,  8,  4,  6,  9, 10,
+    0,  0,  1,  3,  0,  0,  1,  3,  4,  6,  7,  8,  4,  6, 13,  8,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  6, 13,  8,  4,  6, 13, 10,
+    0,  0,  1,  3,  0,  0,  1,  3,  4,  6, 13,  8,  4,  6, 13,  8,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5,  7,  8,  4,  5,  9, 10,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5,  7,  8,  4,  5, 13,  8,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5, 11, 12,  4,  5,  7,  8,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5, 11, 12,  4,  5, 13, 12,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5,  7,  8,  4,  5,  9, 10,
+    0,  0,  1,  2,  0,  0,  1,  3,  4,  5,  7,  8,  4,  5, 13,  8,
+    0,  0,  1,  2,  0,  0,  1,  2,  4,  5, 13,  8,  4,  5, 13, 10,
+    0,  0,  1,  2,  0,  0,  1,  3,  4,  6, 13,  8,  4,  6, 13,  8,
+];
+
+/// `k` mirrored so that output corner `corner` (0 top left, 1 top right, 2 bottom left,
+/// 3 bottom right) becomes the top left one.
+#[inline(always)]
+fn orient<T: Copy>(mut k: [T; 9], corner: usize) -> [T; 9] {
+    if corner & 1 != 0 {
+        k.swap(0, 2);
+        k.swap(3, 5);
+        k.swap(6, 8);
+    }
+    if corner & 2 != 0 {
+        k.swap(0, 6);
+        k.swap(1, 7);
+        k.swap(2, 8);
+    }
+    k
+}
+
+/// Rule of the top left corner of `w` and the result of the colour test it depends on.
+#[inline(always)]
+fn rule(w: &[u32; 9], diff: &[bool; 9]) -> (Rule, bool) {
+    let pattern = [0, 1, 2, 3, 5, 6, 7, 8]
+        .iter()
+        .enumerate()
+        .fold(0, |p, (bit, &n)| p | (diff[n] as usize) << bit);
+    let rule = RULES[PATTERNS[pattern] as usize];
+    let [_, w2, _, w4, _, w6, _, w8, _] = *w;
+    let test = match rule {
+        TopSlope => differ(w2, w6),
+        LeftSlope => differ(w8, w4),
+        Diagonal | DiagonalCorner | ShallowTop | ShallowTopCorner | ShallowLeft
+        | ShallowLeftCorner => differ(w4, w2),
+        _ => true,
+    };
+    (rule, test)
+}
+
+/// Top left pixel of the 2x2 output.
+#[inline(always)]
+fn corner2x(w: &[u32; 9], diff: &[bool; 9]) -> u32 {
+    let [w1, w2, _, w4, w5, ..] = *w;
+    match rule(w, diff) {
+        (Flat, _) | (DiagonalCorner, false) => mix(&[(w5, 2), (w4, 1), (w2, 1)]),
+        (Top, _) => mix(&[(w5, 2), (w1, 1), (w4, 1)]),
+        (Left, _) => mix(&[(w5, 2), (w1, 1), (w2, 1)]),
+        (TopCorner, _) | (TopSlope, true) => mix(&[(w5, 3), (w4, 1)]),
+        (TopSlope, false) => mix(&[(w5, 5), (w2, 2), (w4, 1)]),
+        (LeftCorner, _) | (LeftSlope, true) => mix(&[(w5, 3), (w2, 1)]),
+        (LeftSlope, false) => mix(&[(w5, 5), (w4, 2), (w2, 1)]),
+        (Diagonal | ShallowTop | ShallowLeft, true) | (Notch, _) => mix(&[(w5, 3), (w1, 1)]),
+        (DiagonalCorner | ShallowTopCorner | ShallowLeftCorner, true) => w5,
+        (Diagonal, false) => mix(&[(w5, 6), (w4, 1), (w2, 1)]),
+        (ShallowTop | ShallowTopCorner | ShallowLeft | ShallowLeftCorner, false) => {
+            mix(&[(w5, 2), (w4, 3), (w2, 3)])
+        }
+    }
+}
+
+/// How far a corner of the 3x3 output pulls the edge pixel next to it towards the neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Pull {
+    None,
+    Eighth,
+    Quarter,
+    ThreeQuarters,
+}
+
+/// Top left pixel of the 3x3 output, with how it pulls the top and left edge pixels.
+#[inline(always)]
+fn corner3x(w: &[u32; 9], diff: &[bool; 9]) -> (u32, Pull, Pull) {
+    let [w1, w2, _, w4, w5, ..] = *w;
+    let blend = mix(&[(w5, 2), (w4, 1), (w2, 1)]);
+    match rule(w, diff) {
+        (Flat, _) | (Diagonal, false) => (blend, Pull::None, Pull::None),
+        (Top | Left | Notch, _) | (Diagonal | ShallowTop | ShallowLeft, true) => {
+            (mix(&[(w5, 3), (w1, 1)]), Pull::None, Pull::None)
+        }
+        (TopCorner, _) | (TopSlope, true) => (mix(&[(w5, 3), (w4, 1)]), Pull::None, Pull::None),
+        (TopSlope, false) => (blend, Pull::ThreeQuarters, Pull::None),
+        (LeftCorner, _) | (LeftSlope, true) => (mix(&[(w5, 3), (w2, 1)]), Pull::None, Pull::None),
+        (LeftSlope, false) => (blend, Pull::None, Pull::ThreeQuarters),
+        (DiagonalCorner | ShallowTopCorner | ShallowLeftCorner, true) => {
+            (w5, Pull::None, Pull::None)
+        }
+        (DiagonalCorner, false) => (
+            mix(&[(w5, 2), (w4, 7), (w2, 7)]),
+            Pull::Eighth,
+            Pull::Eighth,
+        ),
+        (ShallowTop | ShallowTopCorner, false) => {
+            (mix(&[(w4, 1), (w2, 1)]), Pull::ThreeQuarters, Pull::Quarter)
+        }
+        (ShallowLeft | ShallowLeftCorner, false) => {
+            (mix(&[(w4, 1), (w2, 1)]), Pull::Quarter, Pull::ThreeQuarters)
+        }
+    }
+}
+
+/// Edge pixel of the 3x3 output next to `side`, pulled by the corners on both ends of it.
+#[inline(always)]
+fn edge3x(w5: u32, side: u32, differs: bool, pull: Pull) -> u32 {
+    if !differs {
+        return mix(&[(w5, 3), (side, 1)]);
+    }
+    match pull {
+        Pull::None => w5,
+        Pull::Eighth => mix(&[(w5, 7), (side, 1)]),
+        Pull::Quarter => mix(&[(w5, 3), (side, 1)]),
+        Pull::ThreeQuarters => mix(&[(w5, 1), (side, 3)]),
+    }
+}
+
+pub fn hq2x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 2, src.height * 2);
+    for y in 0..src.height as isize {
+        for x in 0..src.width as isize {
+            let w = kernel(src, x, y);
+            let diff = w.map(|p| differ(w[4], p));
+            let out: [u32; 4] = std::array::from_fn(|c| corner2x(&orient(w, c), &orient(diff, c)));
+            let i0 = y as usize * 2 * dst.width + x as usize * 2;
+            let i1 = i0 + dst.width;
+            dst.pixels[i0..i0 + 2].copy_from_slice(&out[0..2]);
+            dst.pixels[i1..i1 + 2].copy_from_slice(&out[2..4]);
+        }
+    }
+    dst
+}
+
+pub fn hq3x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 3, src.height * 3);
+    for y in 0..src.height as isize {
+        for x in 0..src.width as isize {
+            let w = kernel(src, x, y);
+            let diff = w.map(|p| differ(w[4], p));
+            // a corner's own top edge is the top or bottom one, its left edge the left or right
+            let [(c0, t0, l0), (c1, t1, l1), (c2, t2, l2), (c3, t3, l3)]: [_; 4] =
+                std::array::from_fn(|c| corner3x(&orient(w, c), &orient(diff, c)));
+            let edge = |n: usize, pull: Pull| edge3x(w[4], w[n], diff[n], pull);
+            let out = [
+                c0,
+                edge(1, t0.max(t1)),
+                c1,
+                edge(3, l0.max(l2)),
+                w[4],
+                edge(5, l1.max(l3)),
+                c2,
+                edge(7, t2.max(t3)),
+                c3,
+            ];
+            for row in 0..3 {
+                let i = (y as usize * 3 + row) * dst.width + x as usize * 3;
+                dst.pixels[i..i + 3].copy_from_slice(&out[row * 3..row * 3 + 3]);
+            }
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _similar_colours_do_not_differ() {
+        assert!(!differ(0x808080, 0x848484));
+        assert!(differ(0x000000, 0xFFFFFF));
+        assert!(differ(0xFF0000, 0x0000FF));
+    }
+
+    #[test]
+    fn _patterns_are_symmetric() {
+        // swapping rows and columns swaps w2/w4, w3/w7 and w6/w8, and top with left
+        let transpose = |p: usize| {
+            [0, 3, 5, 1, 6, 2, 4, 7]
+                .iter()
+                .enumerate()
+                .fold(0, |t, (bit, &from)| t | (p >> from & 1) << bit)
+        };
+        for p in 0..256 {
+            let expected = match RULES[PATTERNS[p] as usize] {
+                Top => Left,
+                TopCorner => LeftCorner,
+                TopSlope => LeftSlope,
+                Left => Top,
+                LeftCorner => TopCorner,
+                LeftSlope => TopSlope,
+                ShallowTop => ShallowLeft,
+                ShallowTopCorner => ShallowLeftCorner,
+                ShallowLeft => ShallowTop,
+                ShallowLeftCorner => ShallowTopCorner,
+                rule => rule,
+            };
+            assert_eq!(expected, RULES[PATTERNS[transpose(p)] as usize], "{}", p);
+        }
+    }
+
+    #[test]
+    fn _diagonal_edge_is_smoothed() {
+        // white above the anti-diagonal, black below
+        let mut src = Image::new(3, 3);
+        src.pixels = vec![
+            0xFFFFFF, 0xFFFFFF, 0xFFFFFF, //
+            0xFFFFFF, 0x000000, 0x000000, //
+            0xFFFFFF, 0x000000, 0x000000,
+        ];
+        let dst = hq2x(&src);
+        // top left corner of the centre pixel blends towards white
+        let [r, _, _] = unpack(dst.pixels[2 * 6 + 2]);
+        assert!(r > 0x40 && r < 0xFF);
+        // its bottom right corner stays black
+        assert_eq!(0, dst.pixels[3 * 6 + 3]);
+
+        let dst = hq3x(&src);
+        let [r, _, _] = unpack(dst.pixels[3 * 9 + 3]);
+        assert!(r > 0x40 && r < 0xFF);
+        // the centre and the far corner stay black
+        assert_eq!(0, dst.pixels[4 * 9 + 4]);
+        assert_eq!(0, dst.pixels[5 * 9 + 5]);
+    }
+
+    #[test]
+    fn _shallow_slope_reaches_the_next_corner() {
+        // a white line whose lower edge drops by one pixel every two columns
+        let mut src = Image::new(4, 3);
+        src.pixels = vec![
+            0xFFFFFF, 0xFFFFFF, 0xFFFFFF, 0xFFFFFF, //
+            0xFFFFFF, 0x000000, 0x000000, 0x000000, //
+            0x000000, 0x000000, 0x000000, 0x000000,
+        ];
+        let dst = hq2x(&src);
+        // (1, 1) has w1 to w4 white: its top right corner takes part in the slope
+        let top_right = dst.pixels[2 * 8 + 3];
+        assert_ne!(0, top_right);
+        assert!(unpack(top_right)[0] < unpack(dst.pixels[2 * 8 + 2])[0]);
+    }
+}
@@ -0,0 +1,126 @@
+use nes_core::entity::{frame_buffer::FrameBuffer, overscan::Overscan, palette::Palette};
+
+/// RGB picture with one `0x00RRGGBB` word per pixel, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Converts a PPU frame; `palette` resolves indexed frames.
+    pub fn from_frame(frame: &FrameBuffer, palette: &Palette) -> Self {
+        let mut image = Self::new(FrameBuffer::WIDTH, FrameBuffer::HEIGHT);
+        for y in 0..FrameBuffer::HEIGHT {
+            for x in 0..FrameBuffer::WIDTH {
+                image.pixels[y * FrameBuffer::WIDTH + x] = pack(frame.rgb(x, y, palette));
+            }
+        }
+        image
+    }
+
+    /// Pixel at (`x`, `y`), with coordinates outside the picture clamped to its edge.
+    #[inline(always)]
+    pub fn get(&self, x: isize, y: isize) -> u32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    /// The part left visible by `overscan`, which is scaled to the size of this picture.
+    pub fn crop(&self, overscan: &Overscan) -> Self {
+        let (x, y, width, height) = overscan.rect(self.width, self.height);
+        let mut image = Self::new(width, height);
+        for (row, dst) in image.pixels.chunks_exact_mut(width).enumerate() {
+            let start = (y + row) * self.width + x;
+            dst.copy_from_slice(&self.pixels[start..start + width]);
+        }
+        image
+    }
+
+    pub fn to_rgb24(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|&p| unpack(p)).collect()
+    }
+
+    pub fn to_rgba32(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&p| {
+                let [r, g, b] = unpack(p);
+                [r, g, b, 0xFF]
+            })
+            .collect()
+    }
+}
+
+#[inline(always)]
+pub fn pack([r, g, b]: [u8; 3]) -> u32 {
+    (r as u32) << 16 | (g as u32) << 8 | b as u32
+}
+
+#[inline(always)]
+pub fn unpack(pixel: u32) -> [u8; 3] {
+    [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+}
+
+/// Weighted average of `colours`, each given with its weight.
+#[inline(always)]
+pub fn mix(colours: &[(u32, u32)]) -> u32 {
+    let total: u32 = colours.iter().map(|&(_, w)| w).sum();
+    let mut rgb = [0; 3];
+    for &(colour, weight) in colours {
+        for (sum, c) in rgb.iter_mut().zip(unpack(colour)) {
+            *sum += c as u32 * weight;
+        }
+    }
+    pack(rgb.map(|sum| ((sum + total / 2) / total) as u8))
+}
+
+/// Scales each channel by `num / den`.
+#[inline(always)]
+pub fn dim(pixel: u32, num: [u32; 3], den: u32) -> u32 {
+    let [r, g, b] = unpack(pixel);
+    pack([
+        (r as u32 * num[0] / den) as u8,
+        (g as u32 * num[1] / den) as u8,
+        (b as u32 * num[2] / den) as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _mix() {
+        assert_eq!(0x808080, mix(&[(0xFFFFFF, 1), (0x000000, 1)]));
+        assert_eq!(0x400000, mix(&[(0xFF0000, 1), (0x000000, 3)]));
+    }
+
+    #[test]
+    fn _edges_are_clamped() {
+        let mut image = Image::new(2, 2);
+        image.pixels = vec![1, 2, 3, 4];
+        assert_eq!(1, image.get(-1, -1));
+        assert_eq!(4, image.get(5, 5));
+    }
+
+    #[test]
+    fn _crop() {
+        let mut image = Image::new(512, 480);
+        image.pixels[16 * 512 + 16] = 1;
+        let cropped = image.crop(&"8,8,8,0".parse().unwrap());
+        assert_eq!((496, 448), (cropped.width, cropped.height));
+        assert_eq!(1, cropped.pixels[0]);
+        assert_eq!(image, image.crop(&Overscan::NONE));
+    }
+}
@@ -0,0 +1,8 @@
+pub mod crt;
+pub mod filter;
+pub mod hqx;
+pub mod image;
+pub mod ntsc;
+pub mod scale;
+pub mod smooth;
+pub mod xbrz;
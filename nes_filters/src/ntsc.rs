@@ -0,0 +1,194 @@
+//! NTSC composite video in the spirit of Blargg's nes_ntsc. Each line is rebuilt as the
+//! signal the PPU outputs (8 samples per pixel, 12 per colour cycle) from the palette
+//! indices, then decoded the way a TV would, into 602 pixels: 7 for every 3 input pixels.
+
+use std::str::FromStr;
+
+use nes_core::entity::{frame_buffer::FrameBuffer, palette::NtscParams};
+
+use crate::image::{pack, Image};
+
+pub const OUT_WIDTH: usize = 602;
+
+const SAMPLES_PER_PIXEL: usize = 8;
+const SAMPLES: usize = FrameBuffer::WIDTH * SAMPLES_PER_PIXEL;
+
+/// How much of the composite artefacts a TV shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtscSettings {
+    /// Unsharp mask on luma, 0 for none.
+    pub sharpness: f64,
+    /// 0..1: chroma leaking into luma (dot crawl, the checkerboard look of dithering).
+    pub artifacts: f64,
+    /// 0..1: luma edges leaking into chroma (colour fringes).
+    pub fringing: f64,
+    /// 0..1: extra colour bleed, widening the chroma filter up to twice.
+    pub bleed: f64,
+    /// Hue, saturation and the other knobs of the TV, as for `Palette::from_ntsc`.
+    pub params: NtscParams,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NtscPreset {
+    #[default]
+    Composite,
+    /// Separate luma and chroma: no dot crawl or fringes, chroma is still blurry.
+    SVideo,
+    /// Sharp picture with only the bandwidth limit of the signal.
+    Rgb,
+}
+
+impl FromStr for NtscPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "composite" => Ok(NtscPreset::Composite),
+            "svideo" => Ok(NtscPreset::SVideo),
+            "rgb" => Ok(NtscPreset::Rgb),
+            _ => Err(format!("unknown NTSC preset {}", s)),
+        }
+    }
+}
+
+impl NtscPreset {
+    pub fn settings(self) -> NtscSettings {
+        match self {
+            NtscPreset::Composite => NtscSettings {
+                sharpness: 0.0,
+                artifacts: 1.0,
+                fringing: 1.0,
+                bleed: 0.5,
+                params: NtscParams::default(),
+            },
+            NtscPreset::SVideo => NtscSettings {
+                sharpness: 0.2,
+                artifacts: 0.0,
+                fringing: 0.0,
+                bleed: 0.5,
+                params: NtscParams::default(),
+            },
+            NtscPreset::Rgb => NtscSettings {
+                sharpness: 0.2,
+                artifacts: 0.0,
+                fringing: 0.0,
+                bleed: 0.0,
+                params: NtscParams::default(),
+            },
+        }
+    }
+}
+
+/// Running sums of a line of samples for O(1) box filters.
+struct Sums(Vec<f64>);
+
+impl Sums {
+    fn new(values: impl Iterator<Item = f64>) -> Self {
+        let mut sums = vec![0.0];
+        let mut total = 0.0;
+        for v in values {
+            total += v;
+            sums.push(total);
+        }
+        Self(sums)
+    }
+
+    /// Average over `width` samples centred on `centre`, clipped to the line.
+    fn average(&self, centre: f64, width: usize) -> f64 {
+        let len = self.0.len() - 1;
+        let start = (centre - width as f64 / 2.0).round().max(0.0) as usize;
+        let end = (start + width).min(len);
+        let start = start.min(end - 1);
+        (self.0[end] - self.0[start]) / (end - start) as f64
+    }
+}
+
+pub fn ntsc(frame: &FrameBuffer, settings: &NtscSettings) -> Image {
+    let params = &settings.params;
+    let mut dst = Image::new(OUT_WIDTH, FrameBuffer::HEIGHT);
+    let chroma_width = 24 + 12 * (settings.bleed * 2.0).round() as usize;
+    let cos: [f64; 12] = std::array::from_fn(|p| params.angle(p).cos());
+    let sin: [f64; 12] = std::array::from_fn(|p| params.angle(p).sin());
+    for y in 0..FrameBuffer::HEIGHT {
+        let indices = &frame.indices()[y * FrameBuffer::WIDTH..(y + 1) * FrameBuffer::WIDTH];
+        let phase = frame.line_phase(y) as usize;
+        let signal: Vec<f64> = (0..SAMPLES)
+            .map(|k| NtscParams::signal(indices[k / SAMPLES_PER_PIXEL] as usize, (phase + k) % 12))
+            .collect();
+        let v = Sums::new(signal.iter().copied());
+        // one full cycle cancels the subcarrier: the luma a comb filter would separate
+        let luma: Vec<f64> = (0..SAMPLES).map(|k| v.average(k as f64, 12)).collect();
+        let chroma = |carrier: &[f64; 12]| {
+            Sums::new((0..SAMPLES).map(|k| {
+                (signal[k] - (1.0 - settings.fringing) * luma[k]) * carrier[(phase + k) % 12]
+            }))
+        };
+        let (i_sums, q_sums) = (chroma(&cos), chroma(&sin));
+
+        for x in 0..OUT_WIDTH {
+            let centre = (x as f64 + 0.5) * SAMPLES as f64 / OUT_WIDTH as f64;
+            let separated = v.average(centre, 12);
+            let raw = v.average(centre, 4);
+            let wide = v.average(centre, 24);
+            let luma = separated
+                + settings.artifacts * (raw - separated)
+                + settings.sharpness * (separated - wide);
+            let i = i_sums.average(centre, chroma_width);
+            let q = q_sums.average(centre, chroma_width);
+            dst.pixels[y * OUT_WIDTH + x] = pack(params.to_rgb(luma, i, q));
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use nes_core::entity::{frame_buffer::PixelFormat, palette::Palette};
+
+    use super::*;
+
+    fn frame(pixel: impl Fn(usize, usize) -> u16) -> FrameBuffer {
+        let palette = Palette::default();
+        let mut frame = FrameBuffer::new(PixelFormat::Rgb24);
+        for y in 0..240 {
+            for x in 0..256 {
+                frame.set(y * 256 + x, pixel(x, y), &palette);
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn _flat_colour_matches_generated_palette() {
+        let params = NtscParams::default();
+        let palette = Palette::from_ntsc(&params);
+        for index in [0x16, 0x2A, 0x30, 1 << 6 | 0x21] {
+            let dst = ntsc(&frame(|_, _| index), &NtscPreset::Rgb.settings());
+            assert_eq!(OUT_WIDTH, dst.width);
+            assert_eq!(
+                pack(palette.rgb[index as usize]),
+                dst.pixels[100 * OUT_WIDTH + 300]
+            );
+        }
+    }
+
+    #[test]
+    fn _composite_shows_dot_crawl_on_alternate_frames() {
+        // a grey and white checkerboard
+        let mut checker = frame(|x, y| if (x + y) % 2 == 0 { 0x00 } else { 0x20 });
+        let settings = NtscPreset::Composite.settings();
+        let even = ntsc(&checker, &settings);
+        checker.phase = 8;
+        let odd = ntsc(&checker, &settings);
+        assert_ne!(even.pixels, odd.pixels);
+        // without artefacts the phase makes no difference
+        let rgb = NtscPreset::Rgb.settings();
+        let flat = frame(|_, _| 0x21);
+        let mut flat_odd = flat.clone();
+        flat_odd.phase = 8;
+        assert_eq!(
+            ntsc(&flat, &rgb).pixels[50 * OUT_WIDTH + 100..50 * OUT_WIDTH + 500],
+            ntsc(&flat_odd, &rgb).pixels[50 * OUT_WIDTH + 100..50 * OUT_WIDTH + 500]
+        );
+    }
+}
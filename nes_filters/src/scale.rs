@@ -0,0 +1,129 @@
+use crate::image::Image;
+
+/// Repeats every pixel `scale` times in both directions.
+pub fn nearest(src: &Image, scale: usize) -> Image {
+    let mut dst = Image::new(src.width * scale, src.height * scale);
+    for y in 0..dst.height {
+        let row = &src.pixels[y / scale * src.width..][..src.width];
+        for (x, pixel) in dst.pixels[y * dst.width..][..dst.width]
+            .iter_mut()
+            .enumerate()
+        {
+            *pixel = row[x / scale];
+        }
+    }
+    dst
+}
+
+/// AdvMAME2x: copies a neighbour into a corner when two edges meet there.
+pub fn scale2x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 2, src.height * 2);
+    for y in 0..src.height as isize {
+        for x in 0..src.width as isize {
+            let b = src.get(x, y - 1);
+            let d = src.get(x - 1, y);
+            let e = src.get(x, y);
+            let f = src.get(x + 1, y);
+            let h = src.get(x, y + 1);
+            let out = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if b == f { f } else { e },
+                    if d == h { d } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 4]
+            };
+            let i = y as usize * 2 * dst.width + x as usize * 2;
+            dst.pixels[i..i + 2].copy_from_slice(&out[..2]);
+            dst.pixels[i + dst.width..i + dst.width + 2].copy_from_slice(&out[2..]);
+        }
+    }
+    dst
+}
+
+/// AdvMAME3x, the 3x3 variant of `scale2x` that also fills the edge centres.
+pub fn scale3x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 3, src.height * 3);
+    for y in 0..src.height as isize {
+        for x in 0..src.width as isize {
+            let [a, b, c] = [-1, 0, 1].map(|dx| src.get(x + dx, y - 1));
+            let [d, e, f] = [-1, 0, 1].map(|dx| src.get(x + dx, y));
+            let [g, h, i] = [-1, 0, 1].map(|dx| src.get(x + dx, y + 1));
+            let out = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if (d == b && e != c) || (b == f && e != a) {
+                        b
+                    } else {
+                        e
+                    },
+                    if b == f { f } else { e },
+                    if (d == b && e != g) || (d == h && e != a) {
+                        d
+                    } else {
+                        e
+                    },
+                    e,
+                    if (b == f && e != i) || (h == f && e != c) {
+                        f
+                    } else {
+                        e
+                    },
+                    if d == h { d } else { e },
+                    if (d == h && e != i) || (h == f && e != g) {
+                        h
+                    } else {
+                        e
+                    },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 9]
+            };
+            for row in 0..3 {
+                let i = (y as usize * 3 + row) * dst.width + x as usize * 3;
+                dst.pixels[i..i + 3].copy_from_slice(&out[row * 3..row * 3 + 3]);
+            }
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: usize, pixels: &[u32]) -> Image {
+        Image {
+            width,
+            height: pixels.len() / width,
+            pixels: pixels.to_vec(),
+        }
+    }
+
+    #[test]
+    fn _nearest() {
+        let dst = nearest(&image(2, &[1, 2]), 2);
+        assert_eq!(vec![1, 1, 2, 2, 1, 1, 2, 2], dst.pixels);
+    }
+
+    #[test]
+    fn _scale2x_fills_diagonal_steps() {
+        let src = image(3, &[1, 0, 0, 0, 1, 0, 0, 0, 1]);
+        let dst = scale2x(&src);
+        // above the diagonal, the lower left corner joins the two 1s
+        assert_eq!(1, dst.pixels[6 + 2]);
+        assert_eq!(0, dst.pixels[3]);
+        assert_eq!(0, dst.pixels[2]);
+        // the diagonal pixels themselves stay whole
+        assert_eq!([1, 1], dst.pixels[6 * 2 + 2..6 * 2 + 4]);
+    }
+
+    #[test]
+    fn _scale3x_keeps_flat_areas() {
+        let dst = scale3x(&image(2, &[7; 4]));
+        assert_eq!(vec![7; 36], dst.pixels);
+    }
+}
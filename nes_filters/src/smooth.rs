@@ -0,0 +1,108 @@
+//! Edge-aware 2x/3x smoothing. Neighbours are compared in YUV with the thresholds of hqx,
+//! but each corner is classified with a few rules instead of the hqx patterns: an edge
+//! crossing the corner, a lone differing corner pixel, or a straight edge next to it.
+
+use crate::{
+    hqx::{differ, kernel},
+    image::{mix, Image},
+};
+
+/// Colour of the output corner of `e` that touches `corner`, between `side1` and `side2`.
+#[inline(always)]
+fn corner(e: u32, side1: u32, side2: u32, corner: u32) -> u32 {
+    let edge1 = differ(e, side1);
+    let edge2 = differ(e, side2);
+    if edge1 && edge2 && !differ(side1, side2) {
+        // an edge runs diagonally across this corner
+        if differ(corner, side1) {
+            mix(&[(e, 2), (side1, 1), (side2, 1)])
+        } else {
+            mix(&[(e, 2), (side1, 3), (side2, 3)])
+        }
+    } else if edge1 && edge2 {
+        mix(&[(e, 2), (side1, 1), (side2, 1)])
+    } else if edge1 {
+        mix(&[(e, 3), (side1, 1)])
+    } else if edge2 {
+        mix(&[(e, 3), (side2, 1)])
+    } else if differ(e, corner) {
+        mix(&[(e, 3), (corner, 1)])
+    } else {
+        e
+    }
+}
+
+/// Colour of an output edge centre of `e` (3x only), next to `side`.
+#[inline(always)]
+fn side(e: u32, side: u32, corner1: u32, corner2: u32) -> u32 {
+    if differ(e, side) && (!differ(side, corner1) || !differ(side, corner2)) {
+        mix(&[(e, 7), (side, 1)])
+    } else {
+        e
+    }
+}
+
+pub fn smooth2x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 2, src.height * 2);
+    for y in 0..src.height as isize {
+        for x in 0..src.width as isize {
+            let [a, b, c, d, e, f, g, h, i] = kernel(src, x, y);
+            let i0 = y as usize * 2 * dst.width + x as usize * 2;
+            let i1 = i0 + dst.width;
+            dst.pixels[i0] = corner(e, b, d, a);
+            dst.pixels[i0 + 1] = corner(e, b, f, c);
+            dst.pixels[i1] = corner(e, h, d, g);
+            dst.pixels[i1 + 1] = corner(e, h, f, i);
+        }
+    }
+    dst
+}
+
+pub fn smooth3x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 3, src.height * 3);
+    for y in 0..src.height as isize {
+        for x in 0..src.width as isize {
+            let [a, b, c, d, e, f, g, h, i] = kernel(src, x, y);
+            let out = [
+                corner(e, b, d, a),
+                side(e, b, a, c),
+                corner(e, b, f, c),
+                side(e, d, a, g),
+                e,
+                side(e, f, c, i),
+                corner(e, h, d, g),
+                side(e, h, g, i),
+                corner(e, h, f, i),
+            ];
+            for row in 0..3 {
+                let i = (y as usize * 3 + row) * dst.width + x as usize * 3;
+                dst.pixels[i..i + 3].copy_from_slice(&out[row * 3..row * 3 + 3]);
+            }
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::unpack;
+
+    use super::*;
+
+    #[test]
+    fn _diagonal_edge_is_smoothed() {
+        // white above the anti-diagonal, black below
+        let mut src = Image::new(3, 3);
+        src.pixels = vec![
+            0xFFFFFF, 0xFFFFFF, 0xFFFFFF, //
+            0xFFFFFF, 0x000000, 0x000000, //
+            0xFFFFFF, 0x000000, 0x000000,
+        ];
+        let dst = smooth2x(&src);
+        // top left corner of the centre pixel blends towards white
+        let [r, _, _] = unpack(dst.pixels[2 * 6 + 2]);
+        assert!(r > 0x40 && r < 0xFF);
+        // its bottom right corner stays black
+        assert_eq!(0, dst.pixels[3 * 6 + 3]);
+    }
+}
@@ -0,0 +1,305 @@
+//! xBRZ by Zenju, for scale factors 2 and 3 with the default configuration.
+//! A first pass finds which corners between four pixels lie on an edge, a second one
+//! blends those corners with a line or rounded-corner pattern in the output block.
+
+use crate::image::{mix, unpack, Image};
+
+const LUMINANCE_WEIGHT: f64 = 1.0;
+const EQUAL_COLOR_TOLERANCE: f64 = 30.0;
+const DOMINANT_DIRECTION_THRESHOLD: f64 = 3.6;
+const STEEP_DIRECTION_THRESHOLD: f64 = 2.2;
+
+const BLEND_NONE: u8 = 0;
+const BLEND_NORMAL: u8 = 1;
+const BLEND_DOMINANT: u8 = 2;
+
+/// Blend type of each corner of a pixel, 2 bits each.
+const TOP_L: u8 = 0;
+const TOP_R: u8 = 2;
+const BOTTOM_R: u8 = 4;
+const BOTTOM_L: u8 = 6;
+
+/// Position in the unrotated 3x3 kernel of each position after a 90 degree rotation.
+const ROT_90: [usize; 9] = [6, 3, 0, 7, 4, 1, 8, 5, 2];
+
+/// Distance in YCbCr (BT.2020 coefficients).
+#[inline(always)]
+fn dist(a: u32, b: u32) -> f64 {
+    let [ra, ga, ba] = unpack(a);
+    let [rb, gb, bb] = unpack(b);
+    let r = ra as f64 - rb as f64;
+    let g = ga as f64 - gb as f64;
+    let b = ba as f64 - bb as f64;
+    const K_B: f64 = 0.0593;
+    const K_R: f64 = 0.2627;
+    const K_G: f64 = 1.0 - K_B - K_R;
+    let y = K_R * r + K_G * g + K_B * b;
+    let c_b = 0.5 / (1.0 - K_B) * (b - y);
+    let c_r = 0.5 / (1.0 - K_R) * (r - y);
+    ((LUMINANCE_WEIGHT * y).powi(2) + c_b.powi(2) + c_r.powi(2)).sqrt()
+}
+
+#[inline(always)]
+fn eq(a: u32, b: u32) -> bool {
+    dist(a, b) < EQUAL_COLOR_TOLERANCE
+}
+
+/// Decides the blending of the four corners meeting between F, G, J and K:
+/// ```text
+/// A B C D
+/// E F G H
+/// I J K L
+/// M N O P
+/// ```
+/// Returns the blend types of the bottom right of F, bottom left of G, top right of J
+/// and top left of K.
+fn pre_process_corners(ker: [u32; 16]) -> [u8; 4] {
+    let [_, b, c, _, e, f, g, h, i, j, k, l, _, n, o, _] = ker;
+    let mut result = [BLEND_NONE; 4];
+    if (f == g && j == k) || (f == j && g == k) {
+        return result;
+    }
+    let jg = dist(i, f) + dist(f, c) + dist(n, k) + dist(k, h) + 4.0 * dist(j, g);
+    let fk = dist(e, j) + dist(j, o) + dist(b, g) + dist(g, l) + 4.0 * dist(f, k);
+    if jg < fk {
+        let blend = if DOMINANT_DIRECTION_THRESHOLD * jg < fk {
+            BLEND_DOMINANT
+        } else {
+            BLEND_NORMAL
+        };
+        if f != g && f != j {
+            result[0] = blend;
+        }
+        if k != j && k != g {
+            result[3] = blend;
+        }
+    } else if fk < jg {
+        let blend = if DOMINANT_DIRECTION_THRESHOLD * fk < jg {
+            BLEND_DOMINANT
+        } else {
+            BLEND_NORMAL
+        };
+        if j != f && j != k {
+            result[2] = blend;
+        }
+        if g != f && g != k {
+            result[1] = blend;
+        }
+    }
+    result
+}
+
+/// Output block of one source pixel, addressed in the rotated frame of `blend_pixel`.
+struct Block<'a> {
+    dst: &'a mut Image,
+    x: usize,
+    y: usize,
+    scale: usize,
+    rotation: usize,
+}
+
+impl Block<'_> {
+    /// Blends `colour` into (`i`, `j`) with weight `num / den`.
+    fn alpha(&mut self, i: usize, j: usize, num: u32, den: u32, colour: u32) {
+        let (mut i, mut j) = (i, j);
+        for _ in 0..self.rotation {
+            (i, j) = (self.scale - 1 - j, i);
+        }
+        let p = &mut self.dst.pixels[(self.y + i) * self.dst.width + self.x + j];
+        *p = mix(&[(colour, num), (*p, den - num)]);
+    }
+
+    fn set(&mut self, i: usize, j: usize, colour: u32) {
+        self.alpha(i, j, 1, 1, colour);
+    }
+
+    fn line_shallow(&mut self, colour: u32) {
+        let s = self.scale;
+        self.alpha(s - 1, 0, 1, 4, colour);
+        if s == 2 {
+            self.alpha(1, 1, 3, 4, colour);
+        } else {
+            self.alpha(1, 2, 1, 4, colour);
+            self.alpha(2, 1, 3, 4, colour);
+            self.set(2, 2, colour);
+        }
+    }
+
+    fn line_steep(&mut self, colour: u32) {
+        let s = self.scale;
+        self.alpha(0, s - 1, 1, 4, colour);
+        if s == 2 {
+            self.alpha(1, 1, 3, 4, colour);
+        } else {
+            self.alpha(2, 1, 1, 4, colour);
+            self.alpha(1, 2, 3, 4, colour);
+            self.set(2, 2, colour);
+        }
+    }
+
+    fn line_steep_and_shallow(&mut self, colour: u32) {
+        if self.scale == 2 {
+            self.alpha(1, 0, 1, 4, colour);
+            self.alpha(0, 1, 1, 4, colour);
+            self.alpha(1, 1, 5, 6, colour);
+        } else {
+            self.alpha(2, 0, 1, 4, colour);
+            self.alpha(0, 2, 1, 4, colour);
+            self.alpha(2, 1, 3, 4, colour);
+            self.alpha(1, 2, 3, 4, colour);
+            self.set(2, 2, colour);
+        }
+    }
+
+    fn line_diagonal(&mut self, colour: u32) {
+        if self.scale == 2 {
+            self.alpha(1, 1, 1, 2, colour);
+        } else {
+            self.alpha(1, 2, 1, 8, colour);
+            self.alpha(2, 1, 1, 8, colour);
+            self.alpha(2, 2, 7, 8, colour);
+        }
+    }
+
+    /// Rounded corner; the weights are the area outside a quarter circle.
+    fn corner(&mut self, colour: u32) {
+        if self.scale == 2 {
+            self.alpha(1, 1, 21, 100, colour);
+        } else {
+            self.alpha(2, 2, 45, 100, colour);
+        }
+    }
+}
+
+/// Blends the bottom right corner of E in
+/// ```text
+/// A B C
+/// D E F
+/// G H I
+/// ```
+fn blend_pixel(ker: [u32; 9], blend: u8, out: &mut Block) {
+    let corner = |at: u8| (blend >> at) & 3;
+    if corner(BOTTOM_R) < BLEND_NORMAL {
+        return;
+    }
+    let [_, b, c, d, e, f, g, h, i] = ker;
+    let do_line_blend = if corner(BOTTOM_R) >= BLEND_DOMINANT {
+        true
+    } else if corner(TOP_R) != BLEND_NONE && !eq(e, g) {
+        // no second blend in an adjacent rotation, except for 90 degree corners
+        false
+    } else if corner(BOTTOM_L) != BLEND_NONE && !eq(e, c) {
+        false
+    } else {
+        // L-shapes only get their corner blended
+        !(!eq(e, i) && eq(g, h) && eq(h, i) && eq(i, f) && eq(f, c))
+    };
+    let px = if dist(e, f) <= dist(e, h) { f } else { h };
+    if !do_line_blend {
+        out.corner(px);
+        return;
+    }
+    let fg = dist(f, g);
+    let hc = dist(h, c);
+    let shallow = STEEP_DIRECTION_THRESHOLD * fg <= hc && e != g && d != g;
+    let steep = STEEP_DIRECTION_THRESHOLD * hc <= fg && e != c && b != c;
+    match (shallow, steep) {
+        (true, true) => out.line_steep_and_shallow(px),
+        (true, false) => out.line_shallow(px),
+        (false, true) => out.line_steep(px),
+        (false, false) => out.line_diagonal(px),
+    }
+}
+
+pub fn xbrz(src: &Image, scale: usize) -> Image {
+    assert!(scale == 2 || scale == 3, "xBRZ supports 2x and 3x");
+    let (width, height) = (src.width as isize, src.height as isize);
+    let index = |x: isize, y: isize| {
+        (x >= 0 && y >= 0 && x < width && y < height).then(|| (y * width + x) as usize)
+    };
+
+    let mut blend = vec![0_u8; src.pixels.len()];
+    for y in -1..height {
+        for x in -1..width {
+            let mut ker = [0; 16];
+            for (n, k) in ker.iter_mut().enumerate() {
+                *k = src.get(x + n as isize % 4 - 1, y + n as isize / 4 - 1);
+            }
+            let [f, g, j, k] = pre_process_corners(ker);
+            for (at, (dx, dy), corner) in [
+                (BOTTOM_R, (0, 0), f),
+                (BOTTOM_L, (1, 0), g),
+                (TOP_R, (0, 1), j),
+                (TOP_L, (1, 1), k),
+            ] {
+                if let Some(i) = index(x + dx, y + dy) {
+                    blend[i] |= corner << at;
+                }
+            }
+        }
+    }
+
+    let mut dst = Image::new(src.width * scale, src.height * scale);
+    for y in 0..height {
+        for x in 0..width {
+            let e = src.get(x, y);
+            for row in 0..scale {
+                let i = (y as usize * scale + row) * dst.width + x as usize * scale;
+                dst.pixels[i..i + scale].fill(e);
+            }
+            let mut info = blend[(y * width + x) as usize];
+            if info == 0 {
+                continue;
+            }
+            let mut ker = [0; 9];
+            for (n, k) in ker.iter_mut().enumerate() {
+                *k = src.get(x + n as isize % 3 - 1, y + n as isize / 3 - 1);
+            }
+            let mut out = Block {
+                dst: &mut dst,
+                x: x as usize * scale,
+                y: y as usize * scale,
+                scale,
+                rotation: 0,
+            };
+            for rotation in 0..4 {
+                out.rotation = rotation;
+                blend_pixel(ker, info, &mut out);
+                ker = ROT_90.map(|n| ker[n]);
+                info = info.rotate_left(2);
+            }
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _flat_areas_are_not_blended() {
+        let mut src = Image::new(4, 4);
+        src.pixels.fill(0x336699);
+        for scale in [2, 3] {
+            assert!(xbrz(&src, scale).pixels.iter().all(|&p| p == 0x336699));
+        }
+    }
+
+    #[test]
+    fn _diagonal_staircase_is_smoothed() {
+        // black below the diagonal, white above
+        let mut src = Image::new(6, 6);
+        for y in 0..6 {
+            for x in 0..6 {
+                src.pixels[y * 6 + x] = if x >= y { 0xFFFFFF } else { 0 };
+            }
+        }
+        let dst = xbrz(&src, 2);
+        // some output pixels of the staircase are in between black and white
+        assert!(dst.pixels.iter().any(|&p| p != 0 && p != 0xFFFFFF));
+        // far from the edge nothing changes
+        assert_eq!(0xFFFFFF, dst.pixels[11]);
+        assert_eq!(0, dst.pixels[11 * 12]);
+    }
+}
@@ -0,0 +1,6 @@
+use nes_core::adapter::audio::AudioAdapter;
+
+#[derive(Default)]
+pub struct AudioCtx {}
+
+impl AudioAdapter for AudioCtx {}
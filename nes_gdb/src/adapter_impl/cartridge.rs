@@ -0,0 +1,23 @@
+use std::{fs::File, io::Read};
+
+use nes_core::adapter::cartridge::CartridgeAdapter;
+
+pub enum CartridgeCtx {
+    File(String),
+    Bytes(Vec<u8>),
+}
+
+impl CartridgeAdapter for CartridgeCtx {
+    fn read_file(&self) -> Vec<u8> {
+        match self {
+            CartridgeCtx::File(file_path) => {
+                let mut file = File::open(file_path).expect("Cannot open file.");
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)
+                    .expect("Failed to read file to the last.");
+                buf
+            }
+            CartridgeCtx::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+use nes_core::{adapter::video::VideoAdapter, entity::frame_buffer::FrameBuffer};
+
+/// The stub runs headless, so frames are dropped.
+#[derive(Default)]
+pub struct VideoCtx {}
+
+impl VideoAdapter for VideoCtx {
+    fn draw_frame(&mut self, _frame: &FrameBuffer) {}
+}
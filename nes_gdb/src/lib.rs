@@ -0,0 +1,29 @@
+use std::{io, net::TcpListener};
+
+use adapter_impl::{audio::AudioCtx, cartridge::CartridgeCtx, video::VideoCtx};
+use nes_core::{adapter::nes::NesAdapter, entity::symbol::SymbolTable};
+use server::GdbServer;
+
+pub mod adapter_impl;
+pub mod packet;
+pub mod server;
+pub mod target;
+
+/// Waits for one debugger connection on `listener` and serves it with a headless NES.
+/// `symbols` are used by the `monitor` commands to resolve labels.
+pub fn start_gdb_server(
+    cartridge: CartridgeCtx,
+    symbols: SymbolTable,
+    listener: TcpListener,
+) -> io::Result<()> {
+    let mut nes_state = NesAdapter {
+        cartridge: Box::new(cartridge),
+        video: Box::new(VideoCtx::default()),
+        audio: Box::new(AudioCtx::default()),
+    }
+    .init();
+    nes_state.symbols = symbols;
+
+    let (stream, _) = listener.accept()?;
+    GdbServer::new(nes_state).serve(stream)
+}
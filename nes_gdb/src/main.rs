@@ -0,0 +1,24 @@
+use std::{env, fs, net::TcpListener};
+
+use nes_core::entity::symbol::SymbolTable;
+use nes_gdb::{adapter_impl::cartridge::CartridgeCtx, start_gdb_server};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:6502";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let file_path = args
+        .next()
+        .unwrap_or_else(|| String::from("assets/helloworld.nes"));
+    let addr = args.next().unwrap_or_else(|| String::from(DEFAULT_ADDR));
+
+    let mut symbols = SymbolTable::default();
+    for symbol_path in args {
+        let text = fs::read_to_string(&symbol_path).expect("Could not read the symbol file.");
+        symbols.load(&symbol_path, &text).unwrap();
+    }
+
+    let listener = TcpListener::bind(&addr).expect("Could not bind the gdb server address.");
+    println!("Waiting for gdb on {}", addr);
+    start_gdb_server(CartridgeCtx::File(file_path), symbols, listener).unwrap();
+}
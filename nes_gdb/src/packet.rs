@@ -0,0 +1,122 @@
+use std::io::{self, Read, Write};
+
+/// Out-of-band byte a client sends to interrupt a running target.
+pub const INTERRUPT: u8 = 0x03;
+
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    /// `$<data>#<checksum>`
+    Command(Vec<u8>),
+    /// Ctrl-C sent while the target is running.
+    Interrupt,
+    Ack,
+    Nack,
+}
+
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+pub fn read_packet(stream: &mut impl Read) -> io::Result<Packet> {
+    loop {
+        match read_byte(stream)? {
+            b'$' => break,
+            b'+' => return Ok(Packet::Ack),
+            b'-' => return Ok(Packet::Nack),
+            INTERRUPT => return Ok(Packet::Interrupt),
+            _ => {}
+        }
+    }
+
+    // the checksum covers the escaped bytes as sent
+    let mut raw = Vec::new();
+    loop {
+        match read_byte(stream)? {
+            b'#' => break,
+            b => raw.push(b),
+        }
+    }
+    let mut data = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&b) = bytes.next() {
+        match b {
+            b'}' => data.push(bytes.next().map_or(0, |b| b ^ 0x20)),
+            _ => data.push(b),
+        }
+    }
+
+    let hi = read_byte(stream)?;
+    let lo = read_byte(stream)?;
+    match parse_hex(&[hi, lo]) {
+        Some(sum) if sum as u8 == checksum(&raw) => Ok(Packet::Command(data)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "packet checksum mismatch",
+        )),
+    }
+}
+
+pub fn write_packet(stream: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(data.len() + 4);
+    buf.push(b'$');
+    for &b in data {
+        if matches!(b, b'$' | b'#' | b'}' | b'*') {
+            buf.push(b'}');
+            buf.push(b ^ 0x20);
+        } else {
+            buf.push(b);
+        }
+    }
+    buf.extend(format!("#{:02x}", checksum(&buf[1..])).bytes());
+    stream.write_all(&buf)?;
+    stream.flush()
+}
+
+fn read_byte(stream: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    stream.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub fn parse_hex(hex: &[u8]) -> Option<u32> {
+    if hex.is_empty() || hex.len() > 8 {
+        return None;
+    }
+    u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+pub fn encode_hex(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|b| format!("{:02x}", b).into_bytes())
+        .collect()
+}
+
+pub fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| parse_hex(pair).map(|b| b as u8))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _packet_round_trip() {
+        let mut buf = Vec::new();
+        write_packet(&mut buf, b"m8000,2#}").unwrap();
+        assert_eq!(
+            Packet::Command(b"m8000,2#}".to_vec()),
+            read_packet(&mut buf.as_slice()).unwrap()
+        );
+    }
+
+    #[test]
+    fn _bad_checksum() {
+        assert!(read_packet(&mut b"$g#00".as_slice()).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+use nes_core::usecase::nes::NesState;
+
+use crate::{
+    packet::{decode_hex, encode_hex, parse_hex, read_packet, write_packet, Packet, INTERRUPT},
+    target::{
+        read_memory, read_register, read_registers, step, write_memory, write_register,
+        write_registers, TARGET_XML,
+    },
+};
+
+/// Number of instructions executed between polls for a client interrupt while running.
+const POLL_INTERVAL: u32 = 1024;
+
+const SIGINT: &[u8] = b"S02";
+/// The CPU ran a KIL/JAM opcode.
+const SIGILL: &[u8] = b"S04";
+const SIGTRAP: &[u8] = b"S05";
+
+pub struct GdbServer {
+    pub nes_state: NesState,
+    breakpoints: BTreeSet<u16>,
+    no_ack: bool,
+}
+
+enum Response {
+    Reply(Vec<u8>),
+    Close(Option<Vec<u8>>),
+}
+
+impl GdbServer {
+    pub fn new(nes_state: NesState) -> Self {
+        Self {
+            nes_state,
+            breakpoints: BTreeSet::new(),
+            no_ack: false,
+        }
+    }
+
+    /// Serves one debugger session until the client kills, detaches or disconnects.
+    pub fn serve(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        let mut last_reply = Vec::new();
+        loop {
+            let packet = match read_packet(&mut stream) {
+                Ok(packet) => packet,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    stream.write_all(b"-")?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let data = match packet {
+                Packet::Command(data) => data,
+                Packet::Interrupt => {
+                    last_reply = SIGINT.to_vec();
+                    write_packet(&mut stream, &last_reply)?;
+                    continue;
+                }
+                Packet::Nack => {
+                    write_packet(&mut stream, &last_reply)?;
+                    continue;
+                }
+                Packet::Ack => continue,
+            };
+
+            if !self.no_ack {
+                stream.write_all(b"+")?;
+            }
+            match self.handle(&data, &mut stream)? {
+                Response::Reply(reply) => {
+                    write_packet(&mut stream, &reply)?;
+                    last_reply = reply;
+                }
+                Response::Close(reply) => {
+                    if let Some(reply) = reply {
+                        write_packet(&mut stream, &reply)?;
+                    }
+                    return Ok(());
+                }
+            }
+            if data == b"QStartNoAckMode" {
+                self.no_ack = true;
+            }
+        }
+    }
+
+    fn handle(&mut self, data: &[u8], stream: &mut TcpStream) -> io::Result<Response> {
+        let (cmd, args) = match data.split_first() {
+            Some((cmd, args)) => (*cmd, args),
+            None => return Ok(Response::Reply(Vec::new())),
+        };
+        let reply = match cmd {
+            b'?' => self.stop_reply().to_vec(),
+            b'g' => encode_hex(&read_registers(&self.nes_state)),
+            b'G' => ok_or_error(
+                decode_hex(args).and_then(|bytes| write_registers(&mut self.nes_state, &bytes)),
+            ),
+            b'p' => parse_hex(args)
+                .and_then(|n| read_register(&self.nes_state, n as usize))
+                .map(|bytes| encode_hex(&bytes))
+                .unwrap_or_else(|| b"E01".to_vec()),
+            b'P' => ok_or_error(split_once(args, b'=').and_then(|(n, value)| {
+                write_register(
+                    &mut self.nes_state,
+                    parse_hex(n)? as usize,
+                    &decode_hex(value)?,
+                )
+            })),
+            b'm' => match parse_addr_len(args) {
+                Some((addr, len)) => encode_hex(
+                    &(0..len)
+                        .map(|i| read_memory(&self.nes_state, addr.wrapping_add(i)))
+                        .collect::<Vec<_>>(),
+                ),
+                None => b"E01".to_vec(),
+            },
+            b'M' => ok_or_error(split_once(args, b':').and_then(|(range, value)| {
+                let (addr, len) = parse_addr_len(range)?;
+                let bytes = decode_hex(value)?;
+                if bytes.len() != len as usize {
+                    return None;
+                }
+                bytes.iter().enumerate().try_for_each(|(i, b)| {
+                    write_memory(&mut self.nes_state, addr.wrapping_add(i as u16), *b)
+                })
+            })),
+            b'c' | b's' => {
+                if let Some(addr) = parse_hex(args) {
+                    self.nes_state.cpu.register.PC = addr as u16;
+                }
+                self.resume(stream, cmd == b's')?
+            }
+            b'Z' | b'z' => match args.split(|b| *b == b',').collect::<Vec<_>>()[..] {
+                [b"0", addr, _] => match parse_hex(addr) {
+                    Some(addr) => {
+                        if cmd == b'Z' {
+                            self.breakpoints.insert(addr as u16);
+                        } else {
+                            self.breakpoints.remove(&(addr as u16));
+                        }
+                        b"OK".to_vec()
+                    }
+                    None => b"E01".to_vec(),
+                },
+                _ => Vec::new(),
+            },
+            b'H' => b"OK".to_vec(),
+            b'k' => return Ok(Response::Close(None)),
+            b'D' => return Ok(Response::Close(Some(b"OK".to_vec()))),
+            b'q' | b'Q' => self.query(data),
+            _ => Vec::new(),
+        };
+        Ok(Response::Reply(reply))
+    }
+
+    fn query(&mut self, data: &[u8]) -> Vec<u8> {
+        if data.starts_with(b"qSupported") {
+            b"PacketSize=4000;qXfer:features:read+;QStartNoAckMode+;swbreak+".to_vec()
+        } else if let Some(range) = data.strip_prefix(b"qXfer:features:read:target.xml:") {
+            match parse_addr_len(range) {
+                Some((offset, len)) => {
+                    let xml = TARGET_XML.as_bytes();
+                    let start = (offset as usize).min(xml.len());
+                    let end = (start + len as usize).min(xml.len());
+                    let mut reply = vec![if end == xml.len() { b'l' } else { b'm' }];
+                    reply.extend_from_slice(&xml[start..end]);
+                    reply
+                }
+                None => b"E01".to_vec(),
+            }
+        } else if let Some(command) = data.strip_prefix(b"qRcmd,") {
+            match decode_hex(command).and_then(|command| String::from_utf8(command).ok()) {
+                Some(command) => encode_hex(self.monitor(&command).as_bytes()),
+                None => b"E01".to_vec(),
+            }
+        } else {
+            match data {
+                b"QStartNoAckMode" => b"OK".to_vec(),
+                b"qAttached" => b"1".to_vec(),
+                b"qC" => b"QC1".to_vec(),
+                b"qfThreadInfo" => b"m1".to_vec(),
+                b"qsThreadInfo" => b"l".to_vec(),
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    /// Handles `monitor` commands, which take labels from the loaded symbol files.
+    fn monitor(&mut self, command: &str) -> String {
+        let nes = &self.nes_state;
+        match command.split_whitespace().collect::<Vec<_>>()[..] {
+            ["break", label] => match self.resolve(label) {
+                Some(addr) => {
+                    self.breakpoints.insert(addr);
+                    format!("Breakpoint at {} (${:04X})\n", label, addr)
+                }
+                None => format!("Unknown label {}\n", label),
+            },
+            ["addr", label] => match self.resolve(label) {
+                Some(addr) => format!("${:04X}\n", addr),
+                None => format!("Unknown label {}\n", label),
+            },
+            ["label", addr] => match parse_hex(addr.trim_start_matches('$').as_bytes()) {
+                Some(addr) => format!("{}\n", nes.format_addr(addr as u16)),
+                None => format!("Bad address {}\n", addr),
+            },
+            ["cdl", "on"] | ["cdl", "off"] => {
+                self.nes_state.cdl.enabled = command.ends_with("on");
+                String::from("OK\n")
+            }
+            ["cdl", "clear"] => {
+                self.nes_state.cdl.clear();
+                String::from("OK\n")
+            }
+            ["cdl", "save", path] => match fs::write(path, nes.cdl.save()) {
+                Ok(()) => format!("Saved {}\n", path),
+                Err(e) => format!("{}: {}\n", path, e),
+            },
+            ["cdl", "load", path] => {
+                match fs::read(path).map_err(|e| e.to_string()).and_then(|file| self.nes_state.cdl.load(&file)) {
+                    Ok(()) => format!("Loaded {}\n", path),
+                    Err(e) => format!("{}: {}\n", path, e),
+                }
+            }
+            _ => String::from(
+                "Commands: break <label>, addr <label>, label <addr>, cdl on|off|clear|save <file>|load <file>\n",
+            ),
+        }
+    }
+
+    /// Accepts either a label or a hex address (`$C0A3`, `c0a3`).
+    fn resolve(&self, label: &str) -> Option<u16> {
+        self.nes_state
+            .resolve_label(label)
+            .or_else(|| parse_hex(label.trim_start_matches('$').as_bytes()).map(|addr| addr as u16))
+    }
+
+    fn stop_reply(&self) -> &'static [u8] {
+        if self.nes_state.cpu.jam {
+            SIGILL
+        } else {
+            SIGTRAP
+        }
+    }
+
+    /// Runs the CPU until it hits a breakpoint, finishes a single step or the client interrupts it.
+    fn resume(&mut self, stream: &mut TcpStream, single_step: bool) -> io::Result<Vec<u8>> {
+        let mut executed: u32 = 0;
+        loop {
+            step(&mut self.nes_state);
+            if self.nes_state.cpu.jam {
+                return Ok(SIGILL.to_vec());
+            }
+            if single_step || self.breakpoints.contains(&self.nes_state.cpu.register.PC) {
+                return Ok(SIGTRAP.to_vec());
+            }
+            executed += 1;
+            if executed.is_multiple_of(POLL_INTERVAL) && poll_interrupt(stream)? {
+                return Ok(SIGINT.to_vec());
+            }
+        }
+    }
+}
+
+fn poll_interrupt(stream: &mut TcpStream) -> io::Result<bool> {
+    stream.set_nonblocking(true)?;
+    let mut buf = [0u8; 1];
+    let res = match stream.read(&mut buf) {
+        Ok(0) => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+        Ok(_) => Ok(buf[0] == INTERRUPT),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+        Err(e) => Err(e),
+    };
+    stream.set_nonblocking(false)?;
+    res
+}
+
+fn ok_or_error(res: Option<()>) -> Vec<u8> {
+    match res {
+        Some(()) => b"OK".to_vec(),
+        None => b"E01".to_vec(),
+    }
+}
+
+fn split_once(data: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let i = data.iter().position(|b| *b == sep)?;
+    Some((&data[..i], &data[i + 1..]))
+}
+
+fn parse_addr_len(args: &[u8]) -> Option<(u16, u16)> {
+    let (addr, len) = split_once(args, b',')?;
+    Some((parse_hex(addr)? as u16, parse_hex(len)? as u16))
+}
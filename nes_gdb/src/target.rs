@@ -0,0 +1,82 @@
+use nes_core::usecase::nes::NesState;
+
+/// Register layout exposed to the debugger: A, X, Y, P, S (8bit) and PC (16bit, little endian).
+pub const REGISTER_COUNT: usize = 6;
+
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.aries.m6502.core">
+    <reg name="a" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="x" bitsize="8" type="uint8" regnum="1"/>
+    <reg name="y" bitsize="8" type="uint8" regnum="2"/>
+    <reg name="p" bitsize="8" type="uint8" regnum="3"/>
+    <reg name="sp" bitsize="8" type="uint8" regnum="4"/>
+    <reg name="pc" bitsize="16" type="code_ptr" regnum="5"/>
+  </feature>
+</target>
+"#;
+
+pub fn read_register(nes: &NesState, n: usize) -> Option<Vec<u8>> {
+    let register = &nes.cpu.register;
+    match n {
+        0 => Some(vec![register.A]),
+        1 => Some(vec![register.X]),
+        2 => Some(vec![register.Y]),
+        3 => Some(vec![register.P.get_u8()]),
+        4 => Some(vec![register.S]),
+        5 => Some(register.PC.to_le_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+pub fn write_register(nes: &mut NesState, n: usize, bytes: &[u8]) -> Option<()> {
+    let register = &mut nes.cpu.register;
+    match (n, bytes) {
+        (0, [v]) => register.A = *v,
+        (1, [v]) => register.X = *v,
+        (2, [v]) => register.Y = *v,
+        (3, [v]) => register.P.set_u8(*v),
+        (4, [v]) => register.S = *v,
+        (5, [lo, hi]) => register.PC = u16::from_le_bytes([*lo, *hi]),
+        _ => return None,
+    }
+    Some(())
+}
+
+pub fn read_registers(nes: &NesState) -> Vec<u8> {
+    (0..REGISTER_COUNT)
+        .flat_map(|n| read_register(nes, n).unwrap_or_default())
+        .collect()
+}
+
+pub fn write_registers(nes: &mut NesState, bytes: &[u8]) -> Option<()> {
+    if bytes.len() != REGISTER_COUNT + 1 {
+        return None;
+    }
+    for n in 0..5 {
+        write_register(nes, n, &bytes[n..n + 1])?;
+    }
+    write_register(nes, 5, &bytes[5..7])
+}
+
+/// Reads CPU address space through the side-effect-free peek path.
+pub fn read_memory(nes: &NesState, addr: u16) -> u8 {
+    nes.peek_cpu(addr)
+}
+
+/// Writes RAM or patches PRG-ROM; I/O registers are not writable from the debugger.
+pub fn write_memory(nes: &mut NesState, addr: u16, value: u8) -> Option<()> {
+    match addr {
+        0x2000..=0x4017 => None,
+        _ => {
+            nes.poke_cpu(addr, value);
+            Some(())
+        }
+    }
+}
+
+/// Executes one instruction, taking a pending interrupt first the same way `run_frame` does.
+pub fn step(nes: &mut NesState) {
+    nes.step_instruction();
+}
@@ -0,0 +1,120 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use nes_core::entity::symbol::SymbolTable;
+use nes_gdb::{
+    adapter_impl::cartridge::CartridgeCtx,
+    packet::{decode_hex, encode_hex, read_packet, write_packet, Packet},
+    start_gdb_server,
+};
+
+/// NROM image whose reset vector runs:
+/// $8000 LDA #$42 / $8002 STA $10 / $8004 INX / $8005 JMP $8004
+fn test_rom() -> Vec<u8> {
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..8].copy_from_slice(&[0xA9, 0x42, 0x85, 0x10, 0xE8, 0x4C, 0x04, 0x80]);
+    prg[0x3FFA..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+    rom.extend(prg);
+    rom.extend(vec![0; 0x2000]);
+    rom
+}
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn request(&mut self, command: &str) -> String {
+        write_packet(&mut self.stream, command.as_bytes()).unwrap();
+        loop {
+            match read_packet(&mut self.stream).unwrap() {
+                Packet::Command(data) => {
+                    self.stream.write_all(b"+").unwrap();
+                    return String::from_utf8(data).unwrap();
+                }
+                Packet::Ack => continue,
+                packet => panic!("unexpected packet {:?}", packet),
+            }
+        }
+    }
+}
+
+fn monitor(client: &mut Client, command: &str) -> String {
+    let reply = client.request(&format!(
+        "qRcmd,{}",
+        String::from_utf8(encode_hex(command.as_bytes())).unwrap()
+    ));
+    String::from_utf8(decode_hex(reply.as_bytes()).unwrap()).unwrap()
+}
+
+#[test]
+fn gdb_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut symbols = SymbolTable::default();
+    symbols
+        .load_mesen_mlb("P:0004:loop\nR:0010:value\n")
+        .unwrap();
+    let server = thread::spawn(move || {
+        start_gdb_server(CartridgeCtx::Bytes(test_rom()), symbols, listener).unwrap();
+    });
+
+    let mut client = Client {
+        stream: TcpStream::connect(addr).unwrap(),
+    };
+    assert!(client.request("qSupported:swbreak+").contains("PacketSize"));
+    assert!(client
+        .request("qXfer:features:read:target.xml:0,1000")
+        .starts_with("l<?xml"));
+    assert_eq!("S05", client.request("?"));
+    assert_eq!("0080", client.request("p5"));
+
+    assert_eq!("OK", client.request("Z0,8004,1"));
+    assert_eq!("value\n", monitor(&mut client, "label 10"));
+    assert_eq!("OK\n", monitor(&mut client, "cdl on"));
+    assert_eq!("S05", client.request("c"));
+    assert_eq!("0480", client.request("p5"));
+    assert_eq!("42", client.request("m10,1"));
+    assert_eq!("a942", client.request("m8000,2"));
+
+    assert_eq!("S05", client.request("s"));
+    assert_eq!("0580", client.request("p5"));
+    assert_eq!("01", client.request("p1"));
+
+    assert_eq!("OK", client.request("M10,2:99aa"));
+    assert_eq!("99aa", client.request("m10,2"));
+    assert_eq!("OK", client.request("P0=7f"));
+    assert!(client.request("g").starts_with("7f0100"));
+
+    // the same breakpoint, set by label
+    assert_eq!("OK", client.request("z0,8004,1"));
+    assert_eq!(
+        "Breakpoint at loop ($8004)\n",
+        monitor(&mut client, "break loop")
+    );
+    assert_eq!("S05", client.request("c"));
+    assert_eq!("0480", client.request("p5"));
+
+    assert_eq!("OK", client.request("z0,8004,1"));
+    write_packet(&mut client.stream, b"c").unwrap();
+    let mut ack = [0u8; 1];
+    client.stream.read_exact(&mut ack).unwrap();
+    client.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(
+        Packet::Command(b"S02".to_vec()),
+        read_packet(&mut client.stream).unwrap()
+    );
+
+    // patch a JAM opcode into the loop
+    assert_eq!("OK", client.request("M8004,1:02"));
+    assert_eq!("S04", client.request("c"));
+    assert_eq!("S04", client.request("?"));
+    assert_eq!("0480", client.request("p5"));
+
+    write_packet(&mut client.stream, b"k").unwrap();
+    server.join().unwrap();
+}
@@ -0,0 +1,10 @@
+use nes_core::adapter::cartridge::CartridgeAdapter;
+
+/// The ROM is read once, since a power cycle in a movie loads it again.
+pub struct CartridgeCtx(pub Vec<u8>);
+
+impl CartridgeAdapter for CartridgeCtx {
+    fn read_file(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+}
@@ -0,0 +1,9 @@
+use nes_core::{adapter::video::VideoAdapter, entity::frame_buffer::FrameBuffer};
+
+/// Frames are read from the PPU after each `run_frame`, so nothing is done here.
+#[derive(Default)]
+pub struct VideoCtx {}
+
+impl VideoAdapter for VideoCtx {
+    fn draw_frame(&mut self, _frame: &FrameBuffer) {}
+}
@@ -0,0 +1,207 @@
+//! Uncompressed AVI writer: one video stream of 24bit bottom-up BGR frames, which keeps the
+//! exact colours. AVI 1.0 sizes are 32bit, so a file holds at most 4GiB (about 6 minutes
+//! of 256x240 NTSC video).
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// `RIFF` and `hdrl` list headers, `avih`, the `strl` list and the `movi` list header.
+const HEADER_LEN: u64 = 224;
+const AVIF_HASINDEX: u32 = 0x10;
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+pub struct AviWriter<W: Write + Seek> {
+    out: W,
+    width: usize,
+    height: usize,
+    frame_rate: (u32, u32),
+    frames: u32,
+}
+
+impl<W: Write + Seek> AviWriter<W> {
+    /// Writes a header for no frames, completed by `finish`.
+    pub fn new(out: W, width: usize, height: usize, frame_rate: (u32, u32)) -> io::Result<Self> {
+        let mut writer = Self {
+            out,
+            width,
+            height,
+            frame_rate,
+            frames: 0,
+        };
+        let header = writer.header();
+        writer.out.write_all(&header)?;
+        Ok(writer)
+    }
+
+    /// Rows are padded to 4 bytes.
+    fn frame_len(&self) -> u32 {
+        ((self.width * 3 + 3) & !3) as u32 * self.height as u32
+    }
+
+    /// Size of the `movi` list after the list size field.
+    fn movi_len(&self) -> u64 {
+        4 + self.frames as u64 * (8 + self.frame_len() as u64)
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let frame_len = self.frame_len();
+        let (rate, scale) = self.frame_rate;
+        let mut h = Vec::with_capacity(HEADER_LEN as usize);
+        let riff_len = 4 + 200 + 8 + self.movi_len() + 8 + 16 * self.frames as u64;
+        h.extend(b"RIFF");
+        h.extend((riff_len as u32).to_le_bytes());
+        h.extend(b"AVI LIST");
+        h.extend(192_u32.to_le_bytes());
+        h.extend(b"hdrlavih");
+        h.extend(56_u32.to_le_bytes());
+        let micro_sec_per_frame = (1_000_000 * scale as u64 + rate as u64 / 2) / rate as u64;
+        for value in [
+            micro_sec_per_frame as u32,
+            (frame_len as u64 * rate as u64 / scale as u64) as u32,
+            0,
+            AVIF_HASINDEX,
+            self.frames,
+            0,
+            1,
+            frame_len,
+            self.width as u32,
+            self.height as u32,
+            0,
+            0,
+            0,
+            0,
+        ] {
+            h.extend(value.to_le_bytes());
+        }
+        h.extend(b"LIST");
+        h.extend(116_u32.to_le_bytes());
+        h.extend(b"strlstrh");
+        h.extend(56_u32.to_le_bytes());
+        h.extend(b"vidsDIB ");
+        // flags, priority and language, initial frames, then the rate as rate / scale
+        for value in [0, 0, 0, scale, rate, 0, self.frames, frame_len, u32::MAX, 0] {
+            h.extend(value.to_le_bytes());
+        }
+        for value in [0, 0, self.width as u16, self.height as u16] {
+            h.extend(value.to_le_bytes());
+        }
+        h.extend(b"strf");
+        h.extend(40_u32.to_le_bytes());
+        // BITMAPINFOHEADER; a positive height means bottom-up rows
+        h.extend(40_u32.to_le_bytes());
+        h.extend((self.width as u32).to_le_bytes());
+        h.extend((self.height as u32).to_le_bytes());
+        h.extend(1_u16.to_le_bytes());
+        h.extend(24_u16.to_le_bytes());
+        for value in [0, frame_len, 0, 0, 0, 0] {
+            h.extend(value.to_le_bytes());
+        }
+        h.extend(b"LIST");
+        h.extend((self.movi_len() as u32).to_le_bytes());
+        h.extend(b"movi");
+        h
+    }
+
+    /// Appends a frame of RGB24 `rgb` rows.
+    pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        let frame_len = self.frame_len();
+        let file_len =
+            HEADER_LEN + self.movi_len() + 8 + frame_len as u64 + 8 + 16 * (self.frames as u64 + 1);
+        if file_len - 8 > u32::MAX as u64 {
+            return Err(io::Error::other("AVI files are limited to 4GiB"));
+        }
+        let row_len = (self.width * 3 + 3) & !3;
+        let mut data = vec![0; frame_len as usize];
+        let rows = rgb.chunks_exact(self.width * 3).take(self.height);
+        for (row, dst) in rows.rev().zip(data.chunks_exact_mut(row_len)) {
+            for (src, dst) in row.chunks_exact(3).zip(dst.chunks_exact_mut(3)) {
+                dst.copy_from_slice(&[src[2], src[1], src[0]]);
+            }
+        }
+        self.out.write_all(b"00db")?;
+        self.out.write_all(&frame_len.to_le_bytes())?;
+        self.out.write_all(&data)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Writes the index and the final sizes.
+    pub fn finish(mut self) -> io::Result<W> {
+        let frame_len = self.frame_len();
+        let mut index = Vec::with_capacity(8 + 16 * self.frames as usize);
+        index.extend(b"idx1");
+        index.extend((16 * self.frames).to_le_bytes());
+        for n in 0..self.frames {
+            // offsets count from the `movi` tag
+            let offset = 4 + n * (8 + frame_len);
+            for value in [
+                u32::from_le_bytes(*b"00db"),
+                AVIIF_KEYFRAME,
+                offset,
+                frame_len,
+            ] {
+                index.extend(value.to_le_bytes());
+            }
+        }
+        self.out.write_all(&index)?;
+        let header = self.header();
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn _layout() {
+        let mut writer = AviWriter::new(Cursor::new(Vec::new()), 3, 2, (60, 1)).unwrap();
+        // top row red, bottom row blue
+        let rgb = [[255, 0, 0]; 3]
+            .iter()
+            .chain(&[[0, 0, 255]; 3])
+            .flatten()
+            .copied()
+            .collect::<Vec<u8>>();
+        writer.write_frame(&rgb).unwrap();
+        writer.write_frame(&rgb).unwrap();
+        let avi = writer.finish().unwrap().into_inner();
+
+        // 12 bytes per row, 2 frames of 8 + 24, 8 + 2 * 16 of index
+        assert_eq!(HEADER_LEN as usize + 64 + 40, avi.len());
+        assert_eq!(b"RIFF", &avi[0..4]);
+        assert_eq!(avi.len() - 8, u32_at(&avi, 4) as usize);
+        assert_eq!(b"hdrl", &avi[20..24]);
+        assert_eq!(16667, u32_at(&avi, 32));
+        // total frames in avih and length in strh
+        assert_eq!(2, u32_at(&avi, 48));
+        assert_eq!(b"strh", &avi[100..104]);
+        assert_eq!(2, u32_at(&avi, 140));
+        assert_eq!(b"strf", &avi[164..168]);
+        assert_eq!(24, u16::from_le_bytes([avi[186], avi[187]]));
+        assert_eq!(b"movi", &avi[220..224]);
+        assert_eq!(4 + 64, u32_at(&avi, 216));
+
+        let frame = &avi[224..256];
+        assert_eq!(b"00db", &frame[..4]);
+        assert_eq!(24, u32_at(frame, 4));
+        // bottom row first, BGR
+        assert_eq!([255, 0, 0], frame[8..11]);
+        assert_eq!([0, 0, 255], frame[20..23]);
+
+        let index = &avi[288..];
+        assert_eq!(b"idx1", &index[..4]);
+        assert_eq!(32, u32_at(index, 4));
+        assert_eq!(4, u32_at(index, 16));
+        assert_eq!(4 + 32, u32_at(index, 32));
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use adapter_impl::{audio::AudioCtx, cartridge::CartridgeCtx, video::VideoCtx};
+use avi::AviWriter;
+use nes_core::{
+    adapter::nes::NesAdapter,
+    entity::{joypad::JoyPadBtnState, movie::Movie, region::Region},
+    usecase::nes::NesState,
+};
+use options::Options;
+use wav::WavWriter;
+use y4m::Y4mWriter;
+
+pub mod adapter_impl;
+pub mod avi;
+pub mod options;
+pub mod wav;
+pub mod y4m;
+
+/// Video file chosen by the extension of its name.
+pub enum VideoWriter {
+    Y4m(Y4mWriter<BufWriter<File>>),
+    Avi(AviWriter<BufWriter<File>>),
+}
+
+impl VideoWriter {
+    pub fn create(
+        path: &str,
+        (width, height): (usize, usize),
+        frame_rate: (u32, u32),
+    ) -> Result<Self, String> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        let error = |e: io::Error| format!("{}: {}", path, e);
+        let out = || File::create(path).map(BufWriter::new).map_err(error);
+        match extension.as_deref() {
+            Some("y4m") => Ok(VideoWriter::Y4m(
+                Y4mWriter::new(out()?, width, height, frame_rate).map_err(error)?,
+            )),
+            Some("avi") => Ok(VideoWriter::Avi(
+                AviWriter::new(out()?, width, height, frame_rate).map_err(error)?,
+            )),
+            _ => Err(format!("{}: the video file must be .y4m or .avi", path)),
+        }
+    }
+
+    pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        match self {
+            VideoWriter::Y4m(writer) => writer.write_frame(rgb),
+            VideoWriter::Avi(writer) => writer.write_frame(rgb),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            VideoWriter::Y4m(writer) => writer.finish().map(drop),
+            VideoWriter::Avi(writer) => writer.finish().map(drop),
+        }
+    }
+}
+
+/// Runs the ROM at `file_path` headless, fed by the movie if any, and writes every emulated
+/// frame and its audio. Frames and samples both come from the emulated clock, so the files
+/// stay in sync however fast the host runs. Returns the number of frames recorded.
+pub fn record(file_path: &str, options: &Options) -> Result<usize, String> {
+    if options.video.is_none() && options.audio.is_none() {
+        return Err(String::from(
+            "nothing to record: give --video and/or --audio",
+        ));
+    }
+    let rom = fs::read(file_path).map_err(|e| format!("{}: {}", file_path, e))?;
+    let movie = match &options.movie {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path, e))?
+            .parse::<Movie>()
+            .map_err(|e| format!("{}: {}", path, e))?,
+        None => Movie::default(),
+    };
+    let frames = options
+        .frames
+        .or(options.movie.as_ref().map(|_| movie.frames.len()))
+        .ok_or("--frames is needed without a movie")?;
+    let region = options
+        .region
+        .or(movie.pal.then_some(Region::Pal))
+        .or_else(|| Region::from_file_name(file_path));
+    let boot = || {
+        let mut nes_state = NesAdapter {
+            cartridge: Box::new(CartridgeCtx(rom.clone())),
+            video: Box::new(VideoCtx::default()),
+            audio: Box::new(AudioCtx::default()),
+        }
+        .init();
+        if let Some(region) = region {
+            nes_state.set_region(region);
+        }
+        if options.audio.is_some() {
+            nes_state.start_audio(options.sample_rate);
+        }
+        nes_state
+    };
+    let mut nes_state = boot();
+
+    let mut video = match &options.video {
+        Some(path) => Some(VideoWriter::create(
+            path,
+            options.overscan.size(),
+            nes_state.region.frame_rate_ratio(),
+        )?),
+        None => None,
+    };
+    let mut audio = match &options.audio {
+        Some(path) => Some(
+            File::create(path)
+                .and_then(|file| WavWriter::new(BufWriter::new(file), options.sample_rate))
+                .map_err(|e| format!("{}: {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    for n in 0..frames {
+        // the buttons are released once the movie is over
+        let input = movie.frames.get(n).copied().unwrap_or_default();
+        if input.power_cycle() {
+            nes_state = boot();
+        } else if input.soft_reset() {
+            nes_state.power();
+        }
+        apply_input(&mut nes_state, input.ports);
+        nes_state.run_frame();
+        if let (Some(video), Some(path)) = (&mut video, &options.video) {
+            video
+                .write_frame(&nes_state.frame_rgb(&options.overscan))
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let (Some(audio), Some(path)) = (&mut audio, &options.audio) {
+            audio
+                .write_samples(&nes_state.take_samples())
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+    }
+
+    if let (Some(video), Some(path)) = (video, &options.video) {
+        video.finish().map_err(|e| format!("{}: {}", path, e))?;
+    }
+    if let (Some(audio), Some(path)) = (audio, &options.audio) {
+        audio.finish().map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(frames)
+}
+
+fn apply_input(nes_state: &mut NesState, [port0, port1]: [u8; 2]) {
+    nes_state.joypad.state_1p = JoyPadBtnState::from_u8(port0);
+    nes_state.joypad.state_2p = JoyPadBtnState::from_u8(port1);
+}
@@ -0,0 +1,18 @@
+use std::{env, process};
+
+use nes_record::{options::Options, record};
+
+fn main() {
+    let (file_path, options) = Options::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    let file_path = file_path.unwrap_or_else(|| String::from("assets/helloworld.nes"));
+    match record(&file_path, &options) {
+        Ok(frames) => println!("Recorded {} frames", frames),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use nes_core::entity::{overscan::Overscan, region::Region};
+
+/// Command line options of the recorder.
+#[derive(Debug)]
+pub struct Options {
+    /// An FCEUX `.fm2` file played from power on.
+    pub movie: Option<String>,
+    /// Frames to record; the length of the movie when not given.
+    pub frames: Option<usize>,
+    /// A `.y4m` or `.avi` file.
+    pub video: Option<String>,
+    /// A `.wav` file.
+    pub audio: Option<String>,
+    pub sample_rate: u32,
+    /// Overrides the region of the movie, the ROM header and the file name.
+    pub region: Option<Region>,
+    pub overscan: Overscan,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            movie: None,
+            frames: None,
+            video: None,
+            audio: None,
+            sample_rate: 44100,
+            region: None,
+            overscan: Overscan::NONE,
+        }
+    }
+}
+
+impl Options {
+    /// Parses `rom [--movie FILE] [--frames N] [--video FILE.y4m|FILE.avi] [--audio FILE.wav]
+    /// [--sample-rate HZ] [--region ntsc|pal|dendy] [--overscan ntsc|none|T,B,L,R]` and returns
+    /// the ROM path, if given, with the options.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<(Option<String>, Self), String> {
+        let mut file_path = None;
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--movie" => options.movie = Some(args.next().ok_or("--movie needs a file")?),
+                "--frames" => {
+                    options.frames = Some(
+                        args.next()
+                            .and_then(|n| n.parse().ok())
+                            .ok_or("--frames needs a number")?,
+                    )
+                }
+                "--video" => options.video = Some(args.next().ok_or("--video needs a file")?),
+                "--audio" => options.audio = Some(args.next().ok_or("--audio needs a file")?),
+                "--sample-rate" => {
+                    options.sample_rate = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|&n| (8000..=192000).contains(&n))
+                        .ok_or("--sample-rate needs a rate between 8000 and 192000")?
+                }
+                "--region" => {
+                    options.region = Some(args.next().ok_or("--region needs a name")?.parse()?)
+                }
+                "--overscan" => {
+                    options.overscan = args.next().ok_or("--overscan needs a setting")?.parse()?
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => file_path = Some(arg),
+            }
+        }
+        Ok((file_path, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<(Option<String>, Options), String> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn _parse() {
+        let (rom, options) =
+            parse("game.nes --movie run.fm2 --video run.avi --audio run.wav --overscan ntsc")
+                .unwrap();
+        assert_eq!(Some(String::from("game.nes")), rom);
+        assert_eq!(Some(String::from("run.fm2")), options.movie);
+        assert_eq!(Some(String::from("run.avi")), options.video);
+        assert_eq!(Some(String::from("run.wav")), options.audio);
+        assert_eq!(None, options.frames);
+        assert_eq!(44100, options.sample_rate);
+        assert_eq!(Overscan::NTSC, options.overscan);
+        assert!(parse("game.nes --frames").is_err());
+        assert!(parse("game.nes --sample-rate 10").is_err());
+        assert!(parse("game.nes --fps 30").is_err());
+    }
+}
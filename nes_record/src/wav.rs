@@ -0,0 +1,90 @@
+//! WAV writer for 16bit mono PCM.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+const HEADER_LEN: u64 = 44;
+
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    sample_rate: u32,
+    samples: u64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Writes a header for no samples, completed by `finish`.
+    pub fn new(out: W, sample_rate: u32) -> io::Result<Self> {
+        let mut writer = Self {
+            out,
+            sample_rate,
+            samples: 0,
+        };
+        let header = writer.header();
+        writer.out.write_all(&header)?;
+        Ok(writer)
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let data_len = self.samples as u32 * 2;
+        let mut h = Vec::with_capacity(HEADER_LEN as usize);
+        h.extend(b"RIFF");
+        h.extend((36 + data_len).to_le_bytes());
+        h.extend(b"WAVEfmt ");
+        h.extend(16_u32.to_le_bytes());
+        // PCM, 1 channel
+        h.extend(1_u16.to_le_bytes());
+        h.extend(1_u16.to_le_bytes());
+        h.extend(self.sample_rate.to_le_bytes());
+        h.extend((self.sample_rate * 2).to_le_bytes());
+        // bytes per frame, bits per sample
+        h.extend(2_u16.to_le_bytes());
+        h.extend(16_u16.to_le_bytes());
+        h.extend(b"data");
+        h.extend(data_len.to_le_bytes());
+        h
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let samples_len = self.samples + samples.len() as u64;
+        if HEADER_LEN - 8 + samples_len * 2 > u32::MAX as u64 {
+            return Err(io::Error::other("WAV files are limited to 4GiB"));
+        }
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.out.write_all(&data)?;
+        self.samples = samples_len;
+        Ok(())
+    }
+
+    /// Writes the final sizes.
+    pub fn finish(mut self) -> io::Result<W> {
+        let header = self.header();
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn _header() {
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), 44100).unwrap();
+        writer.write_samples(&[1, -1]).unwrap();
+        writer.write_samples(&[i16::MAX]).unwrap();
+        let wav = writer.finish().unwrap().into_inner();
+        assert_eq!(50, wav.len());
+        assert_eq!(b"RIFF", &wav[0..4]);
+        assert_eq!(42, u32::from_le_bytes(wav[4..8].try_into().unwrap()));
+        assert_eq!(b"WAVEfmt ", &wav[8..16]);
+        assert_eq!(44100, u32::from_le_bytes(wav[24..28].try_into().unwrap()));
+        assert_eq!(88200, u32::from_le_bytes(wav[28..32].try_into().unwrap()));
+        assert_eq!(b"data", &wav[36..40]);
+        assert_eq!(6, u32::from_le_bytes(wav[40..44].try_into().unwrap()));
+        assert_eq!([0x01, 0x00, 0xFF, 0xFF, 0xFF, 0x7F], wav[44..]);
+    }
+}
@@ -0,0 +1,81 @@
+//! YUV4MPEG2 writer. Frames are stored as 4:4:4 BT.601 studio range YCbCr, which every
+//! y4m reader understands; the conversion rounds, so colours can be off by one step.
+
+use std::io::{self, Write};
+
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Writes the stream header; `frame_rate` is a numerator and denominator.
+    pub fn new(
+        mut out: W,
+        width: usize,
+        height: usize,
+        frame_rate: (u32, u32),
+    ) -> io::Result<Self> {
+        writeln!(
+            out,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444",
+            width, height, frame_rate.0, frame_rate.1
+        )?;
+        Ok(Self { out, width, height })
+    }
+
+    /// Appends a frame of RGB24 `rgb` rows.
+    pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        let pixels = self.width * self.height;
+        let mut planes = vec![0; pixels * 3];
+        for (i, pixel) in rgb.chunks_exact(3).take(pixels).enumerate() {
+            let [y, u, v] = ycbcr(pixel[0], pixel[1], pixel[2]);
+            planes[i] = y;
+            planes[pixels + i] = u;
+            planes[pixels * 2 + i] = v;
+        }
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&planes)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// BT.601 studio range (Y 16~235, Cb and Cr 16~240) in 8bit fixed point.
+fn ycbcr(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, u as u8, v as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _ycbcr() {
+        assert_eq!([16, 128, 128], ycbcr(0, 0, 0));
+        assert_eq!([235, 128, 128], ycbcr(255, 255, 255));
+        assert_eq!([82, 90, 240], ycbcr(255, 0, 0));
+    }
+
+    #[test]
+    fn _stream() {
+        let mut writer = Y4mWriter::new(Vec::new(), 2, 1, (60, 1)).unwrap();
+        writer.write_frame(&[0, 0, 0, 255, 255, 255]).unwrap();
+        writer.write_frame(&[0; 6]).unwrap();
+        let out = writer.finish().unwrap();
+        let header = b"YUV4MPEG2 W2 H1 F60:1 Ip A1:1 C444\n";
+        assert_eq!(header, &out[..header.len()]);
+        let frame = &out[header.len()..];
+        assert_eq!(b"FRAME\n", &frame[..6]);
+        assert_eq!([16, 235, 128, 128, 128, 128], frame[6..12]);
+        assert_eq!(2 * (6 + 6), frame.len());
+    }
+}
@@ -0,0 +1,93 @@
+use std::{env, fs, path::PathBuf};
+
+use nes_record::{options::Options, record};
+
+/// NROM image whose reset vector loops reading controller 1 and, once A is held,
+/// sets the DMC output level:
+/// $8000 LDA #$01 / STA $4016 / LDA #$00 / STA $4016 / LDA $4016 / AND #$01 / BEQ $8000
+/// $8011 LDA #$7F / STA $4011 / JMP $8000
+fn test_rom() -> Vec<u8> {
+    let program = [
+        0xA9, 0x01, 0x8D, 0x16, 0x40, 0xA9, 0x00, 0x8D, 0x16, 0x40, 0xAD, 0x16, 0x40, 0x29, 0x01,
+        0xF0, 0xEF, 0xA9, 0x7F, 0x8D, 0x11, 0x40, 0x4C, 0x00, 0x80,
+    ];
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..program.len()].copy_from_slice(&program);
+    prg[0x3FFA..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+    rom.extend(prg);
+    rom.extend(vec![0; 0x2000]);
+    rom
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("nes_record_{}_{}", std::process::id(), name))
+}
+
+/// Records `frames` frames of the test ROM with A pressed from frame `press`.
+fn record_test_rom(name: &str, frames: usize, press: usize) -> (Vec<u8>, Vec<u8>) {
+    let rom = temp_path(&format!("{}.nes", name));
+    fs::write(&rom, test_rom()).unwrap();
+    let mut movie = String::from("version 3\nport0 1\nport1 1\nport2 0\n");
+    for n in 0..frames {
+        movie += if n < press {
+            "|0|........|........||\n"
+        } else {
+            "|0|.......A|........||\n"
+        };
+    }
+    let movie_path = temp_path(&format!("{}.fm2", name));
+    fs::write(&movie_path, movie).unwrap();
+    let video = temp_path(&format!("{}.y4m", name));
+    let audio = temp_path(&format!("{}.wav", name));
+    let options = Options {
+        movie: Some(movie_path.to_string_lossy().into_owned()),
+        video: Some(video.to_string_lossy().into_owned()),
+        audio: Some(audio.to_string_lossy().into_owned()),
+        ..Options::default()
+    };
+    assert_eq!(Ok(frames), record(&rom.to_string_lossy(), &options));
+    let files = (fs::read(&video).unwrap(), fs::read(&audio).unwrap());
+    for path in [rom, movie_path, video, audio] {
+        fs::remove_file(path).unwrap();
+    }
+    files
+}
+
+#[test]
+fn movie_input_reaches_the_game() {
+    let (y4m, wav) = record_test_rom("input", 20, 10);
+    let header = b"YUV4MPEG2 W256 H240 F39375000:655171 Ip A1:1 C444\n";
+    assert_eq!(header, &y4m[..header.len()]);
+    assert_eq!(header.len() + 20 * (6 + 256 * 240 * 3), y4m.len());
+
+    let samples: Vec<i16> = wav[44..]
+        .chunks_exact(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]))
+        .collect();
+    // about 734 samples a frame
+    assert!(samples.len().abs_diff(20 * 734) < 100, "{}", samples.len());
+    let pressed = 9 * 734;
+    // the triangle resting at level 15 thumps at power on, then the high-pass settles
+    assert!(samples[pressed - 734..pressed]
+        .iter()
+        .all(|&s| s.abs() < 100));
+    assert!(samples[pressed..].iter().any(|&s| s > 10000));
+}
+
+#[test]
+fn replays_are_deterministic() {
+    assert_eq!(
+        record_test_rom("first", 8, 4),
+        record_test_rom("second", 8, 4)
+    );
+}
+
+#[test]
+fn nothing_to_record() {
+    let options = Options {
+        frames: Some(1),
+        ..Options::default()
+    };
+    assert!(record("missing.nes", &options).is_err());
+}
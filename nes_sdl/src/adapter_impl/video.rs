@@ -1,51 +1,172 @@
-use nes_core::adapter::video::VideoAdapter;
-use sdl2::{pixels::Color, rect::Rect, render::WindowCanvas, Sdl};
+use std::{cell::Cell, rc::Rc};
+
+use nes_core::{
+    adapter::video::VideoAdapter,
+    entity::{frame_buffer::FrameBuffer, overscan::Overscan, palette::Palette},
+};
+use nes_filters::filter::FilterChain;
+use sdl2::{
+    pixels::PixelFormatEnum,
+    rect::Rect,
+    render::{Texture, WindowCanvas},
+    video::FullscreenType,
+    Sdl,
+};
+
+/// How the 256x240 picture is put on the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoOptions {
+    /// Initial window size, in multiples of the picture.
+    pub scale: u32,
+    /// Only scale by whole numbers, leaving a border instead of uneven pixels.
+    pub integer_scale: bool,
+    /// Stretch to the 8:7 pixel aspect ratio of an NTSC TV.
+    pub aspect_8_7: bool,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Software filters run on each frame before it is uploaded.
+    pub filter: FilterChain,
+    /// Edges of the picture left out of the window.
+    pub overscan: Overscan,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            scale: 3,
+            integer_scale: false,
+            aspect_8_7: false,
+            fullscreen: false,
+            vsync: false,
+            filter: FilterChain::default(),
+            overscan: Overscan::default(),
+        }
+    }
+}
+
+impl VideoOptions {
+    /// Size of the visible picture once the pixel aspect ratio is applied.
+    pub fn display_size(&self, overscan: &Overscan) -> (u32, u32) {
+        let (width, height) = overscan.size();
+        if self.aspect_8_7 {
+            (width as u32 * 8 / 7, height as u32)
+        } else {
+            (width as u32, height as u32)
+        }
+    }
+}
 
 pub struct VideoCtx {
     canvas: WindowCanvas,
-    pixel_size: u32,
+    texture: Texture,
+    options: VideoOptions,
+    /// Shared with the event loop, which flips it on Alt+Enter or F11.
+    pub fullscreen: Rc<Cell<bool>>,
+    /// Shared with `start_nes`, which may replace it with the game's own setting.
+    pub overscan: Rc<Cell<Overscan>>,
 }
 
 impl VideoCtx {
-    pub fn new(sdl: &Sdl, pixel_size: u32) -> Self {
+    pub fn new(sdl: &Sdl, options: &VideoOptions) -> Self {
         let video_subsystem = sdl.video().expect("Could not initalize SDL video context.");
-        let window = video_subsystem
-            .window("aries", 256 * pixel_size, 240 * pixel_size)
-            .position_centered()
+        let (display_width, display_height) = options.display_size(&options.overscan);
+        let mut window = video_subsystem.window(
+            "aries",
+            display_width * options.scale,
+            display_height * options.scale,
+        );
+        window.position_centered().resizable();
+        if options.fullscreen {
+            window.fullscreen_desktop();
+        }
+        let window = window
             .build()
             .expect("Could not initialize video subsystem of SDL window.");
 
-        let canvas = window
-            .into_canvas()
-            .build()
-            .expect("Could not make a canvas.");
-        Self { canvas, pixel_size }
+        let mut canvas = window.into_canvas().accelerated();
+        if options.vsync {
+            canvas = canvas.present_vsync();
+        }
+        let mut canvas = canvas.build().expect("Could not make a canvas.");
+        // SDL letterboxes the logical size into whatever the window is resized to
+        canvas
+            .set_logical_size(display_width, display_height)
+            .expect("Could not set the logical size.");
+        canvas
+            .set_integer_scale(options.integer_scale)
+            .expect("Could not set integer scaling.");
+        let (width, height) = options.filter.output_size();
+        let texture = canvas
+            .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+            .expect("Could not make a texture.");
+        Self {
+            canvas,
+            texture,
+            options: options.clone(),
+            fullscreen: Rc::new(Cell::new(options.fullscreen)),
+            overscan: Rc::new(Cell::new(options.overscan)),
+        }
     }
 
-    pub fn draw_rect(&mut self, x: i32, y: i32, color: Color) {
-        self.canvas.set_draw_color(color);
-        self.canvas
-            .fill_rect(Rect::new(
-                x * self.pixel_size as i32,
-                y * self.pixel_size as i32,
-                self.pixel_size,
-                self.pixel_size,
-            ))
-            .expect("Could not draw a rectangle pixel.");
+    fn apply_overscan(&mut self) {
+        let overscan = self.overscan.get();
+        if overscan != self.options.overscan {
+            self.options.overscan = overscan;
+            let (width, height) = self.options.display_size(&overscan);
+            self.canvas
+                .set_logical_size(width, height)
+                .expect("Could not set the logical size.");
+        }
     }
-}
 
-impl VideoAdapter for VideoCtx {
-    fn draw_frame(&mut self, pixels: [[u8; 3]; 256 * 240]) {
-        self.canvas.clear();
+    /// SDL id of the game window, which window events refer to.
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
 
-        for y in 0..240 {
-            for x in 0..256 {
-                let rgb = pixels[256 * y + x];
-                self.draw_rect(x as i32, y as i32, Color::RGB(rgb[0], rgb[1], rgb[2]));
-            }
+    fn apply_fullscreen(&mut self) {
+        let fullscreen = if self.fullscreen.get() {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+        let window = self.canvas.window_mut();
+        if window.fullscreen_state() != fullscreen {
+            window
+                .set_fullscreen(fullscreen)
+                .expect("Could not switch fullscreen.");
         }
+    }
+}
 
+impl VideoAdapter for VideoCtx {
+    fn draw_frame(&mut self, frame: &FrameBuffer) {
+        self.apply_fullscreen();
+        self.apply_overscan();
+        let filter = &self.options.filter;
+        let uploaded = if filter.is_empty() {
+            self.texture.update(None, frame.data(), 256 * 3)
+        } else {
+            // the frame is RGB24, so the palette is not consulted
+            let image = filter.apply_frame(frame, &Palette::default());
+            self.texture
+                .update(None, &image.to_rgb24(), image.width * 3)
+        };
+        uploaded.expect("Could not upload the frame.");
+        let query = self.texture.query();
+        let (x, y, width, height) = self
+            .options
+            .overscan
+            .rect(query.width as usize, query.height as usize);
+        let (display_width, display_height) = self.options.display_size(&self.options.overscan);
+        self.canvas.clear();
+        self.canvas
+            .copy(
+                &self.texture,
+                Rect::new(x as i32, y as i32, width as u32, height as u32),
+                Rect::new(0, 0, display_width, display_height),
+            )
+            .expect("Could not draw the frame.");
         self.canvas.present();
     }
 }
@@ -1,23 +1,75 @@
 use std::{
+    fs,
+    path::Path,
     thread::sleep,
     time::{Duration, Instant},
 };
 
 use adapter_impl::{audio::AudioCtx, cartridge::CartridgeCtx, video::VideoCtx};
-use nes_core::adapter::nes::NesAdapter;
-use sdl2::{event::Event, keyboard::Keycode};
+use nes_core::{
+    adapter::nes::NesAdapter,
+    entity::{
+        overscan::{Overscan, OverscanOverrides},
+        palette::{Palette, PalettePreset},
+        region::Region,
+    },
+    usecase::{nes::NesState, screenshot::ScreenshotOptions},
+};
+use options::Options;
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+};
+use viewer::{Viewer, Viewers};
 
 pub mod adapter_impl;
+pub mod options;
+pub mod viewer;
 
-pub fn start_nes(file_path: String) -> Result<(), String> {
+pub fn start_nes(file_path: String, options: Options) -> Result<(), String> {
     let sdl = sdl2::init().expect("Could not initialize SDL context.");
+    let video = VideoCtx::new(&sdl, &options.video);
+    let fullscreen = video.fullscreen.clone();
+    let overscan = video.overscan.clone();
+    let window_id = video.window_id();
     let mut nes_state = NesAdapter {
-        cartridge: Box::new(CartridgeCtx::new(file_path)),
-        video: Box::new(VideoCtx::new(&sdl, 3)),
+        cartridge: Box::new(CartridgeCtx::new(file_path.clone())),
+        video: Box::new(video),
         audio: Box::new(AudioCtx::default()),
     }
     .init();
+    if let Some(region) = options
+        .region
+        .or_else(|| Region::from_file_name(&file_path))
+    {
+        nes_state.set_region(region);
+    }
+    if let Some(palette) = &options.palette {
+        nes_state.ppu.palette = if palette.to_ascii_lowercase().ends_with(".pal") {
+            Palette::from_pal(&fs::read(palette).map_err(|e| format!("{}: {}", palette, e))?)?
+        } else {
+            match palette.parse::<PalettePreset>()? {
+                PalettePreset::Ntsc => Palette::from_ntsc(&options.ntsc_params),
+                preset => preset.palette(),
+            }
+        };
+    }
+    if let Some(path) = &options.overscan_overrides {
+        let overrides: OverscanOverrides = fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path, e))?
+            .parse()
+            .map_err(|e| format!("{}: {}", path, e))?;
+        if let Some(game) = overrides.get(nes_state.cartridge.crc32()) {
+            overscan.set(game);
+        }
+    }
+    nes_state.ppu.layers.no_sprite_limit = options.no_sprite_limit;
+    let frame_nanos = (1_000_000_000.0 / nes_state.region.frame_rate()) as i64;
+    for code in &options.cheats {
+        nes_state.add_cheat(code)?;
+    }
 
+    let mut viewers = Viewers::new(&sdl);
     let mut event_pump = sdl.event_pump()?;
     'window_loop: loop {
         let start = Instant::now();
@@ -27,11 +79,50 @@ pub fn start_nes(file_path: String) -> Result<(), String> {
                 Event::Quit { .. } => {
                     break 'window_loop;
                 }
+                // closing a viewer only closes that window
+                Event::Window {
+                    window_id: id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } if id == window_id || !viewers.close(id) => break 'window_loop,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    fullscreen.set(!fullscreen.get())
+                }
                 Event::KeyDown {
                     keycode: Some(code),
+                    keymod,
                     ..
                 } => match code {
                     Keycode::Escape => break 'window_loop,
+                    Keycode::F11 => fullscreen.set(!fullscreen.get()),
+                    Keycode::F1 => viewers.toggle(Viewer::Nametables, &nes_state),
+                    Keycode::F2 => viewers.toggle(Viewer::PatternTables, &nes_state),
+                    Keycode::F3 => viewers.toggle(Viewer::Oam, &nes_state),
+                    Keycode::F4 => viewers.toggle(Viewer::Palette, &nes_state),
+                    Keycode::F5 => viewers.next_palette(),
+                    Keycode::F6 => {
+                        let layers = &mut nes_state.ppu.layers;
+                        layers.hide_background = !layers.hide_background
+                    }
+                    Keycode::F7 => {
+                        let layers = &mut nes_state.ppu.layers;
+                        layers.hide_sprites = !layers.hide_sprites
+                    }
+                    Keycode::F12 => {
+                        // Shift+F12 also saves the edges the overscan hides
+                        let overscan = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                            Overscan::NONE
+                        } else {
+                            overscan.get()
+                        };
+                        if let Err(e) = save_screenshot(&nes_state, &file_path, overscan) {
+                            eprintln!("Could not save the screenshot: {}", e);
+                        }
+                    }
                     Keycode::X => nes_state.joypad.state_1p.A = true,
                     Keycode::Z => nes_state.joypad.state_1p.B = true,
                     Keycode::A => nes_state.joypad.state_1p.SELECT = true,
@@ -61,8 +152,9 @@ pub fn start_nes(file_path: String) -> Result<(), String> {
         }
 
         nes_state.run_frame();
+        viewers.draw(&nes_state);
 
-        let remaining_time_nanos = 1_000_000_000 / 60 - start.elapsed().subsec_nanos() as i64;
+        let remaining_time_nanos = frame_nanos - start.elapsed().subsec_nanos() as i64;
         if remaining_time_nanos > 0 {
             sleep(Duration::new(0, remaining_time_nanos as u32));
         }
@@ -70,3 +162,19 @@ pub fn start_nes(file_path: String) -> Result<(), String> {
 
     Ok(())
 }
+
+/// Writes the last frame in the working directory as `<rom name>-<frame>.png`, with
+/// the palette indices as metadata for bug reports.
+fn save_screenshot(nes_state: &NesState, rom_path: &str, overscan: Overscan) -> Result<(), String> {
+    let name = Path::new(rom_path)
+        .file_stem()
+        .map_or(String::from("aries"), |stem| {
+            stem.to_string_lossy().into_owned()
+        });
+    let path = format!("{}-{}.png", name, nes_state.ppu.frame.count);
+    let png = nes_state.screenshot(&ScreenshotOptions {
+        overscan,
+        metadata: true,
+    });
+    fs::write(&path, png).map_err(|e| format!("{}: {}", path, e))
+}
@@ -1,7 +1,14 @@
-use nes_sdl::start_nes;
+use std::env;
+
+use nes_sdl::{options::Options, start_nes};
 
 fn main() {
-    start_nes(String::from("assets/helloworld.nes")).unwrap();
+    let (file_path, options) = Options::parse(env::args().skip(1)).unwrap();
+    start_nes(
+        file_path.unwrap_or_else(|| String::from("assets/helloworld.nes")),
+        options,
+    )
+    .unwrap();
 }
 
 #[cfg(test)]
@@ -10,7 +17,11 @@ mod tests {
 
     const PROJECT_ROOT: &str = "../";
     fn start(rel_path: &str) {
-        start_nes(String::from(String::from(PROJECT_ROOT) + rel_path)).unwrap();
+        start_nes(
+            String::from(String::from(PROJECT_ROOT) + rel_path),
+            Options::default(),
+        )
+        .unwrap();
     }
 
     #[test]
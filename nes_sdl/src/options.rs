@@ -0,0 +1,75 @@
+use nes_core::entity::{palette::NtscParams, region::Region};
+
+use crate::adapter_impl::video::VideoOptions;
+
+/// Command line options of the desktop app.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Game Genie or Pro Action Replay codes enabled at startup.
+    pub cheats: Vec<String>,
+    /// Overrides the region detected from the ROM header and file name.
+    pub region: Option<Region>,
+    /// A `.pal` file or the name of a built-in palette.
+    pub palette: Option<String>,
+    /// TV adjustments for the `ntsc` palette and the NTSC filters.
+    pub ntsc_params: NtscParams,
+    /// A file of per-game overscan settings, which win over `--overscan`.
+    pub overscan_overrides: Option<String>,
+    /// Draw every sprite of a line instead of the first 8.
+    pub no_sprite_limit: bool,
+    pub video: VideoOptions,
+}
+
+impl Options {
+    /// Parses `[rom] [--cheat CODE]... [--region ntsc|pal|dendy] [--palette FILE|NAME] [--ntsc-params NAME=VALUE,...] [--scale N]
+    /// [--integer-scale] [--aspect-8-7] [--fullscreen] [--vsync] [--filter NAME,...]
+    /// [--overscan ntsc|none|T,B,L,R] [--overscan-overrides FILE] [--no-sprite-limit]` and returns the ROM path, if given, with the options.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<(Option<String>, Self), String> {
+        let mut file_path = None;
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--cheat" => options
+                    .cheats
+                    .push(args.next().ok_or("--cheat needs a code")?),
+                "--region" => {
+                    options.region = Some(args.next().ok_or("--region needs a name")?.parse()?)
+                }
+                "--palette" => {
+                    options.palette = Some(args.next().ok_or("--palette needs a file or name")?)
+                }
+                "--ntsc-params" => {
+                    options.ntsc_params =
+                        args.next().ok_or("--ntsc-params needs settings")?.parse()?
+                }
+                "--scale" => {
+                    options.video.scale = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|&n| n > 0)
+                        .ok_or("--scale needs a positive number")?
+                }
+                "--integer-scale" => options.video.integer_scale = true,
+                "--aspect-8-7" => options.video.aspect_8_7 = true,
+                "--fullscreen" => options.video.fullscreen = true,
+                "--vsync" => options.video.vsync = true,
+                "--filter" => {
+                    options.video.filter = args.next().ok_or("--filter needs a name")?.parse()?
+                }
+                "--overscan" => {
+                    options.video.overscan =
+                        args.next().ok_or("--overscan needs a setting")?.parse()?
+                }
+                "--overscan-overrides" => {
+                    options.overscan_overrides =
+                        Some(args.next().ok_or("--overscan-overrides needs a file")?)
+                }
+                "--no-sprite-limit" => options.no_sprite_limit = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => file_path = Some(arg),
+            }
+        }
+        options.video.filter.set_ntsc_params(options.ntsc_params);
+        Ok((file_path, options))
+    }
+}
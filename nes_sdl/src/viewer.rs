@@ -0,0 +1,131 @@
+use nes_core::{entity::debug_view::DebugView, usecase::nes::NesState};
+use sdl2::{
+    pixels::PixelFormatEnum,
+    render::{Texture, WindowCanvas},
+    Sdl, VideoSubsystem,
+};
+
+/// PPU debug pictures that open in windows of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viewer {
+    Nametables,
+    PatternTables,
+    Oam,
+    Palette,
+}
+
+impl Viewer {
+    fn title(self) -> &'static str {
+        match self {
+            Viewer::Nametables => "aries - nametables",
+            Viewer::PatternTables => "aries - pattern tables",
+            Viewer::Oam => "aries - OAM",
+            Viewer::Palette => "aries - palette",
+        }
+    }
+
+    /// Initial window size, in multiples of the picture.
+    fn scale(self) -> u32 {
+        match self {
+            Viewer::Nametables => 1,
+            Viewer::PatternTables | Viewer::Oam => 3,
+            Viewer::Palette => 2,
+        }
+    }
+
+    fn render(self, nes: &NesState, pattern_palette: u8) -> DebugView {
+        match self {
+            Viewer::Nametables => nes.nametable_view(),
+            Viewer::PatternTables => nes.pattern_table_view(pattern_palette),
+            Viewer::Oam => nes.oam_view(),
+            Viewer::Palette => nes.palette_view(),
+        }
+    }
+}
+
+struct ViewerWindow {
+    viewer: Viewer,
+    canvas: WindowCanvas,
+    texture: Texture,
+}
+
+/// The open viewer windows, redrawn after every frame.
+pub struct Viewers {
+    video: VideoSubsystem,
+    windows: Vec<ViewerWindow>,
+    /// Palette the pattern tables are drawn with: 0~3 background, 4~7 sprites.
+    pattern_palette: u8,
+}
+
+impl Viewers {
+    pub fn new(sdl: &Sdl) -> Self {
+        Self {
+            video: sdl.video().expect("Could not initalize SDL video context."),
+            windows: Vec::new(),
+            pattern_palette: 0,
+        }
+    }
+
+    /// Opens `viewer`, or closes it when it is open.
+    pub fn toggle(&mut self, viewer: Viewer, nes: &NesState) {
+        if let Some(i) = self.windows.iter().position(|w| w.viewer == viewer) {
+            self.windows.remove(i);
+            return;
+        }
+        let view = viewer.render(nes, self.pattern_palette);
+        let (width, height) = (view.width as u32, view.height as u32);
+        let window = self
+            .video
+            .window(
+                viewer.title(),
+                width * viewer.scale(),
+                height * viewer.scale(),
+            )
+            .resizable()
+            .build()
+            .expect("Could not open a viewer window.");
+        let mut canvas = window
+            .into_canvas()
+            .build()
+            .expect("Could not make a canvas.");
+        canvas
+            .set_logical_size(width, height)
+            .expect("Could not set the logical size.");
+        let texture = canvas
+            .create_texture_streaming(PixelFormatEnum::RGB24, width, height)
+            .expect("Could not make a texture.");
+        self.windows.push(ViewerWindow {
+            viewer,
+            canvas,
+            texture,
+        });
+    }
+
+    /// Closes the viewer shown in window `window_id`. Returns false when it is not a viewer.
+    pub fn close(&mut self, window_id: u32) -> bool {
+        let len = self.windows.len();
+        self.windows.retain(|w| w.canvas.window().id() != window_id);
+        self.windows.len() != len
+    }
+
+    /// Draws the pattern tables with the next of the 8 palettes.
+    pub fn next_palette(&mut self) {
+        self.pattern_palette = (self.pattern_palette + 1) % 8;
+    }
+
+    pub fn draw(&mut self, nes: &NesState) {
+        for window in &mut self.windows {
+            let view = window.viewer.render(nes, self.pattern_palette);
+            window
+                .texture
+                .update(None, &view.data, view.width * 3)
+                .expect("Could not upload the view.");
+            window.canvas.clear();
+            window
+                .canvas
+                .copy(&window.texture, None, None)
+                .expect("Could not draw the view.");
+            window.canvas.present();
+        }
+    }
+}
@@ -1,35 +1,77 @@
-use nes_core::adapter::video::VideoAdapter;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use nes_core::{
+    adapter::video::VideoAdapter,
+    entity::{
+        frame_buffer::{FrameBuffer, PixelFormat},
+        overscan::Overscan,
+        palette::Palette,
+    },
+};
+use nes_filters::{filter::FilterChain, image::Image};
 use wasm_bindgen::Clamped;
 use web_sys::{CanvasRenderingContext2d, ImageData};
 
 pub struct VideoCtx {
     canvas: CanvasRenderingContext2d,
+    /// Shared with `WindowContext::set_filter`.
+    pub filter: Rc<RefCell<FilterChain>>,
+    /// Shared with `WindowContext::set_overscan`.
+    pub overscan: Rc<Cell<Overscan>>,
 }
 
 impl VideoCtx {
     pub fn new(canvas: CanvasRenderingContext2d) -> Self {
-        Self { canvas }
+        Self {
+            canvas,
+            filter: Rc::default(),
+            overscan: Rc::default(),
+        }
     }
-}
 
-impl VideoAdapter for VideoCtx {
-    fn draw_frame(&mut self, pixels: [[u8; 3]; 256 * 240]) {
-        let mut data: [u8; 256 * 240 * 4] = [0; 256 * 240 * 4];
-        for y in 0..240 {
-            for x in 0..256 {
-                let i = y * 256 + x;
-                data[i * 4] = pixels[i][0];
-                data[i * 4 + 1] = pixels[i][1];
-                data[i * 4 + 2] = pixels[i][2];
-                data[i * 4 + 3] = u8::MAX;
-            }
+    fn put(&self, data: &[u8], width: usize, height: usize) {
+        let element = self.canvas.canvas().unwrap();
+        if element.width() != width as u32 || element.height() != height as u32 {
+            element.set_width(width as u32);
+            element.set_height(height as u32);
         }
         self.canvas
             .put_image_data(
-                &ImageData::new_with_u8_clamped_array_and_sh(Clamped(&data), 256, 240).unwrap(),
+                &ImageData::new_with_u8_clamped_array_and_sh(
+                    Clamped(data),
+                    width as u32,
+                    height as u32,
+                )
+                .unwrap(),
                 0.0,
                 0.0,
             )
             .unwrap();
     }
 }
+
+impl VideoAdapter for VideoCtx {
+    /// ImageData takes RGBA, so frames are handed over as they are.
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::Rgba32
+    }
+
+    fn draw_frame(&mut self, frame: &FrameBuffer) {
+        let filter = self.filter.borrow();
+        let overscan = self.overscan.get();
+        if filter.is_empty() && overscan == Overscan::NONE {
+            self.put(frame.data(), 256, 240);
+        } else {
+            let image = if filter.is_empty() {
+                Image::from_frame(frame, &Palette::default())
+            } else {
+                filter.apply_frame(frame, &Palette::default())
+            };
+            let image = image.crop(&overscan);
+            self.put(&image.to_rgba32(), image.width, image.height);
+        }
+    }
+}
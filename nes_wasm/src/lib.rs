@@ -1,43 +1,61 @@
 mod adapter_impl;
 
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
 use adapter_impl::{audio::AudioCtx, cartridge::CartridgeCtx, video::VideoCtx};
-use js_sys::Uint8Array;
-use nes_core::{adapter::nes::NesAdapter, usecase::nes::NesState};
+use js_sys::{Date, Uint8Array};
+use nes_core::{
+    adapter::nes::NesAdapter,
+    entity::{
+        overscan::{Overscan, OverscanOverrides},
+        palette::{NtscParams, Palette, PalettePreset},
+        region::Region,
+    },
+    usecase::{nes::NesState, screenshot::ScreenshotOptions},
+};
+use nes_filters::filter::FilterChain;
 use wasm_bindgen::prelude::*;
 use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement};
 
 #[wasm_bindgen]
 pub struct WindowContext {
     nes_state: Rc<RefCell<NesState>>,
+    filter: Rc<RefCell<FilterChain>>,
+    overscan: Rc<Cell<Overscan>>,
+    ntsc_params: NtscParams,
 }
 
 #[wasm_bindgen]
 impl WindowContext {
     #[wasm_bindgen(constructor)]
     pub fn new(canvas_id: &str, nes_file: Uint8Array) -> WindowContext {
+        let video = VideoCtx::new(
+            window()
+                .unwrap()
+                .document()
+                .unwrap()
+                .get_element_by_id(canvas_id)
+                .unwrap()
+                .dyn_into::<HtmlCanvasElement>()
+                .map_err(|_| ())
+                .unwrap()
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap(),
+        );
+        let filter = video.filter.clone();
+        let overscan = video.overscan.clone();
         let nes_state = Rc::new(RefCell::new(
             NesAdapter {
                 cartridge: Box::new(CartridgeCtx {
                     file_bytes: nes_file.to_vec(),
                 }),
-                video: Box::new(VideoCtx::new(
-                    window()
-                        .unwrap()
-                        .document()
-                        .unwrap()
-                        .get_element_by_id(canvas_id)
-                        .unwrap()
-                        .dyn_into::<HtmlCanvasElement>()
-                        .map_err(|_| ())
-                        .unwrap()
-                        .get_context("2d")
-                        .unwrap()
-                        .unwrap()
-                        .dyn_into::<CanvasRenderingContext2d>()
-                        .unwrap(),
-                )),
+                video: Box::new(video),
                 audio: Box::new(AudioCtx::default()),
             }
             .init(),
@@ -46,6 +64,9 @@ impl WindowContext {
 
         WindowContext {
             nes_state: nes_state,
+            filter,
+            overscan,
+            ntsc_params: NtscParams::default(),
         }
     }
 
@@ -53,8 +74,21 @@ impl WindowContext {
         // We use Rc<RefCell<None>> trick for recursive calling of request_animation_frame.
         let f = Rc::new(RefCell::new(None));
         let g = f.clone();
+        // Run frames at the region's rate whatever the display refresh rate is.
+        let mut last = Date::now();
+        let mut lag = 0.0;
         *g.borrow_mut() = Some(Closure::new(move || {
-            nes_state.as_ref().borrow_mut().run_frame();
+            let now = Date::now();
+            let mut nes_state = nes_state.as_ref().borrow_mut();
+            let frame_ms = 1000.0 / nes_state.region.frame_rate();
+            // drop the backlog after the tab was hidden
+            lag = (lag + now - last).min(frame_ms * 4.0);
+            last = now;
+            while lag >= frame_ms {
+                nes_state.run_frame();
+                lag -= frame_ms;
+            }
+            drop(nes_state);
             Self::request_animation_frame(f.borrow().as_ref().unwrap());
         }));
         Self::request_animation_frame(g.borrow().as_ref().unwrap());
@@ -67,6 +101,118 @@ impl WindowContext {
             .unwrap();
     }
 
+    /// Overrides the region detected from the ROM header: "ntsc", "pal" or "dendy".
+    #[wasm_bindgen]
+    pub fn set_region(&mut self, region: &str) -> Result<(), JsValue> {
+        let region = region.parse::<Region>().map_err(JsValue::from)?;
+        self.nes_state.borrow_mut().set_region(region);
+        Ok(())
+    }
+
+    /// Loads the contents of a 192 or 1536 byte `.pal` file.
+    #[wasm_bindgen]
+    pub fn load_palette(&mut self, pal_file: Uint8Array) -> Result<(), JsValue> {
+        self.nes_state.borrow_mut().ppu.palette =
+            Palette::from_pal(&pal_file.to_vec()).map_err(JsValue::from)?;
+        Ok(())
+    }
+
+    /// Switches to a built-in palette: "aries" or "ntsc".
+    #[wasm_bindgen]
+    pub fn set_palette(&mut self, name: &str) -> Result<(), JsValue> {
+        let preset = name.parse::<PalettePreset>().map_err(JsValue::from)?;
+        self.nes_state.borrow_mut().ppu.palette = match preset {
+            PalettePreset::Ntsc => Palette::from_ntsc(&self.ntsc_params),
+            preset => preset.palette(),
+        };
+        Ok(())
+    }
+
+    /// Sets the TV adjustments of the NTSC filters and of the "ntsc" palette, e.g.
+    /// "hue=15,saturation=1.2". The palette picks them up on the next `set_palette`.
+    #[wasm_bindgen]
+    pub fn set_ntsc_params(&mut self, params: &str) -> Result<(), JsValue> {
+        self.ntsc_params = params.parse().map_err(JsValue::from)?;
+        self.filter.borrow_mut().set_ntsc_params(self.ntsc_params);
+        Ok(())
+    }
+
+    /// Sets the software filters, e.g. "hq2x,scanlines", or "none".
+    #[wasm_bindgen]
+    pub fn set_filter(&mut self, filter: &str) -> Result<(), JsValue> {
+        let mut chain: FilterChain = filter.parse().map_err(JsValue::from)?;
+        chain.set_ntsc_params(self.ntsc_params);
+        *self.filter.borrow_mut() = chain;
+        Ok(())
+    }
+
+    /// Shows or hides the background and the sprites without the game noticing.
+    #[wasm_bindgen]
+    pub fn set_layers(&mut self, background: bool, sprites: bool) {
+        let layers = &mut self.nes_state.borrow_mut().ppu.layers;
+        layers.hide_background = !background;
+        layers.hide_sprites = !sprites;
+    }
+
+    /// Draws every sprite of a line instead of the first 8.
+    #[wasm_bindgen]
+    pub fn set_no_sprite_limit(&mut self, enabled: bool) {
+        self.nes_state.borrow_mut().ppu.layers.no_sprite_limit = enabled;
+    }
+
+    /// Hides the edges of the picture: "ntsc", "none" or "top,bottom,left,right".
+    #[wasm_bindgen]
+    pub fn set_overscan(&mut self, overscan: &str) -> Result<(), JsValue> {
+        self.overscan.set(overscan.parse().map_err(JsValue::from)?);
+        Ok(())
+    }
+
+    /// Applies this game's line of an overscan overrides file (`CRC32 top,bottom,left,right`
+    /// per line). Returns whether the game was listed.
+    #[wasm_bindgen]
+    pub fn load_overscan_overrides(&mut self, overrides: &str) -> Result<bool, JsValue> {
+        let overrides: OverscanOverrides = overrides.parse().map_err(JsValue::from)?;
+        let game = overrides.get(self.nes_state.borrow().cartridge.crc32());
+        if let Some(overscan) = game {
+            self.overscan.set(overscan);
+        }
+        Ok(game.is_some())
+    }
+
+    /// The last frame as PNG bytes, cropped to the overscan when `cropped`. `metadata` adds
+    /// the palette index of every pixel and the emphasis bits.
+    #[wasm_bindgen]
+    pub fn screenshot(&self, cropped: bool, metadata: bool) -> Vec<u8> {
+        let overscan = if cropped {
+            self.overscan.get()
+        } else {
+            Overscan::NONE
+        };
+        self.nes_state
+            .borrow()
+            .screenshot(&ScreenshotOptions { overscan, metadata })
+    }
+
+    /// Enables a Game Genie or Pro Action Replay code.
+    #[wasm_bindgen]
+    pub fn add_cheat(&mut self, code: &str) -> Result<(), JsValue> {
+        self.nes_state
+            .borrow_mut()
+            .add_cheat(code)
+            .map_err(JsValue::from)
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_cheat(&mut self, code: &str) -> bool {
+        self.nes_state.borrow_mut().remove_cheat(code)
+    }
+
+    /// Returns the new state, or `undefined` when the code is not registered.
+    #[wasm_bindgen]
+    pub fn toggle_cheat(&mut self, code: &str) -> Option<bool> {
+        self.nes_state.borrow_mut().toggle_cheat(code)
+    }
+
     #[allow(non_snake_case)]
     #[wasm_bindgen]
     pub fn keydown_A(&mut self) {